@@ -0,0 +1,353 @@
+//! Reading the ABIF (`.ab1`) files produced by capillary sequencers
+//!
+//! Only the tags needed for base calls and chromatograms are decoded: the
+//! analyzed channels `DATA9`-`DATA12`, their base order `FWO_1`, the called
+//! bases `PBAS`, peak locations `PLOC` and quality values `PCON`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+const ELEMENT_CHAR: i16 = 2;
+const ELEMENT_SHORT: i16 = 4;
+const ELEMENT_PSTRING: i16 = 18;
+const ENTRY_SIZE: usize = 28;
+
+/// A decoded Sanger trace
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Trace {
+    /// Bases of the four channels, e.g. `GATC`
+    pub base_order: [u8; 4],
+    /// Signal of each channel, in `base_order` order
+    pub channels: [Vec<i16>; 4],
+    /// Called bases
+    pub bases: Vec<u8>,
+    /// Scan position of each called base in the channels
+    pub peak_locations: Vec<u16>,
+    /// Phred quality of each called base
+    pub qualities: Vec<u8>,
+    pub sample_name: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    element_type: i16,
+    num_elements: usize,
+    data_size: usize,
+    data_offset: usize,
+    /// Offset of the entry itself, for data of 4 bytes or less stored inline
+    entry_offset: usize,
+}
+
+fn be_i16(bytes: &[u8], offset: usize) -> anyhow::Result<i16> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| i16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| anyhow::anyhow!("ABIF data ends at {}", offset))
+}
+
+fn be_i32(bytes: &[u8], offset: usize) -> anyhow::Result<i32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| anyhow::anyhow!("ABIF data ends at {}", offset))
+}
+
+fn read_entry(bytes: &[u8], offset: usize) -> anyhow::Result<((String, i32), Entry)> {
+    let name = bytes
+        .get(offset..offset + 4)
+        .ok_or_else(|| anyhow::anyhow!("ABIF directory ends at {}", offset))?;
+    let name = String::from_utf8_lossy(name).to_string();
+    let number = be_i32(bytes, offset + 4)?;
+    let entry = Entry {
+        element_type: be_i16(bytes, offset + 8)?,
+        num_elements: be_i32(bytes, offset + 12)?.max(0) as usize,
+        data_size: be_i32(bytes, offset + 16)?.max(0) as usize,
+        data_offset: be_i32(bytes, offset + 20)?.max(0) as usize,
+        entry_offset: offset,
+    };
+    Ok(((name, number), entry))
+}
+
+impl Entry {
+    fn data<'a>(&self, bytes: &'a [u8]) -> anyhow::Result<&'a [u8]> {
+        let start = if self.data_size <= 4 {
+            self.entry_offset + 20
+        } else {
+            self.data_offset
+        };
+        bytes
+            .get(start..start + self.data_size)
+            .ok_or_else(|| anyhow::anyhow!("ABIF tag data out of bounds"))
+    }
+}
+
+struct Directory<'a> {
+    bytes: &'a [u8],
+    entries: HashMap<(String, i32), Entry>,
+}
+
+impl Directory<'_> {
+    fn get(&self, name: &str, number: i32) -> Option<&Entry> {
+        self.entries.get(&(name.to_string(), number))
+    }
+
+    fn chars(&self, name: &str, number: i32) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(entry) = self.get(name, number) else {
+            return Ok(None);
+        };
+        let data = entry.data(self.bytes)?;
+        Ok(Some(match entry.element_type {
+            // Pascal strings carry their length in the first byte
+            ELEMENT_PSTRING => data.get(1..).unwrap_or_default().to_vec(),
+            _ => data.to_vec(),
+        }))
+    }
+
+    fn shorts(&self, name: &str, number: i32) -> anyhow::Result<Option<Vec<i16>>> {
+        let Some(entry) = self.get(name, number) else {
+            return Ok(None);
+        };
+        if entry.element_type != ELEMENT_SHORT {
+            anyhow::bail!("ABIF tag {}{} is not a short array", name, number);
+        }
+        let data = entry.data(self.bytes)?;
+        Ok(Some(
+            data.chunks_exact(2)
+                .take(entry.num_elements)
+                .map(|b| i16::from_be_bytes([b[0], b[1]]))
+                .collect(),
+        ))
+    }
+}
+
+impl Trace {
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        if bytes.get(0..4) != Some(b"ABIF") {
+            anyhow::bail!("not an ABIF file");
+        }
+        let (_, root) = read_entry(bytes, 6)?;
+        let mut entries = HashMap::new();
+        for i in 0..root.num_elements {
+            let (key, entry) = read_entry(bytes, root.data_offset + i * ENTRY_SIZE)?;
+            entries.insert(key, entry);
+        }
+        let dir = Directory { bytes, entries };
+
+        let base_order: [u8; 4] = dir
+            .chars("FWO_", 1)?
+            .and_then(|order| order.get(..4).and_then(|o| o.try_into().ok()))
+            .unwrap_or(*b"GATC");
+        let mut channels: [Vec<i16>; 4] = Default::default();
+        for (i, channel) in channels.iter_mut().enumerate() {
+            *channel = dir.shorts("DATA", 9 + i as i32)?.unwrap_or_default();
+        }
+        // Tag number 2 holds the bases as called, 1 as edited by the user
+        let bases = match dir.chars("PBAS", 2)? {
+            Some(bases) => bases,
+            None => dir.chars("PBAS", 1)?.unwrap_or_default(),
+        };
+        let peak_locations = match dir.shorts("PLOC", 2)? {
+            Some(peaks) => peaks,
+            None => dir.shorts("PLOC", 1)?.unwrap_or_default(),
+        };
+        let qualities = match dir.chars("PCON", 2)? {
+            Some(qualities) => qualities,
+            None => dir.chars("PCON", 1)?.unwrap_or_default(),
+        };
+        let sample_name = dir
+            .chars("SMPL", 1)?
+            .map(|name| String::from_utf8_lossy(&name).to_string());
+        Ok(Self {
+            base_order,
+            channels,
+            bases,
+            peak_locations: peak_locations.into_iter().map(|p| p as u16).collect(),
+            qualities,
+            sample_name,
+        })
+    }
+
+    /// The signal of the channel recording `base`
+    pub fn channel(&self, base: u8) -> Option<&[i16]> {
+        let index = self
+            .base_order
+            .iter()
+            .position(|b| b.eq_ignore_ascii_case(&base))?;
+        Some(&self.channels[index])
+    }
+
+    /// Number of scan points in the channels
+    pub fn scan_count(&self) -> usize {
+        self.channels.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Called bases as a string
+    pub fn sequence(&self) -> String {
+        String::from_utf8_lossy(&self.bases).to_string()
+    }
+
+    /// Average Phred quality of the base calls, `None` without quality values
+    pub fn mean_quality(&self) -> Option<f64> {
+        if self.qualities.is_empty() {
+            return None;
+        }
+        let total: u64 = self.qualities.iter().map(|&q| q as u64).sum();
+        Some(total as f64 / self.qualities.len() as f64)
+    }
+
+    /// Encode the tags known to this module as a minimal ABIF file
+    ///
+    /// Useful for producing test traces; vendor files carry many more tags.
+    pub fn to_abif_bytes(&self) -> Vec<u8> {
+        let shorts =
+            |values: &[i16]| -> Vec<u8> { values.iter().flat_map(|v| v.to_be_bytes()).collect() };
+        let peaks: Vec<i16> = self.peak_locations.iter().map(|&p| p as i16).collect();
+        // Name, number, element type, element size, element count and data
+        type Tag<'a> = (&'a [u8; 4], i32, i16, i16, usize, Vec<u8>);
+        let mut tags: Vec<Tag> = Vec::new();
+        for (i, channel) in self.channels.iter().enumerate() {
+            tags.push((
+                b"DATA",
+                9 + i as i32,
+                ELEMENT_SHORT,
+                2,
+                channel.len(),
+                shorts(channel),
+            ));
+        }
+        tags.push((b"FWO_", 1, ELEMENT_CHAR, 1, 4, self.base_order.to_vec()));
+        tags.push((
+            b"PBAS",
+            2,
+            ELEMENT_CHAR,
+            1,
+            self.bases.len(),
+            self.bases.clone(),
+        ));
+        tags.push((b"PLOC", 2, ELEMENT_SHORT, 2, peaks.len(), shorts(&peaks)));
+        tags.push((
+            b"PCON",
+            2,
+            ELEMENT_CHAR,
+            1,
+            self.qualities.len(),
+            self.qualities.clone(),
+        ));
+        if let Some(name) = &self.sample_name {
+            let mut data = vec![name.len().min(255) as u8];
+            data.extend(name.bytes().take(255));
+            tags.push((b"SMPL", 1, ELEMENT_PSTRING, 1, data.len(), data));
+        }
+
+        let mut bytes = vec![0u8; 128];
+        let mut directory = Vec::new();
+        for (name, number, element_type, element_size, num_elements, data) in tags {
+            directory.extend_from_slice(name);
+            directory.extend(number.to_be_bytes());
+            directory.extend(element_type.to_be_bytes());
+            directory.extend(element_size.to_be_bytes());
+            directory.extend((num_elements as i32).to_be_bytes());
+            directory.extend((data.len() as i32).to_be_bytes());
+            if data.len() <= 4 {
+                let mut inline = [0u8; 4];
+                inline[..data.len()].copy_from_slice(&data);
+                directory.extend(inline);
+            } else {
+                directory.extend((bytes.len() as i32).to_be_bytes());
+                bytes.extend(data);
+            }
+            directory.extend(0i32.to_be_bytes());
+        }
+        let directory_offset = bytes.len();
+        let entry_count = directory.len() / ENTRY_SIZE;
+        bytes.extend(directory);
+
+        bytes[0..4].copy_from_slice(b"ABIF");
+        bytes[4..6].copy_from_slice(&101i16.to_be_bytes());
+        bytes[6..10].copy_from_slice(b"tdir");
+        bytes[10..14].copy_from_slice(&1i32.to_be_bytes());
+        bytes[14..16].copy_from_slice(&1023i16.to_be_bytes());
+        bytes[16..18].copy_from_slice(&(ENTRY_SIZE as i16).to_be_bytes());
+        bytes[18..22].copy_from_slice(&(entry_count as i32).to_be_bytes());
+        bytes[22..26].copy_from_slice(&((entry_count * ENTRY_SIZE) as i32).to_be_bytes());
+        bytes[26..30].copy_from_slice(&(directory_offset as i32).to_be_bytes());
+        bytes
+    }
+
+    /// An idealized trace for `sequence`: one Gaussian peak per base, spaced
+    /// `spacing` scans apart, with the given quality for every base
+    pub fn synthetic(sequence: &str, spacing: usize, quality: u8) -> Self {
+        let base_order = *b"GATC";
+        let scan_count = (sequence.len() + 1) * spacing;
+        let mut channels: [Vec<i16>; 4] = Default::default();
+        for channel in channels.iter_mut() {
+            *channel = vec![0; scan_count];
+        }
+        let mut peak_locations = Vec::with_capacity(sequence.len());
+        let sigma = spacing as f64 / 5.0;
+        for (i, base) in sequence.bytes().enumerate() {
+            let center = (i + 1) * spacing;
+            peak_locations.push(center as u16);
+            let Some(index) = base_order
+                .iter()
+                .position(|b| b.eq_ignore_ascii_case(&base))
+            else {
+                continue;
+            };
+            let from = center.saturating_sub(spacing);
+            let to = (center + spacing).min(scan_count);
+            for (scan, value) in channels[index][from..to].iter_mut().enumerate() {
+                let offset = ((from + scan) as f64 - center as f64) / sigma;
+                let height = 1000.0 * (-0.5 * offset * offset).exp();
+                *value = (*value as f64 + height).min(i16::MAX as f64) as i16;
+            }
+        }
+        Self {
+            base_order,
+            channels,
+            bases: sequence.as_bytes().to_vec(),
+            peak_locations,
+            qualities: vec![quality; sequence.len()],
+            sample_name: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut trace = Trace::synthetic("ACGTTGCA", 12, 40);
+        trace.sample_name = Some("K528-1".to_string());
+        let decoded = Trace::from_bytes(&trace.to_abif_bytes()).unwrap();
+        assert_eq!(decoded, trace);
+        assert_eq!(decoded.sequence(), "ACGTTGCA");
+        assert_eq!(decoded.scan_count(), 108);
+    }
+
+    #[test]
+    fn test_synthetic_peaks_on_called_base() {
+        let trace = Trace::synthetic("ACGT", 10, 30);
+        for (base, &peak) in trace.bases.iter().zip(&trace.peak_locations) {
+            let called = trace.channel(*base).unwrap()[peak as usize];
+            for other in trace.base_order {
+                if other != *base {
+                    assert!(trace.channel(other).unwrap()[peak as usize] < called);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rejects_non_abif() {
+        let bytes = std::fs::read("fixtures/ruibio/K528-1.C1.34781340.B08.ab1").unwrap();
+        assert!(Trace::from_bytes(&bytes).is_err());
+        assert!(Trace::from_bytes(b"ABIF").is_err());
+    }
+}
@@ -0,0 +1,443 @@
+//! Local alignment of base calls against a reference sequence
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::abif::Trace;
+use crate::reference::ReferenceLibrary;
+
+const MATCH: i32 = 2;
+const MISMATCH: i32 = -3;
+const GAP: i32 = -5;
+
+/// Read × reference cells above which the alignment is banded around a seed diagonal
+const FULL_ALIGNMENT_CELLS: usize = 2_000_000;
+/// Length of the exact matches seeding a banded alignment
+const SEED_LENGTH: usize = 12;
+/// Diagonals on either side of the seed covered by a banded alignment
+const BAND_WIDTH: usize = 64;
+
+/// Orientation of a read relative to its reference
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
+impl std::fmt::Display for Strand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Strand::Forward => write!(f, "+"),
+            Strand::Reverse => write!(f, "-"),
+        }
+    }
+}
+
+/// One column of an alignment
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AlignOp {
+    Match,
+    Mismatch,
+    /// An ambiguity code, such as `N` or `R`, compatible with the other base
+    Ambiguous,
+    /// A read base missing from the reference
+    Insertion,
+    /// A reference base missing from the read
+    Deletion,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct Alignment {
+    pub strand: Strand,
+    pub score: i32,
+    /// Aligned region of the reference, 0-based and end-exclusive
+    pub reference_start: usize,
+    pub reference_end: usize,
+    /// Aligned region of the read, counted on the strand that aligned
+    pub read_start: usize,
+    pub read_end: usize,
+    pub matches: usize,
+    pub mismatches: usize,
+    /// Ambiguity codes compatible with the other base, counted as neither match nor mismatch
+    pub ambiguous: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    /// Columns from `reference_start` / `read_start` onwards
+    #[serde(skip)]
+    pub ops: Vec<AlignOp>,
+}
+
+impl Alignment {
+    /// Fraction of alignment columns that are matches
+    pub fn identity(&self) -> f64 {
+        let columns = self.matches + self.mismatches + self.insertions + self.deletions;
+        if columns == 0 {
+            return 0.0;
+        }
+        self.matches as f64 / columns as f64
+    }
+
+    /// One-line summary such as `99.6% (+) 2 mm, 1 indel, ref 35..980`
+    pub fn summary(&self) -> String {
+        format!(
+            "{:.1}% ({}) {} mm, {} indel, ref {}..{}",
+            self.identity() * 100.0,
+            self.strand,
+            self.mismatches,
+            self.insertions + self.deletions,
+            self.reference_start + 1,
+            self.reference_end
+        )
+    }
+
+    /// Base call indices of mismatches and indels in a read of `read_len` bases
+    ///
+    /// Ambiguity codes compatible with the reference are not differences.
+    /// Indices count on the read as sequenced, also for reverse alignments.
+    /// A deletion is placed at the read base following it.
+    pub fn differences(&self, read_len: usize) -> Vec<usize> {
+        let mut positions = Vec::new();
+        let mut i = self.read_start;
+        for op in &self.ops {
+            match op {
+                // Ambiguity codes are uncertain calls, not differences
+                AlignOp::Match | AlignOp::Ambiguous => i += 1,
+                AlignOp::Mismatch | AlignOp::Insertion => {
+                    positions.push(i);
+                    i += 1;
+                }
+                AlignOp::Deletion => positions.push(i),
+            }
+        }
+        if self.strand == Strand::Reverse {
+            positions = positions
+                .into_iter()
+                .rev()
+                .map(|i| read_len.saturating_sub(i + 1))
+                .collect();
+        }
+        positions
+    }
+}
+
+pub fn complement(base: u8) -> u8 {
+    match base.to_ascii_uppercase() {
+        b'A' => b'T',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'T' => b'A',
+        b'R' => b'Y',
+        b'Y' => b'R',
+        b'K' => b'M',
+        b'M' => b'K',
+        b'B' => b'V',
+        b'V' => b'B',
+        b'D' => b'H',
+        b'H' => b'D',
+        other => other,
+    }
+}
+
+pub fn reverse_complement(sequence: &[u8]) -> Vec<u8> {
+    sequence.iter().rev().map(|&b| complement(b)).collect()
+}
+
+/// The bases an IUPAC code stands for, one bit each for A, C, G and T
+fn iupac_bases(base: u8) -> u8 {
+    match base.to_ascii_uppercase() {
+        b'A' => 0b0001,
+        b'C' => 0b0010,
+        b'G' => 0b0100,
+        b'T' | b'U' => 0b1000,
+        b'R' => 0b0101,
+        b'Y' => 0b1010,
+        b'S' => 0b0110,
+        b'W' => 0b1001,
+        b'K' => 0b1100,
+        b'M' => 0b0011,
+        b'B' => 0b1110,
+        b'D' => 0b1101,
+        b'H' => 0b1011,
+        b'V' => 0b0111,
+        b'N' => 0b1111,
+        _ => 0,
+    }
+}
+
+fn compare(read: u8, reference: u8) -> AlignOp {
+    let (read, reference) = (iupac_bases(read), iupac_bases(reference));
+    if read & reference == 0 {
+        AlignOp::Mismatch
+    } else if read == reference && read.count_ones() == 1 {
+        AlignOp::Match
+    } else {
+        AlignOp::Ambiguous
+    }
+}
+
+fn score(read: u8, reference: u8) -> i32 {
+    match compare(read, reference) {
+        AlignOp::Match => MATCH,
+        // Ambiguous calls neither support nor contradict the reference
+        AlignOp::Ambiguous => 0,
+        _ => MISMATCH,
+    }
+}
+
+/// Align `read` to `reference` on both strands, keeping the better alignment
+///
+/// Returns `None` when neither strand has a positive scoring local alignment.
+///
+/// Long reads against long references are aligned in a band around the
+/// diagonal with the most exact [`SEED_LENGTH`]-base matches.
+pub fn align(read: &[u8], reference: &[u8]) -> Option<Alignment> {
+    let align_strand = |read: &[u8], strand| {
+        if read.len() * reference.len() <= FULL_ALIGNMENT_CELLS {
+            return local_align(read, reference, strand);
+        }
+        let diagonal = seed_diagonal(read, reference)?;
+        banded_align(read, reference, strand, Some((diagonal, BAND_WIDTH)))
+    };
+    let forward = align_strand(read, Strand::Forward);
+    let reverse = align_strand(&reverse_complement(read), Strand::Reverse);
+    match (forward, reverse) {
+        (Some(f), Some(r)) => Some(if r.score > f.score { r } else { f }),
+        (f, r) => f.or(r),
+    }
+}
+
+/// Align the base calls of the trace at `path` to the reference of `template_name`
+pub fn align_trace(
+    path: &Path,
+    template_name: &str,
+    library: &ReferenceLibrary,
+) -> anyhow::Result<Option<Alignment>> {
+    let reference = library
+        .get(template_name)
+        .ok_or_else(|| anyhow::anyhow!("no reference for {}", template_name))?;
+    let trace = Trace::from_file(path)?;
+    Ok(align(&trace.bases, &reference.sequence))
+}
+
+const STOP: u8 = 0;
+const DIAGONAL: u8 = 1;
+const UP: u8 = 2;
+const LEFT: u8 = 3;
+
+/// The reference offset (`reference index - read index`) shared by most exact seed matches
+fn seed_diagonal(read: &[u8], reference: &[u8]) -> Option<isize> {
+    let mut seeds: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for (j, kmer) in reference.windows(SEED_LENGTH).enumerate() {
+        seeds.entry(kmer).or_default().push(j);
+    }
+    let mut votes: HashMap<isize, usize> = HashMap::new();
+    for (i, kmer) in read.windows(SEED_LENGTH).enumerate() {
+        if !kmer.iter().all(|base| b"ACGT".contains(base)) {
+            continue;
+        }
+        for &j in seeds.get(kmer).into_iter().flatten() {
+            *votes.entry(j as isize - i as isize).or_default() += 1;
+        }
+    }
+    votes
+        .into_iter()
+        .max_by_key(|&(diagonal, count)| (count, std::cmp::Reverse(diagonal)))
+        .map(|(diagonal, _)| diagonal)
+}
+
+/// Smith-Waterman alignment with linear gap penalties
+pub fn local_align(read: &[u8], reference: &[u8], strand: Strand) -> Option<Alignment> {
+    banded_align(read, reference, strand, None)
+}
+
+/// Smith-Waterman alignment, limited to `(diagonal, width)` cells around a
+/// reference offset when a band is given
+fn banded_align(
+    read: &[u8],
+    reference: &[u8],
+    strand: Strand,
+    band: Option<(isize, usize)>,
+) -> Option<Alignment> {
+    let width = reference.len() + 1;
+    // The traceback keeps only the band of each row, starting at `row_start`
+    let row_width = match band {
+        Some((_, band_width)) => (2 * band_width + 1).min(width),
+        None => width,
+    };
+    let mut trace = vec![STOP; (read.len() + 1) * row_width];
+    let mut row_start = vec![0; read.len() + 1];
+    let direction_at =
+        |trace: &[u8], row_start: &[usize], i: usize, j: usize| match j.checked_sub(row_start[i]) {
+            Some(offset) if offset < row_width => trace[i * row_width + offset],
+            _ => STOP,
+        };
+    let mut previous = vec![0i32; width];
+    let mut current = vec![0i32; width];
+    let (mut best, mut best_i, mut best_j) = (0, 0, 0);
+    for i in 1..=read.len() {
+        let (first, last) = match band {
+            Some((diagonal, band_width)) => {
+                let center = i as isize + diagonal;
+                let first = (center - band_width as isize).max(1);
+                let last = (center + band_width as isize).min(reference.len() as isize);
+                if first > last {
+                    current.fill(0);
+                    std::mem::swap(&mut previous, &mut current);
+                    continue;
+                }
+                (first as usize, last as usize)
+            }
+            None => (1, reference.len()),
+        };
+        if band.is_some() {
+            row_start[i] = first;
+        }
+        // Cells left of the band hold values of earlier rows
+        current[first - 1] = 0;
+        for j in first..=last {
+            let diagonal = previous[j - 1] + score(read[i - 1], reference[j - 1]);
+            let up = previous[j] + GAP;
+            let left = current[j - 1] + GAP;
+            let (value, direction) = [(diagonal, DIAGONAL), (up, UP), (left, LEFT)]
+                .into_iter()
+                .fold(
+                    (0, STOP),
+                    |acc, cell| if cell.0 > acc.0 { cell } else { acc },
+                );
+            current[j] = value;
+            trace[i * row_width + j - row_start[i]] = direction;
+            if value > best {
+                (best, best_i, best_j) = (value, i, j);
+            }
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    if best <= 0 {
+        return None;
+    }
+
+    let (mut i, mut j) = (best_i, best_j);
+    let mut ops = Vec::new();
+    loop {
+        match direction_at(&trace, &row_start, i, j) {
+            DIAGONAL => {
+                ops.push(compare(read[i - 1], reference[j - 1]));
+                i -= 1;
+                j -= 1;
+            }
+            UP => {
+                ops.push(AlignOp::Insertion);
+                i -= 1;
+            }
+            LEFT => {
+                ops.push(AlignOp::Deletion);
+                j -= 1;
+            }
+            _ => break,
+        }
+    }
+    ops.reverse();
+    let count = |op: AlignOp| ops.iter().filter(|&&o| o == op).count();
+    Some(Alignment {
+        strand,
+        score: best,
+        reference_start: j,
+        reference_end: best_j,
+        read_start: i,
+        read_end: best_i,
+        matches: count(AlignOp::Match),
+        mismatches: count(AlignOp::Mismatch),
+        ambiguous: count(AlignOp::Ambiguous),
+        insertions: count(AlignOp::Insertion),
+        deletions: count(AlignOp::Deletion),
+        ops,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REFERENCE: &[u8] =
+        b"TTTTTGACCTGAGCGTACGATCGGATCCAAGCTTGCATGCCTGCAGGTCGACTCTAGAGGATCTTTTT";
+
+    #[test]
+    fn test_align_forward_with_variants() {
+        // Reference 6..61 with one substitution, one inserted and one deleted base
+        let read = b"NNACCTGAGCGTACGATCGGTTCCAAGCTTTGCATGCCTGCAGGTCGCTCTAGAGGANN";
+        let alignment = align(read, REFERENCE).unwrap();
+        assert_eq!(alignment.strand, Strand::Forward);
+        assert_eq!(alignment.mismatches, 1);
+        assert_eq!(alignment.insertions, 1);
+        assert_eq!(alignment.deletions, 1);
+        assert_eq!(alignment.reference_start, 6);
+        assert_eq!(alignment.read_start, 2);
+        assert!(alignment.identity() > 0.9);
+        let differences = alignment.differences(read.len());
+        assert_eq!(differences.len(), 3);
+        assert_eq!(differences[0], 20);
+    }
+
+    #[test]
+    fn test_align_reverse_read() {
+        let read = reverse_complement(&REFERENCE[10..50]);
+        let alignment = align(&read, REFERENCE).unwrap();
+        assert_eq!(alignment.strand, Strand::Reverse);
+        assert_eq!(alignment.identity(), 1.0);
+        assert_eq!(
+            (alignment.reference_start, alignment.reference_end),
+            (10, 50)
+        );
+        assert_eq!(alignment.summary(), "100.0% (-) 0 mm, 0 indel, ref 11..50");
+        let mut read = read;
+        read[5] = b'N';
+        let alignment = align(&read, REFERENCE).unwrap();
+        assert!(alignment.differences(read.len()).is_empty());
+        read[6] = complement(read[6]);
+        let alignment = align(&read, REFERENCE).unwrap();
+        assert_eq!(alignment.differences(read.len()), vec![6]);
+    }
+
+    #[test]
+    fn test_ambiguity_codes() {
+        let read = b"GACCTGAGCGTRCGATCGGATCCNAGCTTGCATGCC";
+        let alignment = align(read, REFERENCE).unwrap();
+        assert_eq!((alignment.mismatches, alignment.ambiguous), (0, 2));
+        assert_eq!(alignment.identity(), 1.0);
+        // An R (A or G) where the reference has a C is still a mismatch
+        let read = b"GACCTGAGCGTRCGATCGGATCCAAGCTTGCATGCC";
+        let reference = b"TTTTTGACCTGAGCGTCCGATCGGATCCAAGCTTGCATGCCTTTTT";
+        assert_eq!(align(read, reference).unwrap().mismatches, 1);
+    }
+
+    #[test]
+    fn test_banded_alignment_of_long_reference() {
+        let mut state = 12345u32;
+        let reference: Vec<u8> = (0..10_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                b"ACGT"[(state >> 16) as usize % 4]
+            })
+            .collect();
+        let mut read = reference[6_000..6_300].to_vec();
+        read[100] = if read[100] == b'A' { b'C' } else { b'A' };
+        read.remove(200);
+        let banded = align(&read, &reference).unwrap();
+        assert_eq!(
+            banded,
+            local_align(&read, &reference, Strand::Forward).unwrap()
+        );
+        assert_eq!(
+            (banded.reference_start, banded.mismatches, banded.deletions),
+            (6_000, 1, 1)
+        );
+    }
+
+    #[test]
+    fn test_unrelated_read_does_not_align() {
+        assert_eq!(align(b"NNNNNNNN", REFERENCE), None);
+        assert_eq!(reverse_complement(b"ACGTRN"), b"NYACGT");
+    }
+}
@@ -0,0 +1,530 @@
+//! Quality-weighted consensus of the reads of one template
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::abif::Trace;
+use crate::align::{self, AlignOp, Strand};
+
+/// Phred quality below which read ends are trimmed
+pub const TRIM_QUALITY: u8 = 20;
+/// Reads need this identity to the contig to be merged into it
+pub const MIN_IDENTITY: f64 = 0.8;
+/// Reads need this many aligned columns to be merged into the contig
+pub const MIN_OVERLAP: usize = 20;
+/// A column is a conflict when a second call has this fraction of the winning weight
+pub const CONFLICT_FRACTION: f64 = 0.3;
+
+const SYMBOLS: [u8; 5] = *b"ACGT-";
+const GAP: usize = 4;
+
+/// Base calls with their Phred qualities
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Read {
+    pub name: String,
+    pub bases: Vec<u8>,
+    pub qualities: Vec<u8>,
+}
+
+impl Read {
+    pub fn from_trace(name: &str, trace: &Trace) -> Self {
+        let mut qualities = trace.qualities.clone();
+        // Traces without quality values count every call as barely trusted
+        qualities.resize(trace.bases.len(), 1);
+        Self {
+            name: name.to_string(),
+            bases: trace.bases.to_ascii_uppercase(),
+            qualities,
+        }
+    }
+
+    /// The read without its low quality ends
+    pub fn trimmed(&self, cutoff: u8) -> Self {
+        let range = quality_trim(&self.qualities, cutoff);
+        Self {
+            name: self.name.clone(),
+            bases: self.bases[range.clone()].to_vec(),
+            qualities: self.qualities[range].to_vec(),
+        }
+    }
+
+    fn oriented(&self, strand: Strand) -> (Vec<u8>, Vec<u8>) {
+        match strand {
+            Strand::Forward => (self.bases.clone(), self.qualities.clone()),
+            Strand::Reverse => (
+                align::reverse_complement(&self.bases),
+                self.qualities.iter().rev().copied().collect(),
+            ),
+        }
+    }
+}
+
+/// Mott's trimming: the stretch with the highest sum of `quality - cutoff`
+pub fn quality_trim(qualities: &[u8], cutoff: u8) -> Range<usize> {
+    let (mut best, mut best_range) = (0i64, 0..0);
+    let (mut sum, mut start) = (0i64, 0);
+    for (i, &quality) in qualities.iter().enumerate() {
+        sum += quality as i64 - cutoff as i64;
+        if sum <= 0 {
+            (sum, start) = (0, i + 1);
+        } else if sum > best {
+            (best, best_range) = (sum, start..i + 1);
+        }
+    }
+    best_range
+}
+
+/// Summed quality of every call in one contig column, indexed like `SYMBOLS`
+#[derive(Clone, Copy, Default, Debug)]
+struct Column([u32; 5]);
+
+impl Column {
+    fn vote(&mut self, base: u8, quality: u8) {
+        if let Some(index) = SYMBOLS.iter().position(|&s| s == base) {
+            // Zero quality calls still count a little so every call is seen
+            self.0[index] += quality.max(1) as u32;
+        }
+    }
+
+    fn total(&self) -> u32 {
+        self.0.iter().sum()
+    }
+
+    /// Winning symbol, `N` if nothing was called
+    fn consensus(&self) -> u8 {
+        let (index, weight) = self
+            .0
+            .iter()
+            .enumerate()
+            .max_by_key(|&(i, w)| (*w, std::cmp::Reverse(i)))
+            .unwrap_or((0, &0));
+        if *weight == 0 { b'N' } else { SYMBOLS[index] }
+    }
+}
+
+/// Where a read went in the contig
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct PlacedRead {
+    pub name: String,
+    /// `None` for reads that overlap nothing else
+    pub strand: Option<Strand>,
+}
+
+/// A consensus column where reads disagree
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct Conflict {
+    /// 1-based position in the consensus; for a dropped gap column, the next base
+    pub position: usize,
+    /// Winning call, `-` for a gap
+    pub consensus: char,
+    /// Summed quality per call, strongest first
+    pub calls: Vec<(char, u32)>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct Contig {
+    pub name: String,
+    pub sequence: String,
+    pub reads: Vec<PlacedRead>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Contig {
+    pub fn to_fasta(&self) -> String {
+        let placed = self.reads.iter().filter(|r| r.strand.is_some()).count();
+        let mut fasta = format!(
+            ">{} reads={} conflicts={}\n",
+            self.name,
+            placed,
+            self.conflicts.len()
+        );
+        for line in self.sequence.as_bytes().chunks(70) {
+            fasta.push_str(&String::from_utf8_lossy(line));
+            fasta.push('\n');
+        }
+        fasta
+    }
+
+    /// `{name}.consensus.fasta` in `dir`
+    pub fn fasta_path(&self, dir: &Path) -> PathBuf {
+        dir.join(format!("{}.consensus.fasta", self.name))
+    }
+
+    /// Write the consensus into `dir`, failing if the file exists unless `overwrite` is set
+    pub fn write_fasta(&self, dir: &Path, overwrite: bool) -> anyhow::Result<PathBuf> {
+        let path = self.fasta_path(dir);
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .create_new(!overwrite)
+            .open(&path)
+            .map_err(|err| match err.kind() {
+                std::io::ErrorKind::AlreadyExists => {
+                    anyhow::anyhow!("{} already exists", path.display())
+                }
+                _ => anyhow::anyhow!("{}: {}", path.display(), err),
+            })?;
+        file.write_all(self.to_fasta().as_bytes())?;
+        Ok(path)
+    }
+}
+
+#[derive(Default)]
+struct Pileup {
+    columns: Vec<Column>,
+}
+
+impl Pileup {
+    /// Merge a read, returning the strand it aligned on
+    fn add(&mut self, read: &Read) -> Option<Strand> {
+        if self.columns.is_empty() {
+            self.columns = read
+                .bases
+                .iter()
+                .zip(&read.qualities)
+                .map(|(&base, &quality)| {
+                    let mut column = Column::default();
+                    column.vote(base, quality);
+                    column
+                })
+                .collect();
+            return Some(Strand::Forward);
+        }
+        // Gap columns are left out of the sequence the read is aligned to
+        let visible: Vec<usize> = (0..self.columns.len())
+            .filter(|&c| self.columns[c].consensus() != b'-')
+            .collect();
+        let sequence: Vec<u8> = visible
+            .iter()
+            .map(|&c| self.columns[c].consensus())
+            .collect();
+        let alignment = align::align(&read.bases, &sequence)?;
+        if alignment.identity() < MIN_IDENTITY || alignment.ops.len() < MIN_OVERLAP {
+            return None;
+        }
+        let (bases, qualities) = read.oriented(alignment.strand);
+
+        let mut votes: Vec<Vec<(u8, u8)>> = vec![Vec::new(); self.columns.len()];
+        let mut inserted: HashMap<usize, Vec<(u8, u8)>> = HashMap::new();
+        let (mut i, mut j) = (alignment.read_start, alignment.reference_start);
+        for op in &alignment.ops {
+            match op {
+                AlignOp::Match | AlignOp::Mismatch | AlignOp::Ambiguous => {
+                    votes[visible[j]].push((bases[i], qualities[i]));
+                    i += 1;
+                    j += 1;
+                }
+                AlignOp::Insertion => {
+                    inserted
+                        .entry(visible[j])
+                        .or_default()
+                        .push((bases[i], qualities[i]));
+                    i += 1;
+                }
+                AlignOp::Deletion => {
+                    // A missing base is as trustworthy as the calls around it
+                    let quality = qualities[i.saturating_sub(1)].min(qualities[i]);
+                    votes[visible[j]].push((b'-', quality));
+                    j += 1;
+                }
+            }
+        }
+
+        let mut columns = Vec::with_capacity(self.columns.len() + bases.len());
+        // Read ends hanging over the contig ends extend it
+        let new_column = |(&base, &quality): (&u8, &u8)| {
+            let mut column = Column::default();
+            column.vote(base, quality);
+            column
+        };
+        if alignment.reference_start == 0 {
+            let prefix = &bases[..alignment.read_start];
+            columns.extend(
+                prefix
+                    .iter()
+                    .zip(&qualities[..alignment.read_start])
+                    .map(new_column),
+            );
+        }
+        for (c, mut column) in self.columns.iter().copied().enumerate() {
+            for &(base, quality) in inserted.get(&c).into_iter().flatten() {
+                // Reads already spanning this point had no base here
+                let mut extra = Column::default();
+                extra.0[GAP] = column.total();
+                extra.vote(base, quality);
+                columns.push(extra);
+            }
+            for &(base, quality) in &votes[c] {
+                column.vote(base, quality);
+            }
+            columns.push(column);
+        }
+        if alignment.reference_end == sequence.len() {
+            let suffix = &bases[alignment.read_end..];
+            columns.extend(
+                suffix
+                    .iter()
+                    .zip(&qualities[alignment.read_end..])
+                    .map(new_column),
+            );
+        }
+        self.columns = columns;
+        Some(alignment.strand)
+    }
+}
+
+/// Merge reads into a consensus, seeded with the longest read
+///
+/// Reads are trimmed with [`TRIM_QUALITY`] first. Reads that overlap no
+/// other read are listed without a strand and left out of the consensus.
+pub fn assemble(name: &str, reads: &[Read]) -> Contig {
+    let mut pending: Vec<Read> = reads.iter().map(|r| r.trimmed(TRIM_QUALITY)).collect();
+    pending.sort_by_key(|r| std::cmp::Reverse(r.bases.len()));
+    let mut pileup = Pileup::default();
+    let mut strands: HashMap<String, Strand> = HashMap::new();
+    // Primer-walking reads may only overlap the contig once their neighbours are in
+    loop {
+        let before = pending.len();
+        pending.retain(|read| match pileup.add(read) {
+            Some(strand) => {
+                strands.insert(read.name.clone(), strand);
+                false
+            }
+            None => true,
+        });
+        if pending.is_empty() || pending.len() == before {
+            break;
+        }
+    }
+
+    let mut sequence = String::new();
+    let mut conflicts = Vec::new();
+    for column in &pileup.columns {
+        let consensus = column.consensus();
+        let mut calls: Vec<(char, u32)> = SYMBOLS
+            .iter()
+            .zip(column.0)
+            .filter(|&(_, weight)| weight > 0)
+            .map(|(&symbol, weight)| (symbol as char, weight))
+            .collect();
+        calls.sort_by_key(|&(_, weight)| std::cmp::Reverse(weight));
+        if let [(_, best), (_, second), ..] = calls[..]
+            && second as f64 >= best as f64 * CONFLICT_FRACTION
+            && second >= TRIM_QUALITY as u32
+        {
+            conflicts.push(Conflict {
+                position: sequence.len() + 1,
+                consensus: consensus as char,
+                calls,
+            });
+        }
+        if consensus != b'-' {
+            sequence.push(consensus as char);
+        }
+    }
+    Contig {
+        name: name.to_string(),
+        sequence,
+        reads: reads
+            .iter()
+            .map(|read| PlacedRead {
+                name: read.name.clone(),
+                strand: strands.get(&read.name).copied(),
+            })
+            .collect(),
+        conflicts,
+    }
+}
+
+/// The outcome of assembling the traces of one template
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct Assembly {
+    /// `None` if no trace could be read
+    pub contig: Option<Contig>,
+    /// Traces that could not be read, with the reason
+    pub unreadable: Vec<(PathBuf, String)>,
+}
+
+/// Read the traces at `paths` and assemble them into a contig called `name`
+pub fn assemble_traces(name: &str, paths: &[PathBuf]) -> Assembly {
+    let mut reads = Vec::new();
+    let mut unreadable = Vec::new();
+    for path in paths {
+        match Trace::from_file(path) {
+            Ok(trace) => {
+                let read_name = path.file_name().unwrap_or_default().to_string_lossy();
+                reads.push(Read::from_trace(&read_name, &trace));
+            }
+            Err(err) => unreadable.push((path.clone(), err.to_string())),
+        }
+    }
+    Assembly {
+        contig: (!reads.is_empty()).then(|| assemble(name, &reads)),
+        unreadable,
+    }
+}
+
+/// The assembly of one template and where its consensus was written
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct TemplateAssembly {
+    pub template: String,
+    pub assembly: Assembly,
+    pub fasta: Option<PathBuf>,
+    /// Why the consensus could not be written, e.g. because it already exists
+    pub write_error: Option<String>,
+}
+
+/// Assemble the traces of each template, given as `(template, path)` pairs
+///
+/// The consensus is written as `{template}.consensus.fasta` into `output_dir`,
+/// or next to the template's first trace. Existing consensus files are only
+/// replaced with `overwrite`; a template whose consensus cannot be written
+/// keeps the error and the other templates are still assembled.
+pub fn assemble_templates(
+    traces: &[(String, PathBuf)],
+    output_dir: Option<&Path>,
+    overwrite: bool,
+) -> Vec<TemplateAssembly> {
+    let mut templates: Vec<(String, Vec<PathBuf>)> = Vec::new();
+    for (template, path) in traces {
+        match templates.iter_mut().find(|(t, _)| t == template) {
+            Some((_, paths)) => paths.push(path.clone()),
+            None => templates.push((template.clone(), vec![path.clone()])),
+        }
+    }
+    let mut assemblies = Vec::new();
+    for (template, paths) in templates {
+        let assembly = assemble_traces(&template, &paths);
+        let dir = output_dir
+            .map(Path::to_path_buf)
+            .or_else(|| paths[0].parent().map(Path::to_path_buf))
+            .unwrap_or_default();
+        let (fasta, write_error) = match assembly
+            .contig
+            .as_ref()
+            .map(|c| c.write_fasta(&dir, overwrite))
+        {
+            Some(Ok(fasta)) => (Some(fasta), None),
+            Some(Err(err)) => (None, Some(err.to_string())),
+            None => (None, None),
+        };
+        assemblies.push(TemplateAssembly {
+            template,
+            assembly,
+            fasta,
+            write_error,
+        });
+    }
+    assemblies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLASMID: &str = "GACCTGAGCGTACGATCGGATCCAAGCTTGCATGCCTGCAGGTCGACTCTAGAGGATCCCCGGGTACCGAGCTCGAATTCACTGGCCGTCGTTTTACAACG";
+
+    fn read(name: &str, bases: &str, quality: u8) -> Read {
+        Read {
+            name: name.to_string(),
+            bases: bases.as_bytes().to_vec(),
+            qualities: vec![quality; bases.len()],
+        }
+    }
+
+    #[test]
+    fn test_quality_trim() {
+        assert_eq!(quality_trim(&[5, 10, 40, 40, 15, 40, 3, 2], 20), 2..6);
+        assert_eq!(quality_trim(&[5, 5], 20), 0..0);
+    }
+
+    #[test]
+    fn test_forward_and_reverse_reads_merge() {
+        let forward = read("F.ab1", &PLASMID[..75], 40);
+        let reverse_bases = align::reverse_complement(&PLASMID.as_bytes()[30..]);
+        let reverse = read("R.ab1", &String::from_utf8(reverse_bases).unwrap(), 40);
+        let contig = assemble("K528-1", &[forward, reverse]);
+        assert_eq!(contig.sequence, PLASMID);
+        assert!(contig.conflicts.is_empty());
+        assert_eq!(contig.reads[1].strand, Some(Strand::Reverse));
+        assert!(
+            contig
+                .to_fasta()
+                .starts_with(">K528-1 reads=2 conflicts=0\n")
+        );
+    }
+
+    #[test]
+    fn test_quality_decides_disagreements() {
+        // A low quality miscall is outvoted, a high quality one is reported
+        let mut miscalled = PLASMID[..80].to_string();
+        miscalled.replace_range(40..41, "A");
+        let mut weak = read("weak.ab1", &miscalled, 40);
+        weak.qualities[40] = 22;
+        let contig = assemble("K528-1", &[read("good.ab1", &PLASMID[..80], 40), weak]);
+        assert_eq!(&contig.sequence, &PLASMID[..80]);
+        assert_eq!(contig.conflicts.len(), 1);
+        assert_eq!(contig.conflicts[0].position, 41);
+        assert_eq!(contig.conflicts[0].calls, vec![('G', 40), ('A', 22)]);
+    }
+
+    #[test]
+    fn test_primer_walking_and_unrelated_reads() {
+        let reads = [
+            read("a.ab1", &PLASMID[..55], 40),
+            read("c.ab1", &PLASMID[55..], 40),
+            read("b.ab1", &PLASMID[25..80], 40),
+            read("other.ab1", "ATATATATATATATATATATATATATATAT", 40),
+        ];
+        let contig = assemble("K528-1", &reads);
+        assert_eq!(contig.sequence, PLASMID);
+        assert_eq!(contig.reads[3].strand, None);
+    }
+
+    #[test]
+    fn test_assemble_templates_writes_consensus() {
+        let dir = std::env::temp_dir().join("sanger_rename_assembly_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut traces = Vec::new();
+        for (name, bases) in [
+            ("250601.K528-1.T7.ab1", &PLASMID[..75]),
+            ("250601.K528-1.SP6.ab1", &PLASMID[30..]),
+            ("250601.GFP.T7.ab1", "not a trace"),
+        ] {
+            let path = dir.join(name);
+            if name.contains("GFP") {
+                std::fs::write(&path, bases).unwrap();
+            } else {
+                std::fs::write(&path, Trace::synthetic(bases, 10, 40).to_abif_bytes()).unwrap();
+            }
+            let template = name.split('.').nth(1).unwrap().to_string();
+            traces.push((template, path));
+        }
+        let assemblies = assemble_templates(&traces, None, false);
+        assert_eq!(assemblies.len(), 2);
+        let fasta = std::fs::read_to_string(dir.join("K528-1.consensus.fasta")).unwrap();
+        assert_eq!(fasta.lines().skip(1).collect::<String>(), PLASMID);
+        assert_eq!(assemblies[1].fasta, None);
+        assert_eq!(assemblies[1].assembly.unreadable.len(), 1);
+
+        // An earlier consensus is kept unless overwriting is asked for, and
+        // only that template is skipped
+        let fresh = dir.join("250601.GFP.T7.ab1");
+        std::fs::write(
+            &fresh,
+            Trace::synthetic(&PLASMID[..60], 10, 40).to_abif_bytes(),
+        )
+        .unwrap();
+        let assemblies = assemble_templates(&traces, None, false);
+        assert_eq!(assemblies[0].fasta, None);
+        assert!(assemblies[0].write_error.is_some());
+        assert_eq!(assemblies[1].fasta, Some(dir.join("GFP.consensus.fasta")));
+        let assemblies = assemble_templates(&traces, None, true);
+        assert!(assemblies.iter().all(|a| a.write_error.is_none()));
+    }
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// What one successful rename did, as stored in the catalog
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct RenameRecord {
+    pub original_name: String,
+    pub new_path: PathBuf,
+    pub vendor: String,
+    pub vendor_id: String,
+    pub well: Option<String>,
+    /// Date of the standardized name, as `YYYY-MM-DD`
+    pub date: String,
+    pub template: String,
+    pub primer: String,
+    /// SHA-256 of the file content, hex encoded
+    pub sha256: String,
+}
+
+/// SHA-256 of a file's content, hex encoded
+pub fn file_sha256(path: &Path) -> anyhow::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut file = std::fs::File::open(path)?;
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// A local JSON-lines file with one [`RenameRecord`] per renamed trace
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Catalog {
+    path: PathBuf,
+}
+
+impl Catalog {
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// `catalog.jsonl` in the user's data directory
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("sanger_rename").join("catalog.jsonl"))
+    }
+
+    pub fn open_default() -> anyhow::Result<Self> {
+        Self::default_path()
+            .map(Self::open)
+            .ok_or_else(|| anyhow::anyhow!("No data directory available for the catalog"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, record: &RenameRecord) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+
+    /// All records, oldest first; an absent catalog is empty
+    pub fn records(&self) -> anyhow::Result<Vec<RenameRecord>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let file = std::fs::File::open(&self.path)?;
+        let mut records = Vec::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = serde_json::from_str(&line).map_err(|err| {
+                anyhow::anyhow!("{} line {}: {}", self.path.display(), i + 1, err)
+            })?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_append_and_read() {
+        let dir = std::env::temp_dir().join("sanger_rename_catalog_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let catalog = Catalog::open(dir.join("catalog.jsonl"));
+        assert!(catalog.records().unwrap().is_empty());
+
+        let record = RenameRecord {
+            original_name: "K528-1.C1.34781340.B08.ab1".to_string(),
+            new_path: dir.join("250601.K528-1.C1.ab1"),
+            vendor: "Ruibio".to_string(),
+            vendor_id: "34781340.B08".to_string(),
+            well: Some("B08".to_string()),
+            date: "2025-06-01".to_string(),
+            template: "K528-1".to_string(),
+            primer: "C1".to_string(),
+            sha256: "00".repeat(32),
+        };
+        catalog.append(&record).unwrap();
+        catalog.append(&record).unwrap();
+        assert_eq!(catalog.records().unwrap(), vec![record.clone(), record]);
+    }
+
+    #[test]
+    fn test_file_sha256() {
+        let path = std::env::temp_dir().join("sanger_rename_sha256_test.txt");
+        std::fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            file_sha256(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
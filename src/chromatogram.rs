@@ -0,0 +1,517 @@
+//! Chromatogram images of traces as SVG or PNG
+//!
+//! Both formats are drawn from the same list of shapes: the four channels,
+//! a ruler, base calls and quality bars, optionally highlighting one base.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::abif::Trace;
+
+/// Bases shown on each side of a highlighted position by default
+pub const DEFAULT_FLANK: usize = 20;
+
+const BASE_WIDTH: f64 = 12.0;
+const MARGIN: f64 = 10.0;
+const RULER_HEIGHT: f64 = 14.0;
+const PLOT_HEIGHT: f64 = 150.0;
+const BASE_ROW_HEIGHT: f64 = 18.0;
+const QUALITY_HEIGHT: f64 = 30.0;
+/// Quality drawn as a full bar
+const MAX_QUALITY: f64 = 60.0;
+
+type Rgb = [u8; 3];
+const WHITE: Rgb = [255, 255, 255];
+const GREY: Rgb = [120, 120, 120];
+const HIGHLIGHT: Rgb = [255, 240, 170];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImageFormat {
+    Svg,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Svg => "svg",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "svg" => Ok(ImageFormat::Svg),
+            "png" => Ok(ImageFormat::Png),
+            _ => Err(format!("unknown image format {}, expected svg or png", s)),
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+/// Base calls to draw, 0-based and end-exclusive
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Region {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Region {
+    /// `flank` bases on each side of `center`, clipped to `len` bases
+    pub fn around(center: usize, flank: usize, len: usize) -> Self {
+        Self {
+            start: center.saturating_sub(flank),
+            end: (center + flank + 1).min(len),
+        }
+    }
+
+    /// Parse a 1-based inclusive range such as `120-180`
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (start, end) = s
+            .split_once(['-', ':'])
+            .ok_or_else(|| format!("expected START-END, got {}", s))?;
+        let parse = |n: &str| {
+            n.trim()
+                .parse::<usize>()
+                .map_err(|err| format!("{}: {}", n, err))
+        };
+        let (start, end) = (parse(start)?, parse(end)?);
+        if start == 0 || end < start {
+            return Err(format!("{} is not a range of positions from 1", s));
+        }
+        Ok(Self {
+            start: start - 1,
+            end,
+        })
+    }
+}
+
+fn base_color(base: u8) -> Rgb {
+    match base.to_ascii_uppercase() {
+        b'A' => [0, 160, 0],
+        b'C' => [0, 0, 220],
+        b'G' => [0, 0, 0],
+        b'T' => [220, 0, 0],
+        _ => GREY,
+    }
+}
+
+fn quality_color(quality: u8) -> Rgb {
+    match quality {
+        0..20 => [220, 80, 80],
+        20..30 => [230, 180, 0],
+        _ => [90, 170, 230],
+    }
+}
+
+enum Shape {
+    Polyline(Vec<(f64, f64)>, Rgb),
+    Rect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        fill: Rgb,
+    },
+    /// Text centered on `x`, its top at `y`
+    Text {
+        x: f64,
+        y: f64,
+        text: String,
+        fill: Rgb,
+    },
+}
+
+struct Picture {
+    width: f64,
+    height: f64,
+    shapes: Vec<Shape>,
+}
+
+fn picture(trace: &Trace, region: Region, highlight: Option<usize>) -> anyhow::Result<Picture> {
+    let len = trace.bases.len().min(trace.peak_locations.len());
+    let (start, end) = (region.start, region.end.min(len));
+    if start >= end {
+        anyhow::bail!(
+            "region {}-{} is outside the trace's {} bases",
+            region.start + 1,
+            region.end,
+            len
+        );
+    }
+    let peaks = &trace.peak_locations[..len];
+    let width = MARGIN * 2.0 + (end - start) as f64 * BASE_WIDTH;
+    let plot_top = MARGIN + RULER_HEIGHT;
+    let base_top = plot_top + PLOT_HEIGHT;
+    let quality_top = base_top + BASE_ROW_HEIGHT;
+    let height = quality_top + QUALITY_HEIGHT + MARGIN;
+    let center = |i: usize| MARGIN + (i as f64 - start as f64 + 0.5) * BASE_WIDTH;
+    let mut shapes = Vec::new();
+
+    if let Some(i) = highlight.filter(|i| (start..end).contains(i)) {
+        shapes.push(Shape::Rect {
+            x: center(i) - BASE_WIDTH / 2.0,
+            y: plot_top,
+            width: BASE_WIDTH,
+            height: height - plot_top - MARGIN,
+            fill: HIGHLIGHT,
+        });
+    }
+
+    // Scans are placed linearly between neighbouring peaks, so base calls fall on their columns
+    let scans: Vec<(f64, usize)> = (start.saturating_sub(1)..end.min(len - 1))
+        .flat_map(|i| {
+            let (from, to) = (peaks[i] as usize, peaks[i + 1] as usize);
+            let span = to.saturating_sub(from).max(1) as f64;
+            (from..to).map(move |scan| (center(i) + (scan - from) as f64 / span * BASE_WIDTH, scan))
+        })
+        .filter(|&(x, _)| x >= MARGIN && x <= width - MARGIN)
+        .collect();
+    let top = trace
+        .channels
+        .iter()
+        .flat_map(|channel| scans.iter().filter_map(|&(_, scan)| channel.get(scan)))
+        .copied()
+        .max()
+        .unwrap_or(1)
+        .max(1) as f64;
+    for (channel, &base) in trace.channels.iter().zip(&trace.base_order) {
+        let points = scans
+            .iter()
+            .filter_map(|&(x, scan)| {
+                let value = channel.get(scan)?.max(&0);
+                Some((x, base_top - *value as f64 / top * (PLOT_HEIGHT - 4.0)))
+            })
+            .collect();
+        shapes.push(Shape::Polyline(points, base_color(base)));
+    }
+
+    for i in start..end {
+        if (i + 1) % 10 == 0 {
+            shapes.push(Shape::Text {
+                x: center(i),
+                y: MARGIN,
+                text: (i + 1).to_string(),
+                fill: GREY,
+            });
+        }
+        let base = trace.bases[i];
+        shapes.push(Shape::Text {
+            x: center(i),
+            y: base_top + 2.0,
+            text: (base as char).to_string(),
+            fill: base_color(base),
+        });
+        if let Some(&quality) = trace.qualities.get(i) {
+            let bar = (quality as f64).min(MAX_QUALITY) / MAX_QUALITY * QUALITY_HEIGHT;
+            shapes.push(Shape::Rect {
+                x: center(i) - BASE_WIDTH / 2.0 + 1.0,
+                y: quality_top + QUALITY_HEIGHT - bar,
+                width: BASE_WIDTH - 2.0,
+                height: bar,
+                fill: quality_color(quality),
+            });
+        }
+    }
+    Ok(Picture {
+        width,
+        height,
+        shapes,
+    })
+}
+
+fn hex(color: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// SVG of the region, `title` shown as a tooltip
+pub fn render_svg(
+    trace: &Trace,
+    region: Region,
+    highlight: Option<usize>,
+    title: &str,
+) -> anyhow::Result<String> {
+    let picture = picture(trace, region, highlight)?;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n<title>{}</title>\n<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\n",
+        escape(title),
+        w = picture.width,
+        h = picture.height,
+    );
+    for shape in &picture.shapes {
+        match shape {
+            Shape::Polyline(points, color) => {
+                let points: Vec<String> = points
+                    .iter()
+                    .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+                    .collect();
+                svg.push_str(&format!(
+                    "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"1\" points=\"{}\"/>\n",
+                    hex(*color),
+                    points.join(" ")
+                ));
+            }
+            Shape::Rect {
+                x,
+                y,
+                width,
+                height,
+                fill,
+            } => svg.push_str(&format!(
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>\n",
+                x,
+                y,
+                width,
+                height,
+                hex(*fill)
+            )),
+            Shape::Text { x, y, text, fill } => svg.push_str(&format!(
+                "<text x=\"{:.1}\" y=\"{:.1}\" fill=\"{}\" font-family=\"monospace\" font-size=\"11\" text-anchor=\"middle\">{}</text>\n",
+                x,
+                y + 10.0,
+                hex(*fill),
+                escape(text)
+            )),
+        }
+    }
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+/// 5×7 glyphs for digits and base calls, one row per byte
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        '0' => [14, 17, 19, 21, 25, 17, 14],
+        '1' => [4, 12, 4, 4, 4, 4, 14],
+        '2' => [14, 17, 1, 2, 4, 8, 31],
+        '3' => [31, 2, 4, 2, 1, 17, 14],
+        '4' => [2, 6, 10, 18, 31, 2, 2],
+        '5' => [31, 16, 30, 1, 1, 17, 14],
+        '6' => [6, 8, 16, 30, 17, 17, 14],
+        '7' => [31, 1, 2, 4, 8, 8, 8],
+        '8' => [14, 17, 17, 14, 17, 17, 14],
+        '9' => [14, 17, 17, 15, 1, 2, 12],
+        'A' => [14, 17, 17, 31, 17, 17, 17],
+        'C' => [14, 17, 16, 16, 16, 17, 14],
+        'G' => [14, 17, 16, 23, 17, 17, 15],
+        'T' => [31, 4, 4, 4, 4, 4, 4],
+        'R' => [30, 17, 17, 30, 20, 18, 17],
+        'Y' => [17, 17, 10, 4, 4, 4, 4],
+        'S' => [15, 16, 16, 14, 1, 1, 30],
+        'W' => [17, 17, 17, 21, 21, 21, 10],
+        'K' => [17, 18, 20, 24, 20, 18, 17],
+        'M' => [17, 27, 21, 21, 17, 17, 17],
+        _ => [17, 25, 21, 19, 17, 17, 17],
+    }
+}
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: WHITE.repeat(width * height),
+        }
+    }
+
+    fn set(&mut self, x: i64, y: i64, color: Rgb) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            let offset = (y as usize * self.width + x as usize) * 3;
+            self.pixels[offset..offset + 3].copy_from_slice(&color);
+        }
+    }
+
+    fn line(&mut self, (x1, y1): (f64, f64), (x2, y2): (f64, f64), color: Rgb) {
+        let steps = (x2 - x1).abs().max((y2 - y1).abs()).ceil().max(1.0) as usize;
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let (x, y) = (x1 + (x2 - x1) * t, y1 + (y2 - y1) * t);
+            self.set(x.round() as i64, y.round() as i64, color);
+        }
+    }
+
+    fn rect(&mut self, x: f64, y: f64, width: f64, height: f64, color: Rgb) {
+        for py in y.round() as i64..(y + height).round() as i64 {
+            for px in x.round() as i64..(x + width).round() as i64 {
+                self.set(px, py, color);
+            }
+        }
+    }
+
+    fn text(&mut self, x: f64, y: f64, text: &str, color: Rgb) {
+        let left = (x - (text.len() * 6) as f64 / 2.0).round() as i64;
+        for (n, c) in text.chars().enumerate() {
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for column in 0..5 {
+                    if bits & (16 >> column) != 0 {
+                        self.set(left + (n * 6 + column) as i64, y as i64 + row as i64, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// PNG of the region
+pub fn render_png(
+    trace: &Trace,
+    region: Region,
+    highlight: Option<usize>,
+) -> anyhow::Result<Vec<u8>> {
+    let picture = picture(trace, region, highlight)?;
+    let mut canvas = Canvas::new(
+        picture.width.ceil() as usize,
+        picture.height.ceil() as usize,
+    );
+    for shape in &picture.shapes {
+        match shape {
+            Shape::Polyline(points, color) => {
+                for pair in points.windows(2) {
+                    canvas.line(pair[0], pair[1], *color);
+                }
+            }
+            Shape::Rect {
+                x,
+                y,
+                width,
+                height,
+                fill,
+            } => canvas.rect(*x, *y, *width, *height, *fill),
+            Shape::Text { x, y, text, fill } => canvas.text(*x, *y + 2.0, text, *fill),
+        }
+    }
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, canvas.width as u32, canvas.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&canvas.pixels)?;
+    writer.finish()?;
+    Ok(bytes)
+}
+
+/// Write images of `trace` into `dir` as `{name}.{ext}`, or `{name}_{start}-{end}.{ext}` for a region
+pub fn export(
+    trace: &Trace,
+    name: &str,
+    region: Option<Region>,
+    highlight: Option<usize>,
+    formats: &[ImageFormat],
+    dir: &Path,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let len = trace.bases.len().min(trace.peak_locations.len());
+    let (stem, region) = match region {
+        Some(region) => (
+            format!("{}_{}-{}", name, region.start + 1, region.end.min(len)),
+            region,
+        ),
+        None => (name.to_string(), Region { start: 0, end: len }),
+    };
+    let mut written = Vec::new();
+    for &format in formats {
+        let path = dir.join(format!("{}.{}", stem, format.extension()));
+        match format {
+            ImageFormat::Svg => std::fs::write(&path, render_svg(trace, region, highlight, name)?)?,
+            ImageFormat::Png => std::fs::write(&path, render_png(trace, region, highlight)?)?,
+        }
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_region() {
+        assert_eq!(
+            Region::parse("120-180"),
+            Ok(Region {
+                start: 119,
+                end: 180
+            })
+        );
+        assert!(Region::parse("0-5").is_err());
+        assert!(Region::parse("9-5").is_err());
+        assert_eq!(Region::around(5, 20, 100), Region { start: 0, end: 26 });
+        assert_eq!(
+            Region::around(90, 20, 100),
+            Region {
+                start: 70,
+                end: 100
+            }
+        );
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let trace = Trace::synthetic("ACGTACGTACGTAC", 10, 40);
+        let svg = render_svg(&trace, Region { start: 2, end: 12 }, Some(5), "a<b").unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("<title>a&lt;b</title>"));
+        assert_eq!(svg.matches("<polyline").count(), 4);
+        // Ten base calls, one ruler number and the highlight
+        assert_eq!(svg.matches("<text").count(), 11);
+        assert!(svg.contains(&hex(HIGHLIGHT)));
+        assert!(render_svg(&trace, Region { start: 20, end: 30 }, None, "").is_err());
+    }
+
+    #[test]
+    fn test_export_png() {
+        let dir = std::env::temp_dir().join("sanger_rename_chromatogram_export");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let trace = Trace::synthetic("ACGTACGTACGTAC", 10, 40);
+        let written = export(
+            &trace,
+            "2025-01-01_K528_T7",
+            Some(Region::around(4, 3, 14)),
+            Some(4),
+            &[ImageFormat::Svg, ImageFormat::Png],
+            &dir,
+        )
+        .unwrap();
+        assert_eq!(
+            written,
+            vec![
+                dir.join("2025-01-01_K528_T7_2-8.svg"),
+                dir.join("2025-01-01_K528_T7_2-8.png")
+            ]
+        );
+        let png = std::fs::read(&written[1]).unwrap();
+        let decoder = png::Decoder::new(png.as_slice());
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!(
+            (info.width, info.height),
+            (
+                (MARGIN * 2.0 + 7.0 * BASE_WIDTH) as u32,
+                (MARGIN * 2.0 + RULER_HEIGHT + PLOT_HEIGHT + BASE_ROW_HEIGHT + QUALITY_HEIGHT)
+                    as u32
+            )
+        );
+    }
+}
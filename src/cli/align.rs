@@ -0,0 +1,104 @@
+use clap::Args;
+use sanger_rename::Vendor;
+use sanger_rename::align::{self, Alignment};
+use sanger_rename::reference::ReferenceLibrary;
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct AlignArgs {
+    /// Traces to align
+    #[arg(value_name = "FILE", required = true)]
+    files: Vec<PathBuf>,
+    /// FASTA or GenBank file, or a directory of them; records are matched to template names
+    #[arg(short, long = "reference", value_name = "PATH", required = true)]
+    references: Vec<PathBuf>,
+    /// Vendor naming scheme of files that are not renamed yet
+    #[arg(long)]
+    vendor: Option<Vendor>,
+    /// Align every file to this template's reference instead of the one in its name
+    #[arg(short, long)]
+    template: Option<String>,
+    /// Print results as JSON instead of a table
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct AlignReport {
+    path: PathBuf,
+    template: Option<String>,
+    alignment: Option<Alignment>,
+    error: Option<String>,
+    /// The trace or its reference could not be read, as opposed to not aligning
+    #[serde(skip)]
+    failed: bool,
+}
+
+pub fn run(args: AlignArgs) -> anyhow::Result<()> {
+    let library = ReferenceLibrary::load(&args.references)?;
+    let config = super::load_config(&args.files)?;
+    let reports: Vec<AlignReport> = args
+        .files
+        .iter()
+        .map(|path| {
+            let template = args
+                .template
+                .clone()
+                .or_else(|| super::template_name(path, args.vendor, &config));
+            let result = match &template {
+                Some(template) => align::align_trace(path, template, &library),
+                None => Err(anyhow::anyhow!(
+                    "no template name, use --vendor or --template"
+                )),
+            };
+            let (alignment, error, failed) = match result {
+                Ok(Some(alignment)) => (Some(alignment), None, false),
+                Ok(None) => (None, Some("no alignment".to_string()), false),
+                Err(err) => (None, Some(err.to_string()), true),
+            };
+            AlignReport {
+                path: path.clone(),
+                template,
+                alignment,
+                error,
+                failed,
+            }
+        })
+        .collect();
+
+    let failed = reports.iter().filter(|report| report.failed).count();
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+        return super::check_failures(failed, reports.len(), "could not be aligned");
+    }
+    println!(
+        "file\ttemplate\tstrand\tidentity\tmismatches\tinsertions\tdeletions\treference\tread"
+    );
+    for report in &reports {
+        let template = report.template.as_deref().unwrap_or("-");
+        match (&report.alignment, &report.error) {
+            (Some(a), _) => println!(
+                "{}\t{}\t{}\t{:.2}%\t{}\t{}\t{}\t{}..{}\t{}..{}",
+                report.path.display(),
+                template,
+                a.strand,
+                a.identity() * 100.0,
+                a.mismatches,
+                a.insertions,
+                a.deletions,
+                a.reference_start + 1,
+                a.reference_end,
+                a.read_start + 1,
+                a.read_end
+            ),
+            (None, error) => println!(
+                "{}\t{}\t{}",
+                report.path.display(),
+                template,
+                error.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+    super::check_failures(failed, reports.len(), "could not be aligned")
+}
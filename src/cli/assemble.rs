@@ -0,0 +1,102 @@
+use clap::Args;
+use sanger_rename::Vendor;
+use sanger_rename::assembly;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct AssembleArgs {
+    /// Traces to assemble, grouped by the template in their names
+    #[arg(value_name = "FILE", required = true)]
+    files: Vec<PathBuf>,
+    /// Vendor naming scheme of files that are not renamed yet
+    #[arg(long)]
+    vendor: Option<Vendor>,
+    /// Directory for the consensus files instead of next to each template's traces
+    #[arg(short, long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+    /// Replace existing consensus files
+    #[arg(long)]
+    overwrite: bool,
+    /// Print results as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: AssembleArgs) -> anyhow::Result<()> {
+    let config = super::load_config(&args.files)?;
+    let mut traces = Vec::new();
+    let mut failed = 0;
+    for path in &args.files {
+        match super::template_name(path, args.vendor, &config) {
+            Some(template) => traces.push((template, path.clone())),
+            None => {
+                eprintln!("{}: no template name, use --vendor", path.display());
+                failed += 1;
+            }
+        }
+    }
+    let assemblies =
+        assembly::assemble_templates(&traces, args.output_dir.as_deref(), args.overwrite);
+    failed += assemblies
+        .iter()
+        .map(|template| template.assembly.unreadable.len())
+        .sum::<usize>();
+    let unwritten = assemblies
+        .iter()
+        .filter(|t| t.write_error.is_some())
+        .count();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&assemblies)?);
+        check_unwritten(unwritten, assemblies.len())?;
+        return super::check_failures(failed, args.files.len(), "could not be assembled");
+    }
+    for template in &assemblies {
+        for (path, err) in &template.assembly.unreadable {
+            eprintln!("{}: {}", path.display(), err);
+        }
+        if let Some(err) = &template.write_error {
+            eprintln!("{}: {}", template.template, err);
+            continue;
+        }
+        let (Some(contig), Some(fasta)) = (&template.assembly.contig, &template.fasta) else {
+            println!("{}: no readable traces", template.template);
+            continue;
+        };
+        let placed = contig.reads.iter().filter(|r| r.strand.is_some()).count();
+        println!(
+            "{}: {}/{} reads, {} bp, {} conflict(s) -> {}",
+            template.template,
+            placed,
+            contig.reads.len(),
+            contig.sequence.len(),
+            contig.conflicts.len(),
+            fasta.display()
+        );
+        for read in contig.reads.iter().filter(|r| r.strand.is_none()) {
+            println!("  not placed: {}", read.name);
+        }
+        for conflict in &contig.conflicts {
+            let calls: Vec<String> = conflict
+                .calls
+                .iter()
+                .map(|(call, weight)| format!("{} {}", call, weight))
+                .collect();
+            println!(
+                "  conflict at {}: {} ({})",
+                conflict.position,
+                conflict.consensus,
+                calls.join(", ")
+            );
+        }
+    }
+    check_unwritten(unwritten, assemblies.len())?;
+    super::check_failures(failed, args.files.len(), "could not be assembled")
+}
+
+fn check_unwritten(unwritten: usize, total: usize) -> anyhow::Result<()> {
+    if unwritten > 0 {
+        anyhow::bail!("{} of {} consensus file(s) not written", unwritten, total);
+    }
+    Ok(())
+}
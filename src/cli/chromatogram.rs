@@ -0,0 +1,138 @@
+use clap::Args;
+use sanger_rename::Vendor;
+use sanger_rename::abif::Trace;
+use sanger_rename::align;
+use sanger_rename::chromatogram::{self, ImageFormat, Region};
+use sanger_rename::config::Config;
+use sanger_rename::reference::ReferenceLibrary;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+#[command(group = clap::ArgGroup::new("area").args(["region", "around", "mismatches"]))]
+pub struct ChromatogramArgs {
+    /// Traces to draw
+    #[arg(value_name = "FILE", required = true)]
+    files: Vec<PathBuf>,
+    /// Image formats to write; both by default
+    #[arg(short, long = "format", value_name = "FORMAT")]
+    formats: Vec<ImageFormat>,
+    /// Draw only these base calls, 1-based and inclusive, such as 120-180
+    #[arg(long, value_name = "START-END", value_parser = Region::parse)]
+    region: Option<Region>,
+    /// Draw the bases around this 1-based position, highlighting it
+    #[arg(long, value_name = "POSITION")]
+    around: Option<usize>,
+    /// Draw one image around each mismatch or indel against the template's reference
+    #[arg(long, requires = "references")]
+    mismatches: bool,
+    /// Bases drawn on each side of a highlighted position
+    #[arg(long, value_name = "BASES", default_value_t = chromatogram::DEFAULT_FLANK)]
+    flank: usize,
+    /// FASTA or GenBank references (files or directories) to find mismatches with
+    #[arg(short, long = "reference", value_name = "PATH")]
+    references: Vec<PathBuf>,
+    /// Vendor naming scheme of files that are not renamed yet
+    #[arg(long)]
+    vendor: Option<Vendor>,
+    /// Directory for the images; next to each trace by default
+    #[arg(short, long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+}
+
+/// Areas to draw, each with an optional highlighted base
+fn areas(
+    args: &ChromatogramArgs,
+    path: &Path,
+    trace: &Trace,
+    references: Option<&ReferenceLibrary>,
+    config: &Config,
+) -> anyhow::Result<Vec<(Option<Region>, Option<usize>)>> {
+    let len = trace.bases.len();
+    if let Some(position) = args.around {
+        let index = position
+            .checked_sub(1)
+            .filter(|&i| i < len)
+            .ok_or_else(|| anyhow::anyhow!("position {} is outside the {} bases", position, len))?;
+        return Ok(vec![(
+            Some(Region::around(index, args.flank, len)),
+            Some(index),
+        )]);
+    }
+    let Some(references) = references.filter(|_| args.mismatches) else {
+        return Ok(vec![(args.region, None)]);
+    };
+    let template = super::template_name(path, args.vendor, config)
+        .ok_or_else(|| anyhow::anyhow!("no template name, use --vendor"))?;
+    let reference = references
+        .get(&template)
+        .ok_or_else(|| anyhow::anyhow!("no reference for {}", template))?;
+    let alignment = align::align(&trace.bases, &reference.sequence)
+        .ok_or_else(|| anyhow::anyhow!("trace does not align to {}", template))?;
+    // Differences close to one already drawn are part of its image
+    let mut areas: Vec<(Option<Region>, Option<usize>)> = Vec::new();
+    let mut last: Option<usize> = None;
+    for index in alignment.differences(len) {
+        if last.is_some_and(|last| index.abs_diff(last) <= args.flank) {
+            continue;
+        }
+        areas.push((Some(Region::around(index, args.flank, len)), Some(index)));
+        last = Some(index);
+    }
+    Ok(areas)
+}
+
+fn export(
+    args: &ChromatogramArgs,
+    path: &Path,
+    references: Option<&ReferenceLibrary>,
+    config: &Config,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let trace = Trace::from_file(path)?;
+    let formats = if args.formats.is_empty() {
+        vec![ImageFormat::Svg, ImageFormat::Png]
+    } else {
+        args.formats.clone()
+    };
+    let name = super::standardized_stem(path, args.vendor, config);
+    let dir = match &args.output_dir {
+        Some(dir) => dir.as_path(),
+        None => path.parent().unwrap_or(Path::new("")),
+    };
+    let mut written = Vec::new();
+    for (region, highlight) in areas(args, path, &trace, references, config)? {
+        written.extend(chromatogram::export(
+            &trace, &name, region, highlight, &formats, dir,
+        )?);
+    }
+    Ok(written)
+}
+
+pub fn run(args: ChromatogramArgs) -> anyhow::Result<()> {
+    let references = if args.references.is_empty() {
+        None
+    } else {
+        Some(ReferenceLibrary::load(&args.references)?)
+    };
+    if let Some(dir) = &args.output_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+    let config = super::load_config(&args.files)?;
+    let mut failed = 0;
+    for path in &args.files {
+        match export(&args, path, references.as_ref(), &config) {
+            Ok(written) if written.is_empty() => {
+                println!("{}\tno mismatches", path.display())
+            }
+            Ok(written) => {
+                for image in written {
+                    println!("{}\t{}", path.display(), image.display());
+                }
+            }
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                failed += 1;
+            }
+        }
+    }
+    super::check_failures(failed, args.files.len(), "could not be drawn")
+}
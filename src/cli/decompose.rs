@@ -0,0 +1,160 @@
+use clap::Args;
+use sanger_rename::Vendor;
+use sanger_rename::abif::Trace;
+use sanger_rename::decompose::{self, DecomposeOptions, IndelSpectrum};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+#[command(group = clap::ArgGroup::new("cut").required(true))]
+pub struct DecomposeArgs {
+    /// Traces of edited samples
+    #[arg(value_name = "FILE", required = true)]
+    files: Vec<PathBuf>,
+    /// Traces of unedited controls, matched to edited traces by template name
+    #[arg(short, long = "control", value_name = "FILE", required = true)]
+    controls: Vec<PathBuf>,
+    /// Cut site as a 1-based position in the control's base calls; the cut lies after it
+    #[arg(long, value_name = "POSITION", group = "cut")]
+    cut_site: Option<usize>,
+    /// Cas9 guide sequence without PAM, located in the control to find the cut site
+    #[arg(long, value_name = "SEQUENCE", group = "cut")]
+    guide: Option<String>,
+    /// Largest insertion or deletion to consider
+    #[arg(long, value_name = "BASES", default_value_t = decompose::DEFAULT_MAX_INDEL)]
+    max_indel: usize,
+    /// Number of bases after the cut site used for the decomposition
+    #[arg(long, value_name = "BASES", default_value_t = decompose::DEFAULT_WINDOW)]
+    window: usize,
+    /// Vendor naming scheme of files that are not renamed yet
+    #[arg(long)]
+    vendor: Option<Vendor>,
+    /// Write the report to this file instead of printing it
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// Report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct DecomposeReport {
+    path: PathBuf,
+    control: Option<PathBuf>,
+    template: Option<String>,
+    spectrum: Option<IndelSpectrum>,
+    error: Option<String>,
+}
+
+/// The control of the same template, or the only control given
+fn find_control<'a>(
+    template: Option<&str>,
+    controls: &'a [(Option<String>, PathBuf)],
+) -> anyhow::Result<&'a Path> {
+    if let Some(template) = template
+        && let Some((_, path)) = controls.iter().find(|(t, _)| {
+            t.as_deref()
+                .is_some_and(|t| t.eq_ignore_ascii_case(template))
+        })
+    {
+        return Ok(path);
+    }
+    match controls {
+        [(_, path)] => Ok(path),
+        _ => anyhow::bail!(
+            "no control for template {}",
+            template.unwrap_or("(unknown, use --vendor)")
+        ),
+    }
+}
+
+fn decompose_pair(
+    args: &DecomposeArgs,
+    control_path: &Path,
+    edited_path: &Path,
+) -> anyhow::Result<IndelSpectrum> {
+    let control = Trace::from_file(control_path)?;
+    let edited = Trace::from_file(edited_path)?;
+    let cut = match (&args.cut_site, &args.guide) {
+        (Some(position), _) => position
+            .checked_sub(1)
+            .ok_or_else(|| anyhow::anyhow!("cut site positions start at 1"))?,
+        (None, Some(guide)) => decompose::find_cut_site(guide.as_bytes(), &control.bases)
+            .ok_or_else(|| anyhow::anyhow!("guide {} not found in the control", guide))?,
+        (None, None) => unreachable!("clap requires a cut site or guide"),
+    };
+    decompose::decompose(
+        &control,
+        &edited,
+        DecomposeOptions {
+            cut,
+            max_indel: args.max_indel,
+            window: args.window,
+        },
+    )
+}
+
+pub fn run(args: DecomposeArgs) -> anyhow::Result<()> {
+    let config = super::load_config(&args.files)?;
+    let controls: Vec<(Option<String>, PathBuf)> = args
+        .controls
+        .iter()
+        .map(|path| {
+            (
+                super::template_name(path, args.vendor, &config),
+                path.clone(),
+            )
+        })
+        .collect();
+
+    let mut reports = Vec::new();
+    for path in &args.files {
+        let template = super::template_name(path, args.vendor, &config);
+        let control = find_control(template.as_deref(), &controls);
+        let control_path = control.as_ref().ok().map(|p| p.to_path_buf());
+        let result = control.and_then(|control| decompose_pair(&args, control, path));
+        let (spectrum, error) = match result {
+            Ok(spectrum) => (Some(spectrum), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        reports.push(DecomposeReport {
+            path: path.clone(),
+            control: control_path,
+            template,
+            spectrum,
+            error,
+        });
+    }
+
+    let report = if args.json {
+        serde_json::to_string_pretty(&reports)? + "\n"
+    } else {
+        let mut text = String::new();
+        for report in &reports {
+            text.push_str(&format!(
+                "{} vs {}\n",
+                report.path.display(),
+                report
+                    .control
+                    .as_deref()
+                    .map_or("-".into(), |p| p.display().to_string())
+            ));
+            match (&report.spectrum, &report.error) {
+                (Some(spectrum), _) => text.push_str(&spectrum.report(0.01)),
+                (None, Some(error)) => text.push_str(&format!("error: {}\n", error)),
+                (None, None) => {}
+            }
+            text.push('\n');
+        }
+        text
+    };
+    match &args.output {
+        Some(path) => std::fs::write(path, report)?,
+        None => print!("{}", report),
+    }
+    let failed = reports
+        .iter()
+        .filter(|report| report.error.is_some())
+        .count();
+    super::check_failures(failed, reports.len(), "could not be decomposed")
+}
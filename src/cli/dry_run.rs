@@ -0,0 +1,32 @@
+use sanger_rename::FieldRule;
+use sanger_rename::config::Config;
+use sanger_rename::mapping::NameMappings;
+use sanger_rename::plan::{self, PlannedAction};
+
+/// Print what renaming `filenames` would do, without touching them
+///
+/// Fails when any file could not be renamed, so scripts can check the batch first.
+pub fn run(
+    filenames: &[String],
+    config: &Config,
+    date: Option<time::Date>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let date = date.unwrap_or_else(|| time::OffsetDateTime::now_local().unwrap().date());
+    let mut mappings = NameMappings::load_saved()?;
+    mappings.extend(&config.mappings());
+    let plans = plan::plan(filenames, config, &mappings, FieldRule::load_saved()?, date);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&plans)?);
+    } else {
+        print!("{}", plan::to_table(&plans));
+    }
+    let failing = plans
+        .iter()
+        .filter(|plan| matches!(plan.action, PlannedAction::Fail | PlannedAction::Missing))
+        .count();
+    if failing > 0 {
+        anyhow::bail!("{} of {} file(s) cannot be renamed", failing, plans.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use clap::Subcommand;
+use sanger_rename::config::Config;
+use sanger_rename::{SangerFilename, StandardizedName, Vendor};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+pub mod align;
+pub mod assemble;
+pub mod chromatogram;
+pub mod decompose;
+pub mod dry_run;
+pub mod order;
+pub mod primers;
+pub mod reconcile;
+pub mod rename;
+pub mod scf;
+pub mod search;
+pub mod watch;
+
+pub use align::AlignArgs;
+pub use assemble::AssembleArgs;
+pub use chromatogram::ChromatogramArgs;
+pub use decompose::DecomposeArgs;
+pub use order::OrderArgs;
+pub use primers::PrimersArgs;
+pub use reconcile::ReconcileArgs;
+pub use scf::ScfArgs;
+pub use search::SearchArgs;
+pub use watch::WatchArgs;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Align traces to reference sequences and report identity
+    Align(AlignArgs),
+    /// Merge the reads of each template into a consensus FASTA
+    Assemble(AssembleArgs),
+    /// Export chromatogram images as SVG or PNG
+    Chromatogram(ChromatogramArgs),
+    /// Estimate indel frequencies of edited samples against unedited controls
+    Decompose(DecomposeArgs),
+    /// Write a vendor's submission sheet for a list of templates and primers
+    Order(OrderArgs),
+    /// Check that each trace's primer name fits where the read lies
+    Primers(PrimersArgs),
+    /// Compare delivered traces with the order that was submitted
+    Reconcile(ReconcileArgs),
+    /// Convert ABIF traces to SCF v3
+    Scf(ScfArgs),
+    /// Search renamed traces in the catalog or under given directories
+    Search(SearchArgs),
+    /// Rename or queue new traces as they arrive in a folder such as Downloads
+    Watch(WatchArgs),
+}
+
+impl Command {
+    pub fn run(self) -> anyhow::Result<()> {
+        match self {
+            Command::Align(args) => align::run(args),
+            Command::Assemble(args) => assemble::run(args),
+            Command::Chromatogram(args) => chromatogram::run(args),
+            Command::Decompose(args) => decompose::run(args),
+            Command::Order(args) => order::run(args),
+            Command::Primers(args) => primers::run(args),
+            Command::Reconcile(args) => reconcile::run(args),
+            Command::Scf(args) => scf::run(args),
+            Command::Search(args) => search::run(args),
+            Command::Watch(args) => watch::run(args),
+        }
+    }
+}
+
+/// Parse a `YYYY-MM-DD` date argument
+pub fn parse_date(s: &str) -> Result<time::Date, String> {
+    time::Date::parse(s, time::macros::format_description!("[year]-[month]-[day]"))
+        .map_err(|err| format!("expected YYYY-MM-DD: {}", err))
+}
+
+/// Fail when any of `total` files failed, after the others were handled
+///
+/// Every subcommand carries on past a file it cannot process and reports it,
+/// then exits with an error so scripts notice.
+pub fn check_failures(failed: usize, total: usize, outcome: &str) -> anyhow::Result<()> {
+    if failed > 0 {
+        anyhow::bail!("{} of {} file(s) {}", failed, total, outcome);
+    }
+    Ok(())
+}
+
+/// Replace a `-` among the filenames with the list read from stdin
+///
+/// Names that are not valid UTF-8 are skipped with a warning.
+pub fn read_stdin_filenames(filenames: Vec<String>, null: bool) -> anyhow::Result<Vec<String>> {
+    if !filenames.iter().any(|filename| filename == "-") {
+        return Ok(filenames);
+    }
+    let mut input = Vec::new();
+    std::io::stdin().read_to_end(&mut input)?;
+    let mut listed = Vec::new();
+    for path in split_filenames(&input, null) {
+        match path.into_os_string().into_string() {
+            Ok(name) => listed.push(name),
+            Err(name) => eprintln!("Skipping {}: not valid UTF-8", Path::new(&name).display()),
+        }
+    }
+    let mut expanded = Vec::new();
+    let mut listed = Some(listed);
+    for filename in filenames {
+        if filename != "-" {
+            expanded.push(filename);
+        } else if let Some(listed) = listed.take() {
+            expanded.extend(listed);
+        }
+    }
+    Ok(expanded)
+}
+
+/// Split a file list into names, one per line (LF or CRLF), or NUL-delimited
+/// with `null` as written by `find -print0`. Empty entries are dropped.
+pub fn split_filenames(input: &[u8], null: bool) -> Vec<PathBuf> {
+    let delimiter = if null { b'\0' } else { b'\n' };
+    input
+        .split(|&byte| byte == delimiter)
+        .map(|entry| match entry {
+            [name @ .., b'\r'] if !null => name,
+            _ => entry,
+        })
+        .filter(|entry| !entry.is_empty())
+        .map(path_from_bytes)
+        .collect()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// The configuration of a subcommand's files, looked up from the first file's folder
+pub fn load_config(files: &[PathBuf]) -> anyhow::Result<Config> {
+    Config::load_for(files.first().map(PathBuf::as_path).unwrap_or(Path::new("")))
+}
+
+/// Template and primer names of a renamed file, or else of the vendor's naming scheme
+///
+/// Renamed files are recognized by the configured name template as well as
+/// the default layout.
+pub fn name_fields(
+    path: &Path,
+    vendor: Option<Vendor>,
+    config: &Config,
+) -> Option<(String, String)> {
+    let name = path.to_string_lossy();
+    if let Some(renamed) = config.parse_renamed(&name) {
+        return Some((renamed.template_name, renamed.primer_name));
+    }
+    let sanger_fn = SangerFilename::try_new(&name, vendor?).ok()?;
+    Some((sanger_fn.get_template_name(), sanger_fn.get_primer_name()))
+}
+
+pub fn template_name(path: &Path, vendor: Option<Vendor>, config: &Config) -> Option<String> {
+    name_fields(path, vendor, config).map(|(template, _)| template)
+}
+
+/// Name of a renamed file without extension, or the name renaming would give
+/// it, or else its file stem
+pub fn standardized_stem(path: &Path, vendor: Option<Vendor>, config: &Config) -> String {
+    let name = path.to_string_lossy();
+    // Names written with the name template are kept as they are
+    let file_stem = || {
+        path.file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    };
+    if config
+        .name
+        .template
+        .as_ref()
+        .is_some_and(|template| template.parse_name(&name).is_some())
+    {
+        return file_stem();
+    }
+    if let Ok(mut standardized) = StandardizedName::parse(&name) {
+        standardized.extension = None;
+        return standardized.to_string();
+    }
+    if let Some(vendor) = vendor
+        && let Ok(mut sanger_fn) = SangerFilename::try_new(&name, vendor)
+    {
+        config.apply(&mut sanger_fn);
+        return sanger_fn.get_standardized_name();
+    }
+    file_stem()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_filenames() {
+        let paths = |names: &[&str]| names.iter().map(PathBuf::from).collect::<Vec<_>>();
+        assert_eq!(
+            split_filenames(b"a.ab1\nb c.ab1\n", false),
+            paths(&["a.ab1", "b c.ab1"])
+        );
+        assert_eq!(
+            split_filenames(b"a.ab1\r\n\r\n\nb.ab1\r\n", false),
+            paths(&["a.ab1", "b.ab1"])
+        );
+        assert_eq!(
+            split_filenames(b"a\nb.ab1\0\0c.ab1\0", true),
+            paths(&["a\nb.ab1", "c.ab1"])
+        );
+        assert!(split_filenames(b"", false).is_empty());
+
+        let names = split_filenames(b"caf\xe9.ab1\n", false);
+        assert_eq!(names.len(), 1);
+        assert!(names[0].to_str().is_none());
+    }
+}
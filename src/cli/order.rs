@@ -0,0 +1,66 @@
+use clap::Args;
+use sanger_rename::Vendor;
+use sanger_rename::order::{self, Order};
+use sanger_rename::plate::{PlateFormat, Well};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct OrderArgs {
+    /// Sample list with a template and its primers per line
+    #[arg(value_name = "FILE")]
+    samples: PathBuf,
+    /// Vendor whose submission sheet to write (sangon, ruibio or genewiz)
+    #[arg(long)]
+    vendor: Vendor,
+    /// Primer for templates listed without one
+    #[arg(short, long)]
+    primer: Option<String>,
+    /// Plate format, 96 or 384
+    #[arg(long, default_value = "96")]
+    plate: PlateFormat,
+    /// First well to fill; samples go down each column
+    #[arg(long, value_name = "WELL", default_value = "A01", value_parser = parse_well)]
+    start_well: Well,
+    /// Submission date, YYYY-MM-DD; today by default
+    #[arg(long, value_parser = super::parse_date)]
+    date: Option<time::Date>,
+    /// Write the sheet as CSV to this file instead of standard output
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// Do not keep the order in the data directory
+    #[arg(long)]
+    no_save: bool,
+}
+
+fn parse_well(s: &str) -> Result<Well, String> {
+    Well::parse(&s.to_uppercase()).ok_or_else(|| format!("not a well: {}", s))
+}
+
+pub fn run(args: OrderArgs) -> anyhow::Result<()> {
+    let text = std::fs::read_to_string(&args.samples)?;
+    let samples = order::parse_sample_list(&text, args.primer.as_deref())
+        .map_err(|err| anyhow::anyhow!("{}: {}", args.samples.display(), err))?;
+    if samples.is_empty() {
+        anyhow::bail!("{}: no samples", args.samples.display());
+    }
+    let date = args
+        .date
+        .unwrap_or_else(|| time::OffsetDateTime::now_local().unwrap().date());
+    let order = Order::new(args.vendor, date, args.plate, args.start_well, &samples)?;
+    let form = order.form()?;
+    match &args.output {
+        Some(path) => std::fs::write(path, &form)?,
+        None => print!("{}", form),
+    }
+    if !args.no_save {
+        let dir = Order::default_dir()
+            .ok_or_else(|| anyhow::anyhow!("No data directory available for orders"))?;
+        let path = order.save(&dir)?;
+        eprintln!(
+            "Saved {} sample(s) to {}",
+            order.samples.len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use clap::Args;
+use sanger_rename::Vendor;
+use sanger_rename::primer::{self, PrimerCheck, PrimerLibrary};
+use sanger_rename::reference::ReferenceLibrary;
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct PrimersArgs {
+    /// Traces to check
+    #[arg(value_name = "FILE", required = true)]
+    files: Vec<PathBuf>,
+    /// Primer sequences as FASTA or a name/sequence table; defaults to primers.fasta in the config directory
+    #[arg(short, long, value_name = "FILE")]
+    primers: Option<PathBuf>,
+    /// FASTA or GenBank references (files or directories) to locate primers and reads on
+    #[arg(short, long = "reference", value_name = "PATH")]
+    references: Vec<PathBuf>,
+    /// Vendor naming scheme of files that are not renamed yet
+    #[arg(long)]
+    vendor: Option<Vendor>,
+    /// Write each read as FASTA into this directory, reverse reads reverse complemented
+    #[arg(long, value_name = "DIR")]
+    export: Option<PathBuf>,
+    /// Write mixed positions of exported reads as IUPAC codes, calling a position mixed when
+    /// the secondary peak reaches this fraction of the primary
+    #[arg(long, value_name = "FRACTION", num_args = 0..=1, default_missing_value = "0.33")]
+    mixed_fraction: Option<f64>,
+    /// Print results as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct PrimerReport {
+    path: PathBuf,
+    template: Option<String>,
+    primer: Option<String>,
+    check: PrimerCheck,
+}
+
+pub fn run(args: PrimersArgs) -> anyhow::Result<()> {
+    let primers = match &args.primers {
+        Some(path) => PrimerLibrary::load(path)?,
+        None => PrimerLibrary::load_default()?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "No primer library, use --primers or create primers.fasta in the config directory"
+            )
+        })?,
+    };
+    let references = if args.references.is_empty() {
+        None
+    } else {
+        Some(ReferenceLibrary::load(&args.references)?)
+    };
+    if let Some(dir) = &args.export {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let config = super::load_config(&args.files)?;
+    let mut reports = Vec::new();
+    let mut failed = 0;
+    for path in &args.files {
+        let fields = super::name_fields(path, args.vendor, &config);
+        let check = match &fields {
+            Some((template, primer_name)) => {
+                primer::check_trace(path, template, primer_name, &primers, references.as_ref())
+            }
+            None => PrimerCheck {
+                status: primer::PrimerStatus::Unverified,
+                strand: None,
+                note: "no primer name, use --vendor".to_string(),
+            },
+        };
+        if let Some(dir) = &args.export
+            && let Err(err) =
+                primer::write_oriented_fasta(path, check.strand, args.mixed_fraction, dir)
+        {
+            eprintln!("{}: {}", path.display(), err);
+            failed += 1;
+        }
+        let (template, primer) = fields.unzip();
+        reports.push(PrimerReport {
+            path: path.clone(),
+            template,
+            primer,
+            check,
+        });
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        for report in reports {
+            println!(
+                "{}\t{}\t{}",
+                report.path.display(),
+                report.primer.as_deref().unwrap_or("-"),
+                report.check
+            );
+        }
+    }
+    super::check_failures(failed, args.files.len(), "could not be exported")
+}
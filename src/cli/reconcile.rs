@@ -0,0 +1,60 @@
+use clap::Args;
+use sanger_rename::order::Order;
+use sanger_rename::reconcile::{self, DeliveredFile};
+use sanger_rename::{SangerFilename, Vendor};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct ReconcileArgs {
+    /// Delivered traces, as named by the vendor
+    #[arg(value_name = "FILE", required = true)]
+    files: Vec<PathBuf>,
+    /// Vendor naming scheme of the files
+    #[arg(long)]
+    vendor: Vendor,
+    /// Order saved by the order command; the vendor's latest saved order by default
+    #[arg(long, value_name = "FILE")]
+    order: Option<PathBuf>,
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: ReconcileArgs) -> anyhow::Result<()> {
+    let path = match args.order {
+        Some(path) => path,
+        None => Order::default_dir()
+            .map(|dir| Order::latest(&dir, Some(args.vendor)))
+            .transpose()?
+            .flatten()
+            .ok_or_else(|| anyhow::anyhow!("No saved {} order, use --order", args.vendor))?,
+    };
+    let order = Order::load(&path)?;
+    if let Some(warning) = reconcile::vendor_warning(&order, args.vendor) {
+        eprintln!("Warning: {}", warning);
+    }
+    let files: Vec<DeliveredFile> = args
+        .files
+        .iter()
+        .map(|file| {
+            let sanger_fn = SangerFilename::new(&file.to_string_lossy(), args.vendor);
+            DeliveredFile::from_sanger_filename(&sanger_fn)
+        })
+        .collect();
+    let result = reconcile::reconcile(&order, &files);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(());
+    }
+    println!(
+        "Order {} ({} {}): {}",
+        path.display(),
+        order.vendor,
+        order.date,
+        result.summary()
+    );
+    for discrepancy in &result.discrepancies {
+        println!("  {}", discrepancy);
+    }
+    Ok(())
+}
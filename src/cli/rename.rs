@@ -0,0 +1,74 @@
+use sanger_rename::catalog::{Catalog, RenameRecord};
+use sanger_rename::config::Config;
+use sanger_rename::mapping::NameMappings;
+use sanger_rename::plan::{self, PlannedAction};
+use sanger_rename::{FieldRule, manifest, move_file};
+use std::path::PathBuf;
+
+/// Rename `filenames` as `--dry-run` plans it, without opening the TUI
+///
+/// Nothing is renamed unless every file can be, and a failed move puts the
+/// files moved before it back. The renames are recorded in the catalog and
+/// in a manifest in each folder renamed into.
+pub fn run(filenames: &[String], config: &Config, date: Option<time::Date>) -> anyhow::Result<()> {
+    let date = date.unwrap_or_else(|| time::OffsetDateTime::now_local().unwrap().date());
+    let mut mappings = NameMappings::load_saved()?;
+    mappings.extend(&config.mappings());
+    let field_rule = FieldRule::load_saved()?;
+    let plans = plan::plan(filenames, config, &mappings, field_rule, date);
+    let failing = plans
+        .iter()
+        .filter(|plan| matches!(plan.action, PlannedAction::Fail | PlannedAction::Missing))
+        .count();
+    if failing > 0 {
+        eprint!("{}", plan::to_table(&plans));
+        anyhow::bail!(
+            "{} of {} file(s) cannot be renamed, nothing was renamed",
+            failing,
+            plans.len()
+        );
+    }
+
+    let mut moved: Vec<(PathBuf, RenameRecord)> = Vec::new();
+    for planned in plans
+        .iter()
+        .filter(|plan| plan.action == PlannedAction::Rename)
+    {
+        let result = plan::prepare(&planned.source, config, &mappings, field_rule, date)
+            .map_err(|(_, reason)| anyhow::anyhow!(reason))
+            .and_then(|sanger_fn| sanger_fn.move_to_standardized_name());
+        match result {
+            Ok(Some(record)) => moved.push((planned.source.clone(), record)),
+            Ok(None) => {}
+            Err(err) => {
+                for (source, record) in moved.iter().rev() {
+                    if let Err(err) = move_file(&record.new_path, source) {
+                        eprintln!("Could not move {} back: {}", record.new_path.display(), err);
+                    }
+                }
+                anyhow::bail!("{}: {}", planned.source.display(), err);
+            }
+        }
+    }
+
+    let catalog = Catalog::open_default();
+    let records: Vec<RenameRecord> = moved.into_iter().map(|(_, record)| record).collect();
+    for record in &records {
+        println!("{} → {}", record.original_name, record.new_path.display());
+        let appended = catalog
+            .as_ref()
+            .map_err(|err| err.to_string())
+            .and_then(|catalog| catalog.append(record).map_err(|err| err.to_string()));
+        if let Err(err) = appended {
+            eprintln!("Could not record the rename in the catalog: {}", err);
+        }
+    }
+    let written = manifest::write_batch(&records);
+    for (line, (_, written)) in manifest::summarize(&written).iter().zip(&written) {
+        match written {
+            Ok(_) => println!("{}", line),
+            Err(_) => eprintln!("{}", line),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use clap::Args;
+use sanger_rename::scf;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct ScfArgs {
+    /// ABIF traces to convert
+    #[arg(value_name = "FILE", required = true)]
+    files: Vec<PathBuf>,
+    /// Directory for the SCF files; next to each trace by default
+    #[arg(short, long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+    /// Replace existing SCF files
+    #[arg(long)]
+    overwrite: bool,
+}
+
+pub fn run(args: ScfArgs) -> anyhow::Result<()> {
+    if let Some(dir) = &args.output_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut failed = 0;
+    for path in &args.files {
+        let dir = match &args.output_dir {
+            Some(dir) => dir.as_path(),
+            None => path.parent().unwrap_or(Path::new("")),
+        };
+        match scf::convert(path, dir, args.overwrite) {
+            Ok(output) => println!("{}\t{}", path.display(), output.display()),
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                failed += 1;
+            }
+        }
+    }
+    super::check_failures(failed, args.files.len(), "could not be converted")
+}
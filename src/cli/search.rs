@@ -0,0 +1,80 @@
+use clap::Args;
+use sanger_rename::Vendor;
+use sanger_rename::catalog::Catalog;
+use sanger_rename::search::{self, FieldPattern, SearchQuery};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct SearchArgs {
+    /// Directories to scan for standardized file names; searches the catalog if none are given
+    #[arg(value_name = "ROOT")]
+    roots: Vec<PathBuf>,
+    /// Template name glob, e.g. 'K528*' (case-insensitive)
+    #[arg(short, long)]
+    template: Option<String>,
+    /// Primer name glob, e.g. 'T7'
+    #[arg(short, long)]
+    primer: Option<String>,
+    /// Treat template and primer patterns as regular expressions instead of globs (case-insensitive)
+    #[arg(long)]
+    regex: bool,
+    /// Earliest date, YYYY-MM-DD
+    #[arg(long, value_parser = super::parse_date)]
+    since: Option<time::Date>,
+    /// Latest date, YYYY-MM-DD
+    #[arg(long, value_parser = super::parse_date)]
+    until: Option<time::Date>,
+    /// Only files renamed from this vendor (requires a catalog entry)
+    #[arg(long)]
+    vendor: Option<Vendor>,
+    /// Catalog file to use instead of the default one
+    #[arg(long, value_name = "FILE")]
+    catalog: Option<PathBuf>,
+    /// Print hits as JSON instead of one path per line
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: SearchArgs) -> anyhow::Result<()> {
+    let pattern = |p: &str| {
+        if args.regex {
+            FieldPattern::regex(p)
+        } else {
+            FieldPattern::glob(p)
+        }
+    };
+    let query = SearchQuery {
+        template: args.template.as_deref().map(pattern).transpose()?,
+        primer: args.primer.as_deref().map(pattern).transpose()?,
+        since: args.since,
+        until: args.until,
+        vendor: args.vendor,
+    };
+    let catalog = match &args.catalog {
+        Some(path) => Some(Catalog::open(path)),
+        None => Catalog::open_default().ok(),
+    };
+    let hits = if args.roots.is_empty() {
+        let catalog = catalog.ok_or_else(|| {
+            anyhow::anyhow!("No data directory available for the catalog; give --catalog or ROOT")
+        })?;
+        search::search_catalog(&catalog, &query)?
+    } else {
+        let config = super::load_config(&args.roots)?;
+        search::scan_roots(
+            &args.roots,
+            catalog.as_ref(),
+            config.name.template.as_ref(),
+            &query,
+        )?
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&hits)?);
+    } else {
+        for hit in hits {
+            println!("{}", hit.path.display());
+        }
+    }
+    Ok(())
+}
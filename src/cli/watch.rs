@@ -0,0 +1,211 @@
+use clap::Args;
+use notify::{EventKind, RecursiveMode, Watcher};
+use sanger_rename::catalog::{Catalog, RenameRecord};
+use sanger_rename::config::Config;
+use sanger_rename::manifest;
+use sanger_rename::mapping::NameMappings;
+use sanger_rename::watch::{self, QueuedTrace, WatchQueue};
+use sanger_rename::{FieldRule, Vendor};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Quiet time after the last event before new files count as complete
+const SETTLE: Duration = Duration::from_secs(2);
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// Folder to watch; the Downloads folder by default
+    #[arg(value_name = "DIR")]
+    dir: Option<PathBuf>,
+    /// Vendor of every new trace instead of detecting it from the file name
+    #[arg(long)]
+    vendor: Option<Vendor>,
+    /// Rename new traces right away instead of queueing them for `sanger_rename --queued`
+    #[arg(long)]
+    auto: bool,
+    /// Also handle the traces and ZIPs already in the folder
+    #[arg(long)]
+    existing: bool,
+}
+
+struct Inbox {
+    vendor: Option<Vendor>,
+    auto: bool,
+    mappings: NameMappings,
+    config: Config,
+    field_rule: Option<FieldRule>,
+    catalog: Catalog,
+    queue: WatchQueue,
+    seen: HashSet<PathBuf>,
+    /// Renames since the last manifest was written
+    renamed: Vec<RenameRecord>,
+}
+
+impl Inbox {
+    fn handle(&mut self, path: &Path) {
+        if !path.is_file() || !self.seen.insert(path.to_path_buf()) {
+            return;
+        }
+        if watch::is_zip_file(path) {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            match watch::extract_traces(path, dir) {
+                Ok(extracted) => {
+                    if !extracted.traces.is_empty() {
+                        println!(
+                            "Extracted {} trace(s) from {}",
+                            extracted.traces.len(),
+                            path.display()
+                        );
+                    }
+                    for clash in &extracted.clashes {
+                        eprintln!(
+                            "{}: not extracted from {}, the file already exists",
+                            clash.display(),
+                            path.display()
+                        );
+                    }
+                    for trace in extracted.traces {
+                        self.handle(&trace);
+                    }
+                }
+                Err(err) => eprintln!("{}: {}", path.display(), err),
+            }
+            return;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if !watch::is_trace_file(path) || self.config.is_renamed(&name) {
+            return;
+        }
+        let mut sanger_fn = match watch::prepare(path, self.vendor, self.field_rule) {
+            Ok(sanger_fn) => sanger_fn,
+            Err(reason) => {
+                eprintln!("{}: left alone, {}", path.display(), reason);
+                return;
+            }
+        };
+        self.config.apply(&mut sanger_fn);
+        self.mappings.apply(&mut sanger_fn);
+        if !self.auto {
+            let queued = QueuedTrace {
+                path: path.to_path_buf(),
+                vendor: sanger_fn.get_vendor_name(),
+            };
+            match self.queue.push(&queued) {
+                Ok(()) => println!(
+                    "Queued {} ({}) as {}",
+                    name,
+                    queued.vendor,
+                    sanger_fn.get_new_name()
+                ),
+                Err(err) => eprintln!("{}: {}", path.display(), err),
+            }
+            return;
+        }
+        match sanger_fn.move_to_standardized_name() {
+            Ok(Some(record)) => {
+                println!("{} → {}", name, record.new_path.display());
+                if let Err(err) = self.catalog.append(&record) {
+                    eprintln!("Could not record the rename in the catalog: {}", err);
+                }
+                self.renamed.push(record);
+            }
+            Ok(None) => {}
+            Err(err) => eprintln!("{}: {}", path.display(), err),
+        }
+    }
+
+    /// Write the manifests of the renames since the last ones into their folders
+    fn write_manifest(&mut self) {
+        let written = manifest::write_batch(&std::mem::take(&mut self.renamed));
+        for (line, (_, written)) in manifest::summarize(&written).iter().zip(&written) {
+            match written {
+                Ok(_) => println!("{}", line),
+                Err(_) => eprintln!("{}", line),
+            }
+        }
+    }
+}
+
+pub fn run(args: WatchArgs) -> anyhow::Result<()> {
+    let dir = match args.dir {
+        Some(dir) => dir,
+        None => dirs::download_dir()
+            .ok_or_else(|| anyhow::anyhow!("No Downloads folder, give the folder to watch"))?,
+    };
+    if !dir.is_dir() {
+        anyhow::bail!("{} is not a folder", dir.display());
+    }
+    let config = Config::load_for(&dir)?;
+    let mut mappings = NameMappings::load_saved()?;
+    mappings.extend(&config.mappings());
+    let mut inbox = Inbox {
+        vendor: args.vendor,
+        auto: args.auto,
+        mappings,
+        config,
+        field_rule: FieldRule::load_saved()?,
+        catalog: Catalog::open_default()?,
+        queue: WatchQueue::open_default()?,
+        seen: HashSet::new(),
+        renamed: Vec::new(),
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+    println!(
+        "Watching {} ({}), Ctrl+C to stop",
+        dir.display(),
+        if args.auto {
+            "renaming new traces"
+        } else {
+            "queueing new traces for `sanger_rename --queued`"
+        }
+    );
+    if args.existing {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        paths.sort();
+        for path in paths {
+            inbox.handle(&path);
+        }
+        inbox.write_manifest();
+    }
+
+    let mut pending: Vec<PathBuf> = Vec::new();
+    loop {
+        // Downloads arrive in pieces, so wait until the folder is quiet
+        let event = if pending.is_empty() {
+            rx.recv()
+                .map_err(|_| anyhow::anyhow!("Stopped watching {}", dir.display()))?
+        } else {
+            match rx.recv_timeout(SETTLE) {
+                Ok(event) => event,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    for path in std::mem::take(&mut pending) {
+                        inbox.handle(&path);
+                    }
+                    inbox.write_manifest();
+                    continue;
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    anyhow::bail!("Stopped watching {}", dir.display())
+                }
+            }
+        };
+        match event {
+            Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                for path in event.paths {
+                    if !pending.contains(&path) {
+                        pending.push(path);
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(err) => eprintln!("Watch error: {}", err),
+        }
+    }
+}
@@ -0,0 +1,353 @@
+//! User defaults from `config.toml` and per-project `.sanger_rename.toml` files
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::mapping::NameMappings;
+use crate::mixed_peaks;
+use crate::name_template::NameTemplate;
+use crate::sanger_filename::{SangerFilename, Vendor};
+use crate::sanitize::{FieldCase, SanitizeRules};
+use crate::standardized_name::StandardizedName;
+
+/// Name of the per-project configuration, looked up from the input files upward
+pub const PROJECT_FILE: &str = ".sanger_rename.toml";
+
+/// Mean quality from which a read counts as good
+pub const DEFAULT_GOOD_QUALITY: f64 = 30.0;
+/// Mean quality below which a read counts as failed
+pub const DEFAULT_POOR_QUALITY: f64 = 20.0;
+
+/// How the template and primer names are written in the new names
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NameStyle {
+    pub case: Option<FieldCase>,
+    pub transliterate: Option<bool>,
+    /// Layout of the new names, such as `{date}_{template}_{primer}`
+    pub template: Option<NameTemplate>,
+    /// Characters replaced in template and primer names, on top of the built-in `.` and space
+    #[serde(deserialize_with = "deserialize_replacements")]
+    pub replacements: BTreeMap<char, String>,
+    /// Replacement for characters that are invalid in file names
+    #[serde(deserialize_with = "deserialize_replacement")]
+    pub invalid_replacement: Option<String>,
+    /// Unicode NFC normalization of template and primer names, on by default
+    pub nfc: Option<bool>,
+}
+
+fn check_replacement<E: serde::de::Error>(to: &str) -> Result<(), E> {
+    if SanitizeRules::is_safe_replacement(to) {
+        Ok(())
+    } else {
+        Err(E::custom(format!(
+            "replacement {:?} contains a dot or a character invalid in file names",
+            to
+        )))
+    }
+}
+
+fn deserialize_replacements<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<char, String>, D::Error> {
+    let replacements = BTreeMap::<char, String>::deserialize(deserializer)?;
+    for to in replacements.values() {
+        check_replacement(to)?;
+    }
+    Ok(replacements)
+}
+
+fn deserialize_replacement<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    let replacement = String::deserialize(deserializer)?;
+    check_replacement(&replacement)?;
+    Ok(Some(replacement))
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QcThresholds {
+    /// Secondary to primary peak height above which a position counts as mixed
+    pub mixed_fraction: Option<f64>,
+    pub good_quality: Option<f64>,
+    pub poor_quality: Option<f64>,
+}
+
+impl QcThresholds {
+    pub fn mixed_fraction(&self) -> f64 {
+        self.mixed_fraction
+            .unwrap_or(mixed_peaks::DEFAULT_SECONDARY_FRACTION)
+    }
+
+    pub fn good_quality(&self) -> f64 {
+        self.good_quality.unwrap_or(DEFAULT_GOOD_QUALITY)
+    }
+
+    pub fn poor_quality(&self) -> f64 {
+        self.poor_quality.unwrap_or(DEFAULT_POOR_QUALITY)
+    }
+}
+
+/// Settings read from configuration files; unset values keep the built-in defaults
+#[derive(Clone, PartialEq, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Vendor highlighted in the TUI and used by `--dry-run` without `--vendor`
+    pub vendor: Option<Vendor>,
+    /// Folder the renamed traces are moved into, relative to the config file
+    pub output_dir: Option<PathBuf>,
+    pub name: NameStyle,
+    /// Primer renames, applied after the saved mappings
+    pub primers: BTreeMap<String, String>,
+    /// Template renames, applied after the saved mappings
+    pub templates: BTreeMap<String, String>,
+    pub qc: QcThresholds,
+}
+
+impl Config {
+    /// `config.toml` in the user's configuration directory
+    pub fn user_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("sanger_rename").join("config.toml"))
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let mut config: Config = toml::from_str(&content)
+            .map_err(|err| anyhow::anyhow!("{}: {}", path.display(), err))?;
+        if let (Some(dir), Some(parent)) = (&config.output_dir, path.parent())
+            && dir.is_relative()
+        {
+            config.output_dir = Some(parent.join(dir));
+        }
+        Ok(config)
+    }
+
+    /// The user configuration overridden by the project configuration found from `start` upward
+    pub fn load_for(start: &Path) -> anyhow::Result<Self> {
+        let mut config = match Self::user_path() {
+            Some(path) if path.exists() => Self::load(&path)?,
+            _ => Self::default(),
+        };
+        if let Some(path) = find_project_file(start) {
+            config.merge(Self::load(&path)?);
+        }
+        Ok(config)
+    }
+
+    /// Take the values set in `other`, and add its dictionaries to these
+    pub fn merge(&mut self, other: Config) {
+        self.vendor = other.vendor.or(self.vendor);
+        self.output_dir = other.output_dir.or(self.output_dir.take());
+        self.name.case = other.name.case.or(self.name.case);
+        self.name.transliterate = other.name.transliterate.or(self.name.transliterate);
+        self.name.template = other.name.template.or(self.name.template.take());
+        self.name.replacements.extend(other.name.replacements);
+        self.name.invalid_replacement = other
+            .name
+            .invalid_replacement
+            .or(self.name.invalid_replacement.take());
+        self.name.nfc = other.name.nfc.or(self.name.nfc);
+        self.primers.extend(other.primers);
+        self.templates.extend(other.templates);
+        self.qc.mixed_fraction = other.qc.mixed_fraction.or(self.qc.mixed_fraction);
+        self.qc.good_quality = other.qc.good_quality.or(self.qc.good_quality);
+        self.qc.poor_quality = other.qc.poor_quality.or(self.qc.poor_quality);
+    }
+
+    /// The `[primers]` and `[templates]` dictionaries
+    pub fn mappings(&self) -> NameMappings {
+        NameMappings {
+            primers: self.primers.clone(),
+            templates: self.templates.clone(),
+        }
+    }
+
+    /// Whether `name` is already standardized, or written with the configured name template
+    pub fn is_renamed(&self, name: &str) -> bool {
+        self.parse_renamed(name).is_some()
+    }
+
+    /// Read back a renamed file's name, trying the configured name template
+    /// before the default `date.template.primer` layout
+    pub fn parse_renamed(&self, name: &str) -> Option<StandardizedName> {
+        self.name
+            .template
+            .as_ref()
+            .and_then(|template| template.parse_name(name))
+            .or_else(|| StandardizedName::parse(name).ok())
+    }
+
+    /// `rules` with the `[name]` sanitization settings applied
+    pub fn sanitize_rules(&self, mut rules: SanitizeRules) -> SanitizeRules {
+        if let Some(case) = self.name.case {
+            rules.case = case;
+        }
+        for (from, to) in &self.name.replacements {
+            rules = rules.with_replacement(*from, to);
+        }
+        if let Some(replacement) = &self.name.invalid_replacement {
+            rules.invalid_replacement = replacement.clone();
+        }
+        if let Some(nfc) = self.name.nfc {
+            rules.normalize_nfc = nfc;
+        }
+        rules
+    }
+
+    /// Apply the naming style and output folder to a parsed file
+    ///
+    /// A file that already has a standardized name only takes the case, so
+    /// re-normalizing it never rewrites its template or primer name.
+    pub fn apply(&self, sanger_fn: &mut SangerFilename) {
+        if self.name.template.is_some() {
+            sanger_fn.set_name_template(self.name.template.clone());
+        }
+        if self.output_dir.is_some() {
+            sanger_fn.set_output_dir(self.output_dir.clone());
+        }
+        if sanger_fn.is_standardized() {
+            if let Some(case) = self.name.case {
+                let mut rules = sanger_fn.get_sanitize_rules().clone();
+                rules.case = case;
+                sanger_fn.set_sanitize_rules(rules);
+            }
+            return;
+        }
+        sanger_fn.set_sanitize_rules(self.sanitize_rules(sanger_fn.get_sanitize_rules().clone()));
+        if let Some(transliterate) = self.name.transliterate {
+            sanger_fn.set_transliterate(transliterate);
+        }
+    }
+}
+
+/// The nearest [`PROJECT_FILE`] in `start` (a file or folder) or one of its parents
+pub fn find_project_file(start: &Path) -> Option<PathBuf> {
+    let start = if start.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        start
+    };
+    let start = std::fs::canonicalize(start).ok()?;
+    let dir = if start.is_dir() {
+        start.as_path()
+    } else {
+        start.parent()?
+    };
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_config() {
+        let dir = std::env::temp_dir().join("sanger_rename_config");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("plate1")).unwrap();
+        std::fs::write(
+            dir.join(PROJECT_FILE),
+            "vendor = \"ruibio\"\noutput_dir = \"renamed\"\n\n[name]\ncase = \"upper\"\n\n\
+             [primers]\nC1 = \"T7\"\n\n[qc]\npoor_quality = 15.0\n",
+        )
+        .unwrap();
+        let trace = dir.join("plate1").join("K528-1.C1.34781340.B08.ab1");
+        std::fs::write(&trace, b"trace").unwrap();
+
+        let found = find_project_file(&trace).unwrap();
+        let mut config = Config {
+            vendor: Some(Vendor::Genewiz),
+            ..Config::default()
+        };
+        config.merge(Config::load(&found).unwrap());
+        assert_eq!(config.vendor, Some(Vendor::Ruibio));
+        assert_eq!(config.qc.poor_quality(), 15.0);
+        assert_eq!(config.qc.good_quality(), DEFAULT_GOOD_QUALITY);
+
+        let mut sanger_fn = SangerFilename::new(&trace.to_string_lossy(), Vendor::Ruibio);
+        config.mappings().apply(&mut sanger_fn);
+        config.apply(&mut sanger_fn);
+        sanger_fn
+            .set_date(time::macros::date!(2025 - 06 - 01))
+            .unwrap();
+        assert_eq!(
+            sanger_fn.get_standardized_path(),
+            found.parent().unwrap().join("renamed/250601.K528-1.T7.ab1")
+        );
+        sanger_fn.move_to_standardized_name().unwrap();
+        assert!(dir.join("renamed/250601.K528-1.T7.ab1").exists());
+
+        assert!(toml::from_str::<Config>("vendor = \"acme\"").is_err());
+    }
+
+    #[test]
+    fn test_config_rejects_unknown_keys() {
+        assert!(toml::from_str::<Config>("vendr = \"ruibio\"").is_err());
+        assert!(toml::from_str::<Config>("[primer]\nC1 = \"T7\"").is_err());
+        assert!(toml::from_str::<Config>("[name]\ntemplate = \"{date}_{template}\"").is_err());
+        assert!(toml::from_str::<Config>("[name.replacements]\n\"+\" = \".\"").is_err());
+        assert!(toml::from_str::<Config>("[name.replacements]\n\"ab\" = \"-\"").is_err());
+    }
+
+    #[test]
+    fn test_replacements_config() {
+        let config: Config = toml::from_str(
+            "[name]\ninvalid_replacement = \"-\"\n\n[name.replacements]\n\" \" = \"\"\n\"+\" = \"p\"\n",
+        )
+        .unwrap();
+        let mut sanger_fn = SangerFilename::new("K528 1+2.C1.34781340.B08.ab1", Vendor::Ruibio);
+        config.apply(&mut sanger_fn);
+        sanger_fn
+            .set_date(time::macros::date!(2025 - 06 - 01))
+            .unwrap();
+        assert_eq!(sanger_fn.get_standardized_name(), "250601.K5281p2.C1");
+        let rules = sanger_fn.get_sanitize_rules();
+        assert_eq!(rules.sanitize_field("a:b"), "a-b");
+        assert!(rules.normalize_nfc);
+
+        // Re-normalizing a standardized name only changes the date and casing
+        let config: Config =
+            toml::from_str("[name]\ncase = \"upper\"\n\n[name.replacements]\n\"-\" = \"_\"\n")
+                .unwrap();
+        let mut renamed = SangerFilename::new("250101.k528-1.c1.ab1", Vendor::Ruibio);
+        config.apply(&mut renamed);
+        renamed.set_renormalize(true);
+        renamed
+            .set_date(time::macros::date!(2025 - 06 - 01))
+            .unwrap();
+        assert_eq!(renamed.get_standardized_name(), "250601.K528-1.C1");
+
+        let config: Config = toml::from_str("[name]\nnfc = false").unwrap();
+        assert!(
+            !config
+                .sanitize_rules(SanitizeRules::default())
+                .normalize_nfc
+        );
+    }
+
+    #[test]
+    fn test_name_template_config() {
+        let config: Config =
+            toml::from_str("[name]\ntemplate = \"{date}_{template}_{primer}_{well}\"").unwrap();
+        let mut sanger_fn = SangerFilename::new("K528-1.C1.34781340.B08.ab1", Vendor::Ruibio);
+        config.apply(&mut sanger_fn);
+        sanger_fn
+            .set_date(time::macros::date!(2025 - 06 - 01))
+            .unwrap();
+        assert_eq!(
+            sanger_fn.get_standardized_path(),
+            PathBuf::from("250601_K528-1_C1_B08.ab1")
+        );
+
+        // Names written with the template are left alone
+        assert!(config.is_renamed("250601_K528-1_C1_B08.ab1"));
+        let mut renamed = SangerFilename::new("250601_K528-1_C1_B08.ab1", Vendor::Ruibio);
+        config.apply(&mut renamed);
+        assert!(renamed.is_skipped());
+        assert_eq!(renamed.get_template_name(), "K528-1");
+    }
+}
@@ -0,0 +1,389 @@
+//! Indel spectra of edited cell pools, decomposed from trace data like TIDE
+//!
+//! Downstream of a cut site an edited pool's trace is a mix of the control
+//! trace shifted by each indel size. The share of every shift is estimated
+//! with non-negative least squares on the normalized peak heights.
+
+use serde::Serialize;
+
+use crate::abif::Trace;
+use crate::align::{self, AlignOp, Strand};
+
+/// Largest insertion or deletion considered by default
+pub const DEFAULT_MAX_INDEL: usize = 10;
+/// Default number of bases in the decomposition window
+pub const DEFAULT_WINDOW: usize = 100;
+/// Bases between the cut site and the window, beyond the largest indel
+const WINDOW_GAP: usize = 5;
+/// Bases upstream of the cut site that are aligned to find the trace offset
+const ALIGNMENT_REGION: usize = 150;
+const ITERATIONS: usize = 2000;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub struct IndelFrequency {
+    /// Inserted bases if positive, deleted bases if negative
+    pub size: i32,
+    /// Share of the pool, the shares of all sizes summing to 1
+    pub frequency: f64,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct IndelSpectrum {
+    /// Cut site in the control's base calls, 1-based: the cut is after this base
+    pub cut_site: usize,
+    /// Decomposed region of the control's base calls, 1-based and inclusive
+    pub window: (usize, usize),
+    /// How much of the edited signal the model explains
+    pub r_squared: f64,
+    /// Share of the pool carrying any indel
+    pub efficiency: f64,
+    /// Every size considered, from the largest deletion to the largest insertion
+    pub indels: Vec<IndelFrequency>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DecomposeOptions {
+    /// 0-based index of the last base before the cut, in the control's base calls
+    pub cut: usize,
+    pub max_indel: usize,
+    pub window: usize,
+}
+
+impl DecomposeOptions {
+    pub fn new(cut: usize) -> Self {
+        Self {
+            cut,
+            max_indel: DEFAULT_MAX_INDEL,
+            window: DEFAULT_WINDOW,
+        }
+    }
+}
+
+/// Cut site of a Cas9 guide (protospacer without PAM) in `sequence`
+///
+/// Returns the 0-based index of the last base before the cut, which lies
+/// three bases upstream of the PAM.
+pub fn find_cut_site(guide: &[u8], sequence: &[u8]) -> Option<usize> {
+    if guide.len() < 4 || guide.len() > sequence.len() {
+        return None;
+    }
+    let matches = |needle: &[u8]| {
+        sequence
+            .windows(needle.len())
+            .position(|w| w.eq_ignore_ascii_case(needle))
+    };
+    if let Some(position) = matches(guide) {
+        return Some(position + guide.len() - 4);
+    }
+    // On the other strand the PAM lies upstream, so the cut is three bases into the match
+    matches(&align::reverse_complement(guide)).map(|position| position + 2)
+}
+
+/// Normalized height of each channel at each base call
+fn peak_signals(trace: &Trace) -> Vec<[f64; 4]> {
+    trace
+        .peak_locations
+        .iter()
+        .map(|&peak| {
+            let mut signal = [0.0; 4];
+            for (value, channel) in signal.iter_mut().zip(&trace.channels) {
+                *value = channel.get(peak as usize).copied().unwrap_or(0).max(0) as f64;
+            }
+            let total: f64 = signal.iter().sum();
+            if total > 0.0 {
+                signal.iter_mut().for_each(|v| *v /= total);
+            }
+            signal
+        })
+        .collect()
+}
+
+/// Signals in the base order of `reference`, so traces with different channel orders compare
+fn reorder(signals: Vec<[f64; 4]>, from: [u8; 4], to: [u8; 4]) -> Vec<[f64; 4]> {
+    let map: Vec<usize> = to
+        .iter()
+        .map(|base| from.iter().position(|b| b == base).unwrap_or(0))
+        .collect();
+    signals
+        .into_iter()
+        .map(|s| std::array::from_fn(|i| s[map[i]]))
+        .collect()
+}
+
+/// Edited base index minus control base index, from the region upstream of the cut
+fn trace_offset(control: &[u8], edited: &[u8], cut: usize) -> anyhow::Result<isize> {
+    let start = cut.saturating_sub(ALIGNMENT_REGION);
+    let region = &control[start..=cut.min(control.len() - 1)];
+    let matching = |read: &[u8]| {
+        align::local_align(read, region, Strand::Forward)
+            .filter(|a| a.identity() > 0.8 && a.matches >= 20)
+    };
+    let Some(alignment) = matching(edited) else {
+        if matching(&align::reverse_complement(edited)).is_some() {
+            anyhow::bail!(
+                "edited trace reads the other strand than the control, sequence both with the same primer"
+            );
+        }
+        anyhow::bail!("edited trace does not match the control before the cut");
+    };
+    // The offset of the last match, closest to the cut
+    let (mut i, mut j) = (alignment.read_start, alignment.reference_start);
+    let mut offset = None;
+    for op in &alignment.ops {
+        match op {
+            AlignOp::Match => {
+                offset = Some(i as isize - (start + j) as isize);
+                i += 1;
+                j += 1;
+            }
+            AlignOp::Mismatch | AlignOp::Ambiguous => {
+                i += 1;
+                j += 1;
+            }
+            AlignOp::Insertion => i += 1,
+            AlignOp::Deletion => j += 1,
+        }
+    }
+    offset.ok_or_else(|| anyhow::anyhow!("edited trace does not match the control"))
+}
+
+/// Non-negative least squares by cyclic coordinate descent
+fn nnls(columns: &[Vec<f64>], target: &[f64]) -> Vec<f64> {
+    let mut weights = vec![0.0; columns.len()];
+    let mut residual = target.to_vec();
+    let norms: Vec<f64> = columns
+        .iter()
+        .map(|c| c.iter().map(|v| v * v).sum())
+        .collect();
+    for _ in 0..ITERATIONS {
+        let mut change: f64 = 0.0;
+        for (k, column) in columns.iter().enumerate() {
+            if norms[k] == 0.0 {
+                continue;
+            }
+            let gradient: f64 = column.iter().zip(&residual).map(|(c, r)| c * r).sum();
+            let updated = (weights[k] + gradient / norms[k]).max(0.0);
+            let delta = updated - weights[k];
+            if delta != 0.0 {
+                residual
+                    .iter_mut()
+                    .zip(column)
+                    .for_each(|(r, c)| *r -= delta * c);
+                weights[k] = updated;
+                change = change.max(delta.abs());
+            }
+        }
+        if change < 1e-9 {
+            break;
+        }
+    }
+    weights
+}
+
+/// Decompose the edited trace into shifted copies of the control trace
+pub fn decompose(
+    control: &Trace,
+    edited: &Trace,
+    options: DecomposeOptions,
+) -> anyhow::Result<IndelSpectrum> {
+    let DecomposeOptions {
+        cut,
+        max_indel,
+        window,
+    } = options;
+    let control_len = control.bases.len().min(control.peak_locations.len());
+    if cut + 1 >= control_len {
+        anyhow::bail!(
+            "cut site {} is beyond the control's {} bases",
+            cut + 1,
+            control_len
+        );
+    }
+    // Every shifted copy of the window lies within the control, which also
+    // keeps the window arithmetic below from overflowing
+    if max_indel >= control_len || window > control_len {
+        anyhow::bail!(
+            "indels of up to {} bases and a window of {} do not fit the control's {} bases",
+            max_indel,
+            window,
+            control_len
+        );
+    }
+    let offset = trace_offset(&control.bases[..control_len], &edited.bases, cut)?;
+    let control_signals = peak_signals(control);
+    let edited_signals = reorder(peak_signals(edited), edited.base_order, control.base_order);
+
+    // The window is laid out on the control; every shifted copy has to stay within both traces
+    let start = cut + max_indel + WINDOW_GAP;
+    let positions: Vec<usize> = (start..start + window)
+        .filter(|&c| {
+            let edited_index = c as isize + offset;
+            c + max_indel < control_len
+                && edited_index >= 0
+                && (edited_index as usize) < edited_signals.len()
+        })
+        .collect();
+    if positions.len() < 10 {
+        anyhow::bail!("traces are too short for a decomposition window after the cut");
+    }
+
+    let sizes: Vec<i32> = (-(max_indel as i32)..=max_indel as i32).collect();
+    let target: Vec<f64> = positions
+        .iter()
+        .flat_map(|&c| edited_signals[(c as isize + offset) as usize])
+        .collect();
+    let columns: Vec<Vec<f64>> = sizes
+        .iter()
+        .map(|&size| {
+            positions
+                .iter()
+                .flat_map(|&c| control_signals[(c as isize - size as isize) as usize])
+                .collect()
+        })
+        .collect();
+    let weights = nnls(&columns, &target);
+
+    let mean = target.iter().sum::<f64>() / target.len() as f64;
+    let total_variance: f64 = target.iter().map(|t| (t - mean).powi(2)).sum();
+    let residual: f64 = target
+        .iter()
+        .enumerate()
+        .map(|(row, t)| {
+            let predicted: f64 = columns.iter().zip(&weights).map(|(c, w)| c[row] * w).sum();
+            (t - predicted).powi(2)
+        })
+        .sum();
+    let r_squared = if total_variance > 0.0 {
+        (1.0 - residual / total_variance).max(0.0)
+    } else {
+        0.0
+    };
+    let total: f64 = weights.iter().sum();
+    let indels: Vec<IndelFrequency> = sizes
+        .iter()
+        .zip(&weights)
+        .map(|(&size, &weight)| IndelFrequency {
+            size,
+            frequency: if total > 0.0 { weight / total } else { 0.0 },
+        })
+        .collect();
+    let efficiency = indels
+        .iter()
+        .filter(|i| i.size != 0)
+        .map(|i| i.frequency)
+        .sum();
+    Ok(IndelSpectrum {
+        cut_site: cut + 1,
+        window: (positions[0] + 1, positions[positions.len() - 1] + 1),
+        r_squared,
+        efficiency,
+        indels,
+    })
+}
+
+impl IndelSpectrum {
+    /// Human readable report listing sizes above `min_frequency`
+    pub fn report(&self, min_frequency: f64) -> String {
+        let mut report = format!(
+            "cut site {}, window {}..{}, R² {:.3}, editing efficiency {:.1}%\n",
+            self.cut_site,
+            self.window.0,
+            self.window.1,
+            self.r_squared,
+            self.efficiency * 100.0
+        );
+        for indel in self.indels.iter().filter(|i| i.frequency >= min_frequency) {
+            let bar = "#".repeat((indel.frequency * 50.0).round() as usize);
+            report.push_str(&format!(
+                "{:>+4} {:>6.1}% {}\n",
+                indel.size,
+                indel.frequency * 100.0,
+                bar
+            ));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AMPLICON: &str = "ATGGTGAGCAAGGGCGAGGAGCTGTTCACCGGGGTGGTGCCCATCCTGGTCGAGCTGGACGGCGACGTAAACGGCCACAAGTTCAGCGTGTCCGGCGAGGGCGAGGGCGATGCCACCTACGGCAAGCTGACCCTGAAGTTCATCTGCACCACCGGCAAGCTGCCCGTGCCCTGGCCCACCCTCGTGACCACCCTGACCTACGGCGTGCAGTGCTTCAGCCGCTACCCCGACCACATGAAGCAGCACGACTTCTTCAAGTCCGCCATGCCCGAAGGCTACGTCCAGGAGCGCACCATCTTCTTC";
+    const CUT: usize = 99;
+
+    /// A pool trace: the amplicon with each indel at the cut, mixed in the given shares
+    fn pool(shares: &[(i32, f64)]) -> Trace {
+        let mut mixed = Trace::synthetic(AMPLICON, 10, 40);
+        mixed.channels.iter_mut().for_each(|c| c.fill(0));
+        for &(size, share) in shares {
+            let mut sequence = AMPLICON.to_string();
+            if size < 0 {
+                sequence.replace_range(CUT + 1..CUT + 1 + size.unsigned_abs() as usize, "");
+            } else {
+                sequence.insert_str(CUT + 1, &"A".repeat(size as usize));
+            }
+            let allele = Trace::synthetic(&sequence, 10, 40);
+            for (channel, source) in mixed.channels.iter_mut().zip(&allele.channels) {
+                for (value, add) in channel.iter_mut().zip(source) {
+                    *value += (*add as f64 * share) as i16;
+                }
+            }
+        }
+        mixed
+    }
+
+    #[test]
+    fn test_find_cut_site() {
+        let sequence = AMPLICON.as_bytes();
+        // Guide ending at index 99, followed by the PAM
+        let guide = &sequence[83..103];
+        assert_eq!(find_cut_site(guide, sequence), Some(99));
+        let reverse = align::reverse_complement(&sequence[110..130]);
+        assert_eq!(find_cut_site(&reverse, sequence), Some(112));
+    }
+
+    #[test]
+    fn test_decompose_pool() {
+        let control = Trace::synthetic(AMPLICON, 10, 40);
+        let edited = pool(&[(0, 0.5), (-2, 0.3), (1, 0.2)]);
+        let spectrum = decompose(&control, &edited, DecomposeOptions::new(CUT)).unwrap();
+        let share = |size: i32| {
+            spectrum
+                .indels
+                .iter()
+                .find(|i| i.size == size)
+                .unwrap()
+                .frequency
+        };
+        assert!((share(0) - 0.5).abs() < 0.05, "{}", spectrum.report(0.01));
+        assert!((share(-2) - 0.3).abs() < 0.05, "{}", spectrum.report(0.01));
+        assert!((share(1) - 0.2).abs() < 0.05, "{}", spectrum.report(0.01));
+        assert!((spectrum.efficiency - 0.5).abs() < 0.05);
+        assert!(spectrum.r_squared > 0.9);
+        assert_eq!(spectrum.cut_site, 100);
+    }
+
+    #[test]
+    fn test_unedited_sample() {
+        let control = Trace::synthetic(AMPLICON, 10, 40);
+        let spectrum = decompose(&control, &control, DecomposeOptions::new(CUT)).unwrap();
+        assert!(spectrum.efficiency < 0.02);
+        assert!(decompose(&control, &control, DecomposeOptions::new(AMPLICON.len())).is_err());
+        let options = DecomposeOptions {
+            max_indel: usize::MAX,
+            window: usize::MAX,
+            ..DecomposeOptions::new(CUT)
+        };
+        assert!(decompose(&control, &control, options).is_err());
+    }
+
+    #[test]
+    fn test_reverse_edited_read() {
+        let control = Trace::synthetic(AMPLICON, 10, 40);
+        let reverse = align::reverse_complement(AMPLICON.as_bytes());
+        let edited = Trace::synthetic(std::str::from_utf8(&reverse).unwrap(), 10, 40);
+        let err = decompose(&control, &edited, DecomposeOptions::new(CUT)).unwrap_err();
+        assert!(err.to_string().contains("other strand"), "{}", err);
+    }
+}
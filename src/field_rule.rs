@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use crate::sanger_filename::NameField;
+
+/// Position of a token, counted either from the first or from the last token
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(tag = "from", content = "index", rename_all = "lowercase")]
+pub enum Anchor {
+    Start(usize),
+    End(usize),
+}
+
+impl Anchor {
+    fn resolve(&self, token_count: usize) -> Option<usize> {
+        match *self {
+            Anchor::Start(index) => (index < token_count).then_some(index),
+            Anchor::End(offset) => token_count.checked_sub(offset + 1),
+        }
+    }
+}
+
+/// An inclusive range of tokens
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct TokenSpan {
+    pub first: Anchor,
+    pub last: Anchor,
+}
+
+impl TokenSpan {
+    fn resolve(&self, token_count: usize) -> Option<RangeInclusive<usize>> {
+        let first = self.first.resolve(token_count)?;
+        let last = self.last.resolve(token_count)?;
+        (first <= last).then_some(first..=last)
+    }
+}
+
+/// A user-defined rule locating the template and primer in a filename stem
+///
+/// The stem is split on `separator` and each field is a span of tokens.
+/// Tokens after the primer, if any, are taken as the vendor ID.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct FieldRule {
+    pub separator: char,
+    pub template: TokenSpan,
+    pub primer: TokenSpan,
+}
+
+impl FieldRule {
+    /// Generalize the spans highlighted on one example stem into a rule
+    ///
+    /// The template is treated as the variable-length field: boundaries up to
+    /// the start of the template are counted from the start of the name, and
+    /// boundaries after its end are counted from the end. This way
+    /// `MY_PLASMID_T7_A01` with `MY_PLASMID` as template still matches
+    /// `TL1_T25_A01`.
+    pub fn from_example(
+        example_stem: &str,
+        separator: char,
+        template: RangeInclusive<usize>,
+        primer: RangeInclusive<usize>,
+    ) -> anyhow::Result<Self> {
+        let token_count = example_stem.split(separator).count();
+        for (name, span) in [("template", &template), ("primer", &primer)] {
+            if span.is_empty() || *span.end() >= token_count {
+                anyhow::bail!("{} span {:?} is outside of {}", name, span, example_stem);
+            }
+        }
+        if template.contains(primer.start()) || primer.contains(template.start()) {
+            anyhow::bail!("template and primer spans overlap");
+        }
+        let from_end = |index: usize| Anchor::End(token_count - 1 - index);
+        let template_span = TokenSpan {
+            first: Anchor::Start(*template.start()),
+            last: from_end(*template.end()),
+        };
+        let primer_span = if primer.start() > template.end() {
+            TokenSpan {
+                first: from_end(*primer.start()),
+                last: from_end(*primer.end()),
+            }
+        } else {
+            TokenSpan {
+                first: Anchor::Start(*primer.start()),
+                last: Anchor::Start(*primer.end()),
+            }
+        };
+        Ok(Self {
+            separator,
+            template: template_span,
+            primer: primer_span,
+        })
+    }
+
+    /// Apply the rule to a filename stem, returning template, primer and vendor ID
+    pub fn apply(&self, file_stem: &str) -> Result<(String, String, String), NameField> {
+        let tokens: Vec<&str> = file_stem.split(self.separator).collect();
+        let join = |range: RangeInclusive<usize>| tokens[range].join(&self.separator.to_string());
+        let template = self
+            .template
+            .resolve(tokens.len())
+            .ok_or(NameField::Template)?;
+        let primer = self.primer.resolve(tokens.len()).ok_or(NameField::Primer)?;
+        if template.contains(primer.start()) || primer.contains(template.start()) {
+            return Err(NameField::Primer);
+        }
+        let vendor_id = if primer.end() + 1 < tokens.len() && primer.start() > template.end() {
+            join(primer.end() + 1..=tokens.len() - 1)
+        } else {
+            String::new()
+        };
+        let template = join(template);
+        let primer = join(primer);
+        if template.is_empty() {
+            return Err(NameField::Template);
+        }
+        if primer.is_empty() {
+            return Err(NameField::Primer);
+        }
+        Ok((template, primer, vendor_id))
+    }
+
+    /// Where the Genewiz rule is saved between sessions
+    pub fn saved_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("sanger_rename").join("genewiz_rule.toml"))
+    }
+
+    /// Load the saved Genewiz rule, if one has been saved
+    pub fn load_saved() -> anyhow::Result<Option<Self>> {
+        let Some(path) = Self::saved_path() else {
+            return Ok(None);
+        };
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)?;
+        Ok(Some(toml::from_str(&content)?))
+    }
+
+    pub fn save(&self) -> anyhow::Result<PathBuf> {
+        let path = Self::saved_path()
+            .ok_or_else(|| anyhow::anyhow!("No configuration directory available"))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, toml::to_string(self)?)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_generalizes_template_with_underscores() {
+        let rule = FieldRule::from_example("MY_PLASMID_T7_A01", '_', 0..=1, 2..=2).unwrap();
+        assert_eq!(
+            rule.apply("MY_PLASMID_T7_A01"),
+            Ok((
+                "MY_PLASMID".to_string(),
+                "T7".to_string(),
+                "A01".to_string()
+            ))
+        );
+        assert_eq!(
+            rule.apply("TL1_T25_A01"),
+            Ok(("TL1".to_string(), "T25".to_string(), "A01".to_string()))
+        );
+        assert_eq!(rule.apply("T25_A01"), Err(NameField::Template));
+    }
+
+    #[test]
+    fn test_rule_without_well() {
+        let rule = FieldRule::from_example("BETA-ACTIN_T9", '_', 0..=0, 1..=1).unwrap();
+        assert_eq!(
+            rule.apply("BETA_ACTIN_T9"),
+            Ok(("BETA_ACTIN".to_string(), "T9".to_string(), String::new()))
+        );
+    }
+
+    #[test]
+    fn test_rule_rejects_bad_spans() {
+        assert!(FieldRule::from_example("TL1_T25_A01", '_', 0..=1, 1..=1).is_err());
+        assert!(FieldRule::from_example("TL1_T25_A01", '_', 0..=0, 3..=3).is_err());
+    }
+
+    #[test]
+    fn test_rule_toml_round_trip() {
+        let rule = FieldRule::from_example("TL1_T25_A01", '_', 0..=0, 1..=1).unwrap();
+        let content = toml::to_string(&rule).unwrap();
+        assert_eq!(toml::from_str::<FieldRule>(&content).unwrap(), rule);
+    }
+}
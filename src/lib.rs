@@ -1,5 +1,30 @@
+pub mod abif;
+pub mod align;
+pub mod assembly;
+pub mod catalog;
+pub mod chromatogram;
+pub mod config;
+pub mod decompose;
+mod field_rule;
+pub mod manifest;
+pub mod mapping;
+pub mod mixed_peaks;
+pub mod name_template;
+pub mod order;
+pub mod plan;
+pub mod plate;
+pub mod primer;
+pub mod reconcile;
+pub mod reference;
 mod sanger_filename;
-pub use sanger_filename::{SangerFilename, Vendor};
+pub mod sanitize;
+pub mod scf;
+pub mod search;
+mod standardized_name;
+pub mod watch;
+pub use field_rule::{Anchor, FieldRule, TokenSpan};
+pub use sanger_filename::{NameField, ParseError, ParsedFields, SangerFilename, Vendor, move_file};
+pub use standardized_name::{StandardizedName, StandardizedNameError};
 
 // Re-export for backward compatibility
 pub use sanger_filename::SangerFilename as SangerFilenameVariant;
@@ -1,21 +1,155 @@
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 
+mod cli;
 mod tui;
+use cli::Command;
+use sanger_rename::catalog::Catalog;
+use sanger_rename::config::Config;
+use sanger_rename::mapping::NameMappings;
+use sanger_rename::order::Order;
+use sanger_rename::primer::PrimerLibrary;
+use sanger_rename::reference::ReferenceLibrary;
+use sanger_rename::watch::WatchQueue;
+use sanger_rename::{FieldRule, Vendor};
+use std::path::PathBuf;
 use tui::App;
 
 #[derive(Parser)]
 #[command(name = "sanger-rename")]
 #[command(about = "A tool for renaming files")]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(group = clap::ArgGroup::new("headless").args(["dry_run", "yes"]))]
 struct Args {
-    /// List of filenames to process
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// List of filenames to process; `-` reads the list from stdin, one per line
     #[arg(value_name = "FILE")]
     filenames: Vec<String>,
+
+    /// The list read from stdin is NUL-delimited, as written by `find -print0`; needs `-`
+    #[arg(short = '0', long, requires = "filenames")]
+    null: bool,
+
+    /// FASTA or GenBank references (files or directories) to align traces against in the preview
+    #[arg(short, long = "reference", value_name = "PATH")]
+    references: Vec<PathBuf>,
+
+    /// Primer sequences (FASTA or name/sequence table) to check primer names against;
+    /// defaults to primers.fasta in the config directory
+    #[arg(short, long, value_name = "FILE")]
+    primers: Option<PathBuf>,
+
+    /// Call a position mixed when its secondary peak reaches this fraction of the primary;
+    /// defaults to the configured fraction or 0.33
+    #[arg(long, value_name = "FRACTION")]
+    mixed_fraction: Option<f64>,
+
+    /// Move the renamed traces into this folder instead of leaving them in place
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+
+    /// Order saved by the order command to check the delivery against before renaming
+    #[arg(long, value_name = "FILE")]
+    order: Option<PathBuf>,
+
+    /// Confirm the traces queued by the watch command, one vendor at a time
+    #[arg(long, conflicts_with = "filenames")]
+    queued: bool,
+
+    /// Print the planned renames without touching the files or opening the TUI
+    #[arg(long, requires = "filenames")]
+    dry_run: bool,
+
+    /// Rename the files as --dry-run plans them, without opening the TUI
+    #[arg(short, long, requires = "filenames")]
+    yes: bool,
+
+    /// Vendor of the files, highlighted in the TUI and used by --dry-run and --yes instead of
+    /// detecting it from each name; defaults to the configured vendor
+    #[arg(long)]
+    vendor: Option<Vendor>,
+
+    /// Date of the new names for --dry-run and --yes (YYYY-MM-DD); today by default
+    #[arg(long, requires = "headless", value_parser = cli::parse_date)]
+    date: Option<time::Date>,
+
+    /// Print the --dry-run plan as JSON
+    #[arg(long, requires = "dry_run")]
+    json: bool,
 }
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
+    if let Some(command) = args.command {
+        return command.run();
+    }
+    if args.null && !args.filenames.iter().any(|filename| filename == "-") {
+        Args::command()
+            .error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                "--null reads the file list from stdin and needs `-` among the files",
+            )
+            .exit();
+    }
+    let filenames = cli::read_stdin_filenames(args.filenames, args.null)?;
+    let pending = if args.queued {
+        WatchQueue::open_default()?.pending()?
+    } else {
+        Vec::new()
+    };
+    // The project configuration is looked up from the first file's folder
+    let start = match (filenames.first(), pending.first()) {
+        (Some(filename), _) => PathBuf::from(filename),
+        (None, Some(trace)) => trace.path.clone(),
+        (None, None) => PathBuf::new(),
+    };
+    let mut config = Config::load_for(&start)?;
+    config.vendor = args.vendor.or(config.vendor);
+    config.output_dir = args.output_dir.or(config.output_dir);
+    config.qc.mixed_fraction = args.mixed_fraction.or(config.qc.mixed_fraction);
+    if args.dry_run {
+        return cli::dry_run::run(&filenames, &config, args.date, args.json);
+    }
+    if args.yes {
+        return cli::rename::run(&filenames, &config, args.date);
+    }
     let mut app = App::new();
-    app.add_filenames(args.filenames); // Add filenames BEFORE running TUI
+    app.set_field_rule(FieldRule::load_saved()?);
+    let mut mappings = NameMappings::load_saved()?;
+    mappings.extend(&config.mappings());
+    app.set_mappings(mappings);
+    match Catalog::open_default() {
+        Ok(catalog) => app.set_catalog(Some(catalog)),
+        Err(err) => eprintln!("Renames will not be cataloged: {}", err),
+    }
+    if !args.references.is_empty() {
+        app.set_references(Some(ReferenceLibrary::load(&args.references)?));
+    }
+    app.set_primers(match &args.primers {
+        Some(path) => Some(PrimerLibrary::load(path)?),
+        None => PrimerLibrary::load_default()?,
+    });
+    app.set_mixed_fraction(config.qc.mixed_fraction());
+    app.set_config(config);
+    app.set_order(args.order.as_deref().map(Order::load).transpose()?);
+    if args.queued {
+        let Some(first) = pending.first() else {
+            println!("No traces are queued");
+            return Ok(());
+        };
+        let vendor: Vendor = first.vendor.parse().map_err(anyhow::Error::msg)?;
+        let filenames = pending
+            .iter()
+            .filter(|trace| trace.vendor == first.vendor)
+            .map(|trace| trace.path.to_string_lossy().to_string())
+            .collect();
+        app.set_selected_vendor(Some(vendor));
+        app.add_filenames(filenames);
+        app.skip_to_confirmation();
+    } else {
+        app.add_filenames(filenames); // Add filenames BEFORE running TUI
+    }
     app.run()?;
     Ok(())
 }
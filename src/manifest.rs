@@ -0,0 +1,179 @@
+//! Per-batch record of renames for electronic lab notebooks and LIMS imports
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::catalog::RenameRecord;
+use crate::order::csv_field;
+
+/// One renamed trace, as listed in a manifest
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct ManifestEntry {
+    pub original_name: String,
+    pub new_name: String,
+    pub vendor: String,
+    pub vendor_id: String,
+    pub well: Option<String>,
+    /// Date of the standardized name, as `YYYY-MM-DD`
+    pub date: String,
+    pub template: String,
+    pub primer: String,
+    /// SHA-256 of the file content, hex encoded
+    pub sha256: String,
+}
+
+impl From<&RenameRecord> for ManifestEntry {
+    fn from(record: &RenameRecord) -> Self {
+        Self {
+            original_name: record.original_name.clone(),
+            new_name: record
+                .new_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            vendor: record.vendor.clone(),
+            vendor_id: record.vendor_id.clone(),
+            well: record.well.clone(),
+            date: record.date.clone(),
+            template: record.template.clone(),
+            primer: record.primer.clone(),
+            sha256: record.sha256.clone(),
+        }
+    }
+}
+
+const CSV_HEADER: &str = "original_name,new_name,vendor,vendor_id,well,date,template,primer,sha256";
+
+/// The entries as CSV with a header row
+pub fn to_csv(entries: &[ManifestEntry]) -> String {
+    let mut csv = format!("{}\n", CSV_HEADER);
+    for entry in entries {
+        let row = [
+            entry.original_name.as_str(),
+            &entry.new_name,
+            &entry.vendor,
+            &entry.vendor_id,
+            entry.well.as_deref().unwrap_or(""),
+            &entry.date,
+            &entry.template,
+            &entry.primer,
+            &entry.sha256,
+        ]
+        .map(csv_field);
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Write `manifest_{timestamp}.csv` and `.json` for a batch of renames into `dir`
+pub fn write(records: &[RenameRecord], dir: &Path) -> anyhow::Result<[PathBuf; 2]> {
+    let entries: Vec<ManifestEntry> = records.iter().map(ManifestEntry::from).collect();
+    let now = time::OffsetDateTime::now_local().unwrap_or_else(|_| time::OffsetDateTime::now_utc());
+    let stamp = now.format(time::macros::format_description!(
+        "[year][month][day]-[hour][minute][second]"
+    ))?;
+    let mut stem = format!("manifest_{}", stamp);
+    let mut n = 1;
+    while dir.join(format!("{}.csv", stem)).exists() || dir.join(format!("{}.json", stem)).exists()
+    {
+        n += 1;
+        stem = format!("manifest_{}_{}", stamp, n);
+    }
+    let csv = dir.join(format!("{}.csv", stem));
+    let json = dir.join(format!("{}.json", stem));
+    std::fs::write(&csv, to_csv(&entries))?;
+    std::fs::write(&json, serde_json::to_string_pretty(&entries)?)?;
+    Ok([csv, json])
+}
+
+/// Write one manifest per folder the batch was renamed into, each listing the traces in it
+pub fn write_batch(records: &[RenameRecord]) -> Vec<(PathBuf, anyhow::Result<[PathBuf; 2]>)> {
+    let mut by_dir: BTreeMap<PathBuf, Vec<RenameRecord>> = BTreeMap::new();
+    for record in records {
+        let dir = record.new_path.parent().unwrap_or(Path::new(""));
+        by_dir
+            .entry(dir.to_path_buf())
+            .or_default()
+            .push(record.clone());
+    }
+    by_dir
+        .into_iter()
+        .map(|(dir, records)| {
+            let written = write(&records, &dir);
+            (dir, written)
+        })
+        .collect()
+}
+
+/// One line per folder of [`write_batch`], naming the files written or the error
+pub fn summarize(written: &[(PathBuf, anyhow::Result<[PathBuf; 2]>)]) -> Vec<String> {
+    written
+        .iter()
+        .map(|(dir, written)| match written {
+            Ok([csv, json]) => format!(
+                "Manifest written to {} and {}",
+                csv.display(),
+                json.display()
+            ),
+            Err(err) => format!("Could not write the manifest in {}: {}", dir.display(), err),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_manifest() {
+        let dir = std::env::temp_dir().join("sanger_rename_manifest_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let record = RenameRecord {
+            original_name: "0001_31225060307072_(TX,PCR)_[SP1].ab1".to_string(),
+            new_path: dir.join("250601.TX,PCR.SP1.ab1"),
+            vendor: "Sangon".to_string(),
+            vendor_id: "31225060307072".to_string(),
+            well: None,
+            date: "2025-06-01".to_string(),
+            template: "TX,PCR".to_string(),
+            primer: "SP1".to_string(),
+            sha256: "00".repeat(32),
+        };
+        let records = [record];
+        let [csv, json] = write(&records, &dir).unwrap();
+        let second = write(&records, &dir).unwrap();
+        assert_ne!(second[0], csv);
+
+        let csv = std::fs::read_to_string(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            format!(
+                "\"0001_31225060307072_(TX,PCR)_[SP1].ab1\",\"250601.TX,PCR.SP1.ab1\",Sangon,31225060307072,,2025-06-01,\"TX,PCR\",SP1,{}",
+                "00".repeat(32)
+            )
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(json).unwrap()).unwrap();
+        assert_eq!(json[0]["new_name"], "250601.TX,PCR.SP1.ab1");
+        assert_eq!(json[0]["well"], serde_json::Value::Null);
+
+        // A batch renamed into several folders gets a manifest in each
+        let other = dir.join("plate2");
+        std::fs::create_dir_all(&other).unwrap();
+        let mut moved = records[0].clone();
+        moved.new_path = other.join("250601.GFP.T7.ab1");
+        let written = write_batch(&[records[0].clone(), moved]);
+        let dirs: Vec<&Path> = written.iter().map(|(dir, _)| dir.as_path()).collect();
+        assert_eq!(dirs, [dir.as_path(), other.as_path()]);
+        let [csv, _] = written[1].1.as_ref().unwrap();
+        let csv = std::fs::read_to_string(csv).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.contains("250601.GFP.T7.ab1"));
+    }
+}
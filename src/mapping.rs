@@ -0,0 +1,133 @@
+//! Primer and template renames remembered between batches
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use crate::sanger_filename::SangerFilename;
+
+/// Names as the vendor writes them, mapped to the names to use instead
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct NameMappings {
+    #[serde(default)]
+    pub primers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub templates: BTreeMap<String, String>,
+}
+
+impl NameMappings {
+    /// Where the mappings are saved between sessions
+    pub fn saved_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("sanger_rename").join("mappings.toml"))
+    }
+
+    /// Load the saved mappings; none are saved yet if the file is absent
+    pub fn load_saved() -> anyhow::Result<Self> {
+        match Self::saved_path() {
+            Some(path) if path.exists() => {
+                let content = std::fs::read_to_string(&path)?;
+                toml::from_str(&content)
+                    .map_err(|err| anyhow::anyhow!("{}: {}", path.display(), err))
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<PathBuf> {
+        let path = Self::saved_path()
+            .ok_or_else(|| anyhow::anyhow!("No configuration directory available"))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, toml::to_string(self)?)?;
+        Ok(path)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.primers.is_empty() && self.templates.is_empty()
+    }
+
+    /// Add the mappings of `other`, replacing those for the same names
+    pub fn extend(&mut self, other: &NameMappings) {
+        self.primers.extend(other.primers.clone());
+        self.templates.extend(other.templates.clone());
+    }
+
+    /// Add the renames set in a rename stage to the saved mappings, in the
+    /// dictionary `field` picks
+    ///
+    /// Returns how many renames were saved and where, or `None` when none are
+    /// set. Renames of an empty name, which every unparsed file has, are skipped.
+    pub fn remember(
+        renames: &HashMap<String, Option<String>>,
+        field: fn(&mut NameMappings) -> &mut BTreeMap<String, String>,
+    ) -> anyhow::Result<Option<(usize, PathBuf)>> {
+        let renames: Vec<(&String, &String)> = renames
+            .iter()
+            .filter(|(old, _)| !old.is_empty())
+            .filter_map(|(old, new)| new.as_ref().map(|new| (old, new)))
+            .collect();
+        if renames.is_empty() {
+            return Ok(None);
+        }
+        let mut mappings = Self::load_saved()?;
+        for (old, new) in &renames {
+            field(&mut mappings).insert(old.to_string(), new.to_string());
+        }
+        Ok(Some((renames.len(), mappings.save()?)))
+    }
+
+    /// Replace the file's primer and template names that have a mapping
+    ///
+    /// Empty names, left by a name that did not parse, are never mapped, and
+    /// neither are the names of a file that is already standardized.
+    pub fn apply(&self, sanger_fn: &mut SangerFilename) {
+        if sanger_fn.is_standardized() {
+            return;
+        }
+        let primer_name = sanger_fn.get_primer_name();
+        if let Some(primer) = self
+            .primers
+            .get(&primer_name)
+            .filter(|_| !primer_name.is_empty())
+        {
+            sanger_fn.set_primer_name(primer).unwrap();
+        }
+        let template_name = sanger_fn.get_template_name();
+        if let Some(template) = self
+            .templates
+            .get(&template_name)
+            .filter(|_| !template_name.is_empty())
+        {
+            sanger_fn.set_template_name(template).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vendor;
+
+    #[test]
+    fn test_apply_mappings() {
+        let mappings: NameMappings = toml::from_str("[primers]\nC1 = \"T7\"\n").unwrap();
+        assert!(mappings.templates.is_empty());
+        let mut sanger_fn = SangerFilename::new("K528-1.C1.34781340.B08.ab1", Vendor::Ruibio);
+        mappings.apply(&mut sanger_fn);
+        assert_eq!(sanger_fn.get_primer_name(), "T7");
+        assert_eq!(sanger_fn.get_template_name(), "K528-1");
+
+        // An empty name saved by an earlier version does not fill unparsed files
+        let mappings: NameMappings = toml::from_str("[primers]\n\"\" = \"T7\"\n").unwrap();
+        let mut unparsed = SangerFilename::new("notes.ab1", Vendor::Ruibio);
+        mappings.apply(&mut unparsed);
+        assert_eq!(unparsed.get_primer_name(), "");
+
+        // Nor do mappings rewrite a name that is already standardized
+        let mappings: NameMappings = toml::from_str("[primers]\nC1 = \"T7\"\n").unwrap();
+        let mut renamed = SangerFilename::new("250601.K528-1.C1.ab1", Vendor::Ruibio);
+        mappings.apply(&mut renamed);
+        assert_eq!(renamed.get_primer_name(), "C1");
+    }
+}
@@ -0,0 +1,161 @@
+//! Secondary peaks under base calls, as seen in heterozygous or mixed samples
+
+use serde::Serialize;
+
+use crate::abif::Trace;
+use crate::assembly::{TRIM_QUALITY, quality_trim};
+
+/// Default height of a secondary peak, relative to the primary, to call a position mixed
+pub const DEFAULT_SECONDARY_FRACTION: f64 = 0.33;
+
+/// A base call with a second peak underneath it
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct MixedPosition {
+    /// 0-based index of the base call
+    pub index: usize,
+    pub primary: char,
+    pub secondary: char,
+    /// Secondary peak height divided by the primary one
+    pub ratio: f64,
+    /// IUPAC code for both bases
+    pub code: char,
+}
+
+/// IUPAC ambiguity code of two different bases
+pub fn iupac(a: u8, b: u8) -> u8 {
+    let (a, b) = (a.to_ascii_uppercase(), b.to_ascii_uppercase());
+    let pair = if a <= b { [a, b] } else { [b, a] };
+    match &pair {
+        b"AG" => b'R',
+        b"CT" => b'Y',
+        b"CG" => b'S',
+        b"AT" => b'W',
+        b"GT" => b'K',
+        b"AC" => b'M',
+        _ if a == b => a,
+        _ => b'N',
+    }
+}
+
+/// Positions where the strongest other channel reaches `fraction` of the called one
+///
+/// Peaks are measured within a quarter of the distance to the neighbouring
+/// calls. Only the quality trimmed part of the read is analyzed, as the noisy
+/// ends would otherwise be full of secondary peaks.
+pub fn mixed_positions(trace: &Trace, fraction: f64) -> Vec<MixedPosition> {
+    let peaks = &trace.peak_locations;
+    let range = if trace.qualities.len() == trace.bases.len() {
+        quality_trim(&trace.qualities, TRIM_QUALITY)
+    } else {
+        0..trace.bases.len()
+    };
+    let mut mixed = Vec::new();
+    for index in range.filter(|&i| i < peaks.len()) {
+        let peak = peaks[index] as usize;
+        let before = index.checked_sub(1).map(|i| peaks[i] as usize);
+        let after = peaks.get(index + 1).map(|&p| p as usize);
+        let reach =
+            |neighbour: Option<usize>| neighbour.map_or(2, |n| (n.abs_diff(peak) / 4).max(1));
+        let window = peak.saturating_sub(reach(before))..peak + reach(after) + 1;
+        let heights: Vec<i32> = trace
+            .channels
+            .iter()
+            .map(|channel| {
+                let end = window.end.min(channel.len());
+                let start = window.start.min(end);
+                channel[start..end]
+                    .iter()
+                    .map(|&v| v as i32)
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let called = trace.bases[index].to_ascii_uppercase();
+        let primary = trace
+            .base_order
+            .iter()
+            .position(|&b| b == called)
+            .unwrap_or_else(|| (0..4).max_by_key(|&c| heights[c]).unwrap_or(0));
+        let Some(secondary) = (0..4).filter(|&c| c != primary).max_by_key(|&c| heights[c]) else {
+            continue;
+        };
+        if heights[primary] <= 0 {
+            continue;
+        }
+        let ratio = heights[secondary] as f64 / heights[primary] as f64;
+        if ratio >= fraction {
+            let (primary, secondary) = (trace.base_order[primary], trace.base_order[secondary]);
+            mixed.push(MixedPosition {
+                index,
+                primary: primary as char,
+                secondary: secondary as char,
+                ratio,
+                code: iupac(primary, secondary) as char,
+            });
+        }
+    }
+    mixed
+}
+
+/// The base calls with IUPAC codes at mixed positions
+pub fn ambiguity_sequence(trace: &Trace, fraction: f64) -> Vec<u8> {
+    let mut bases = trace.bases.clone();
+    for position in mixed_positions(trace, fraction) {
+        bases[position.index] = position.code as u8;
+    }
+    bases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A trace with a secondary peak of `height` relative to the call at `index`
+    fn with_secondary(sequence: &str, index: usize, base: u8, height: f64) -> Trace {
+        let mut trace = Trace::synthetic(sequence, 10, 40);
+        let source = trace.channel(trace.bases[index]).unwrap().to_vec();
+        let channel = trace.base_order.iter().position(|&b| b == base).unwrap();
+        let peak = trace.peak_locations[index] as usize;
+        let region = peak - 5..peak + 6;
+        for (value, called) in trace.channels[channel][region.clone()]
+            .iter_mut()
+            .zip(&source[region])
+        {
+            *value += (*called as f64 * height) as i16;
+        }
+        trace
+    }
+
+    #[test]
+    fn test_iupac() {
+        assert_eq!(iupac(b'G', b'A'), b'R');
+        assert_eq!(iupac(b'c', b't'), b'Y');
+        assert_eq!(iupac(b'T', b'T'), b'T');
+        assert_eq!(iupac(b'A', b'N'), b'N');
+    }
+
+    #[test]
+    fn test_mixed_positions() {
+        let trace = with_secondary("ACGTACGTAC", 4, b'G', 0.5);
+        let mixed = mixed_positions(&trace, DEFAULT_SECONDARY_FRACTION);
+        assert_eq!(mixed.len(), 1);
+        assert_eq!(mixed[0].index, 4);
+        assert_eq!(
+            (mixed[0].primary, mixed[0].secondary, mixed[0].code),
+            ('A', 'G', 'R')
+        );
+        assert!((mixed[0].ratio - 0.5).abs() < 0.01);
+        assert_eq!(
+            ambiguity_sequence(&trace, DEFAULT_SECONDARY_FRACTION),
+            b"ACGTRCGTAC"
+        );
+        // A higher threshold ignores the same peak
+        assert!(mixed_positions(&trace, 0.6).is_empty());
+    }
+
+    #[test]
+    fn test_clean_trace_has_no_mixed_positions() {
+        let trace = Trace::synthetic("ACGTTGCAACGT", 10, 40);
+        assert!(mixed_positions(&trace, DEFAULT_SECONDARY_FRACTION).is_empty());
+    }
+}
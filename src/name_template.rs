@@ -0,0 +1,291 @@
+//! Layouts of the new file names other than `date.template.primer`
+
+use regex::Regex;
+
+use crate::standardized_name::StandardizedName;
+
+/// A value a name template can place
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TemplateField {
+    /// The `YYMMDD` date
+    Date,
+    Template,
+    Primer,
+    Vendor,
+    VendorId,
+    Well,
+}
+
+impl TemplateField {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "date" => Some(TemplateField::Date),
+            "template" => Some(TemplateField::Template),
+            "primer" => Some(TemplateField::Primer),
+            "vendor" => Some(TemplateField::Vendor),
+            "vendor_id" => Some(TemplateField::VendorId),
+            "well" => Some(TemplateField::Well),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Segment {
+    Literal(String),
+    Field(TemplateField),
+}
+
+/// Characters that stand in for a separator found inside a field value, in
+/// order of preference
+const ESCAPES: [char; 3] = ['-', '_', '~'];
+
+/// Values of one file filled into a template
+pub struct TemplateValues<'a> {
+    pub name: &'a StandardizedName,
+    pub vendor: &'a str,
+    pub vendor_id: &'a str,
+    pub well: Option<&'a str>,
+}
+
+/// A file name layout such as `{date}_{template}_{primer}`, without the extension
+///
+/// The date, template and primer must all appear, so names written with the
+/// template are recognized as already renamed. Field values never contain the
+/// punctuation the template separates them with, nor a dot, so names read
+/// back into the same fields.
+#[derive(Clone, Debug)]
+pub struct NameTemplate {
+    source: String,
+    segments: Vec<Segment>,
+    /// Characters replaced by `escape` in field values
+    separators: Vec<char>,
+    escape: char,
+    /// Matches the names the template writes, capturing the date, template and primer
+    pattern: Regex,
+}
+
+impl PartialEq for NameTemplate {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for NameTemplate {}
+
+impl NameTemplate {
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let mut segments = Vec::new();
+        let mut rest = source;
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                segments.push(Segment::Literal(rest[..open].to_string()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .ok_or_else(|| anyhow::anyhow!("unclosed {{ in name template {}", source))?;
+            let name = &rest[open + 1..open + close];
+            let field = TemplateField::from_name(name).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "unknown field {{{}}} in name template, expected date, template, primer, \
+                     vendor, vendor_id or well",
+                    name
+                )
+            })?;
+            segments.push(Segment::Field(field));
+            rest = &rest[open + close + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+        for segment in &segments {
+            if let Segment::Literal(literal) = segment
+                && literal.contains(['/', '\\', '}'])
+            {
+                anyhow::bail!("name template {} contains {:?}", source, literal);
+            }
+        }
+        for required in [
+            TemplateField::Date,
+            TemplateField::Template,
+            TemplateField::Primer,
+        ] {
+            if !segments.contains(&Segment::Field(required)) {
+                anyhow::bail!(
+                    "name template {} needs {{date}}, {{template}} and {{primer}}",
+                    source
+                );
+            }
+        }
+
+        let mut separators = vec!['.'];
+        for segment in &segments {
+            if let Segment::Literal(literal) = segment {
+                separators.extend(literal.chars().filter(|c| !c.is_alphanumeric()));
+            }
+        }
+        separators.sort_unstable();
+        separators.dedup();
+        let escape = ESCAPES
+            .into_iter()
+            .find(|c| !separators.contains(c))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "name template {} separates fields with each of {:?}, leaving none to \
+                     replace them with in names",
+                    source,
+                    ESCAPES
+                )
+            })?;
+
+        let mut pattern = String::from("^");
+        let mut captured = Vec::new();
+        for segment in &segments {
+            match segment {
+                Segment::Literal(literal) => pattern.push_str(&regex::escape(literal)),
+                // Fields placed twice are captured once
+                Segment::Field(field) if captured.contains(field) => pattern.push_str(".+?"),
+                Segment::Field(field) => {
+                    captured.push(*field);
+                    pattern.push_str(match field {
+                        TemplateField::Date => r"(?P<date>\d{6})",
+                        TemplateField::Template => "(?P<template>.+?)",
+                        TemplateField::Primer => "(?P<primer>.+?)",
+                        _ => ".*?",
+                    });
+                }
+            }
+        }
+        pattern.push_str(r"(?:\.(?P<extension>[^.]+))?$");
+        Ok(Self {
+            source: source.to_string(),
+            segments,
+            separators,
+            escape,
+            pattern: Regex::new(&pattern)?,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// `value` with the template's separators and dots replaced, so it reads
+    /// back as one field
+    pub fn escape_field(&self, value: &str) -> String {
+        value
+            .chars()
+            .map(|c| {
+                if self.separators.contains(&c) {
+                    self.escape
+                } else {
+                    c
+                }
+            })
+            .collect()
+    }
+
+    /// The new file name without extension
+    pub fn render(&self, values: &TemplateValues) -> String {
+        let mut name = String::new();
+        for segment in &self.segments {
+            let value = match segment {
+                Segment::Literal(literal) => {
+                    name.push_str(literal);
+                    continue;
+                }
+                Segment::Field(TemplateField::Date) => values.name.date_field(),
+                Segment::Field(TemplateField::Template) => values.name.template_name.clone(),
+                Segment::Field(TemplateField::Primer) => values.name.primer_name.clone(),
+                Segment::Field(TemplateField::Vendor) => values.vendor.to_string(),
+                Segment::Field(TemplateField::VendorId) => values.vendor_id.to_string(),
+                Segment::Field(TemplateField::Well) => values.well.unwrap_or("").to_string(),
+            };
+            name.push_str(&self.escape_field(&value));
+        }
+        name
+    }
+
+    /// Read back the date, template and primer of a file name written with this template
+    pub fn parse_name(&self, name: &str) -> Option<StandardizedName> {
+        let file_name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+        let captures = self.pattern.captures(file_name)?;
+        let mut standardized = StandardizedName::parse(&format!(
+            "{}.{}.{}",
+            &captures["date"], &captures["template"], &captures["primer"]
+        ))
+        .ok()?;
+        standardized.extension = captures
+            .name("extension")
+            .map(|extension| extension.as_str().to_string());
+        Some(standardized)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for NameTemplate {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        NameTemplate::parse(&source).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_template() {
+        let template = NameTemplate::parse("{date}_{template}_{primer}_{well}").unwrap();
+        let name = StandardizedName::parse("250601.K528-1.T7").unwrap();
+        let values = TemplateValues {
+            name: &name,
+            vendor: "Ruibio",
+            vendor_id: "34781340.B08",
+            well: Some("B08"),
+        };
+        assert_eq!(template.render(&values), "250601_K528-1_T7_B08");
+
+        let parsed = template
+            .parse_name("/data/250601_K528-1_T7_B08.ab1")
+            .unwrap();
+        assert_eq!(
+            (parsed.template_name.as_str(), parsed.primer_name.as_str()),
+            ("K528-1", "T7")
+        );
+        assert_eq!(parsed.extension.as_deref(), Some("ab1"));
+        assert!(template.parse_name("K528-1.T7.34781340.B08.ab1").is_none());
+
+        assert!(NameTemplate::parse("{date}.{template}").is_err());
+        assert!(NameTemplate::parse("{date}-{template}_{primer}~{well}").is_err());
+        assert!(NameTemplate::parse("{date}.{template}.{primer}.{plate}").is_err());
+        assert!(NameTemplate::parse("{date}/{template}.{primer}").is_err());
+    }
+
+    #[test]
+    fn test_separators_in_fields_round_trip() {
+        let template = NameTemplate::parse("{date}_{template}_{primer}_{vendor_id}").unwrap();
+        let name = StandardizedName::parse("250601.GFP_CTRL.SP6").unwrap();
+        let values = TemplateValues {
+            name: &name,
+            vendor: "Ruibio",
+            vendor_id: "34781340.B08",
+            well: Some("B08"),
+        };
+        let rendered = template.render(&values);
+        assert_eq!(rendered, "250601_GFP-CTRL_SP6_34781340-B08");
+        let parsed = template.parse_name(&format!("{}.ab1", rendered)).unwrap();
+        assert_eq!(
+            (parsed.template_name.as_str(), parsed.primer_name.as_str()),
+            ("GFP-CTRL", "SP6")
+        );
+        let values = TemplateValues {
+            name: &parsed,
+            ..values
+        };
+        assert_eq!(template.render(&values), rendered);
+
+        // Without `-` among the separators, it is the one replaced
+        let template = NameTemplate::parse("{date}-{template}-{primer}").unwrap();
+        assert_eq!(template.escape_field("K528-1"), "K528_1");
+    }
+}
@@ -0,0 +1,254 @@
+//! Submission sheets for sequencing orders, and the record of what was submitted
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::plate::{PlateFormat, Well};
+use crate::sanger_filename::Vendor;
+use crate::sanitize::SanitizeRules;
+
+/// One sample of an order
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct OrderSample {
+    /// 1-based sample number
+    pub index: usize,
+    pub well: String,
+    pub template: String,
+    pub primer: String,
+}
+
+/// An order as submitted, kept to check the delivery against it
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Order {
+    pub vendor: String,
+    /// Submission date, as `YYYY-MM-DD`
+    pub date: String,
+    pub plate: String,
+    pub samples: Vec<OrderSample>,
+}
+
+/// Read template and primer pairs from a sample list
+///
+/// Each line holds a template followed by one or more primers, separated by
+/// tabs or commas, so names may contain spaces; a template alone gets
+/// `default_primer`. Lines starting with `#`, lines of separators only and a
+/// `template,primer` header are skipped.
+pub fn parse_sample_list(
+    text: &str,
+    default_primer: Option<&str>,
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut samples = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line
+            .split(['\t', ','])
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .collect();
+        if fields.is_empty() {
+            continue;
+        }
+        if samples.is_empty() && fields[0].eq_ignore_ascii_case("template") {
+            continue;
+        }
+        match (&fields[1..], default_primer) {
+            ([], Some(primer)) => samples.push((fields[0].to_string(), primer.to_string())),
+            ([], None) => anyhow::bail!("line {}: no primer for {}", i + 1, fields[0]),
+            (primers, _) => {
+                for primer in primers {
+                    samples.push((fields[0].to_string(), primer.to_string()));
+                }
+            }
+        }
+    }
+    Ok(samples)
+}
+
+/// Quote a CSV field when it needs it
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl Order {
+    /// Number the samples and place them column by column from `start`
+    ///
+    /// Names are sanitized as they would be when renaming, so the files
+    /// coming back parse into the same template and primer.
+    pub fn new(
+        vendor: Vendor,
+        date: time::Date,
+        plate: PlateFormat,
+        start: Well,
+        samples: &[(String, String)],
+    ) -> anyhow::Result<Self> {
+        let first = plate
+            .index_of(start)
+            .ok_or_else(|| anyhow::anyhow!("{} is not on a {} plate", start, plate))?;
+        if first + samples.len() > plate.size() {
+            anyhow::bail!(
+                "{} samples do not fit on a {} plate from {}",
+                samples.len(),
+                plate,
+                start
+            );
+        }
+        let rules = SanitizeRules::default();
+        let samples = samples
+            .iter()
+            .enumerate()
+            .map(|(i, (template, primer))| OrderSample {
+                index: i + 1,
+                well: plate.well_at(first + i).unwrap().to_string(),
+                template: rules.sanitize_field(template),
+                primer: rules.sanitize_field(primer),
+            })
+            .collect();
+        Ok(Self {
+            vendor: vendor.to_string(),
+            date: date.to_string(),
+            plate: plate.to_string(),
+            samples,
+        })
+    }
+
+    /// The submission sheet in the vendor's column layout, as CSV
+    pub fn form(&self) -> anyhow::Result<String> {
+        let vendor: Vendor = self.vendor.parse().map_err(anyhow::Error::msg)?;
+        let header: &[&str] = match vendor {
+            Vendor::Sangon => &["No.", "Sample Name", "Primer", "Well"],
+            Vendor::Ruibio => &["Well", "Sample Name", "Primer"],
+            Vendor::Genewiz => &["No.", "Well", "Sample Name", "Primer Name"],
+            _ => anyhow::bail!("No order form for {}", vendor),
+        };
+        let mut csv = header.join(",");
+        csv.push('\n');
+        for sample in &self.samples {
+            let index = match vendor {
+                // Sangon returns the sample number as the file name prefix
+                Vendor::Sangon => format!("{:04}", sample.index),
+                _ => sample.index.to_string(),
+            };
+            let (well, template, primer) = (&sample.well, &sample.template, &sample.primer);
+            let row = match vendor {
+                Vendor::Sangon => vec![&index, template, primer, well],
+                Vendor::Ruibio => vec![well, template, primer],
+                _ => vec![&index, well, template, primer],
+            };
+            let row: Vec<String> = row.into_iter().map(|f| csv_field(f)).collect();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        Ok(csv)
+    }
+
+    /// `orders/` in the user's data directory
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("sanger_rename").join("orders"))
+    }
+
+    /// Write the order as `{vendor}_{date}.json` in `dir`, numbering repeated names
+    pub fn save(&self, dir: &Path) -> anyhow::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let stem = format!("{}_{}", self.vendor.to_lowercase(), self.date);
+        let mut path = dir.join(format!("{}.json", stem));
+        let mut n = 1;
+        while path.exists() {
+            n += 1;
+            path = dir.join(format!("{}_{}.json", stem, n));
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+
+    /// The most recently saved order in `dir`, of `vendor` if given
+    pub fn latest(dir: &Path, vendor: Option<Vendor>) -> anyhow::Result<Option<PathBuf>> {
+        if !dir.exists() {
+            return Ok(None);
+        }
+        let prefix = vendor.map(|v| format!("{}_", v.to_string().to_lowercase()));
+        let mut latest = None;
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !name.ends_with(".json")
+                || prefix
+                    .as_ref()
+                    .is_some_and(|prefix| !name.starts_with(prefix))
+            {
+                continue;
+            }
+            let modified = std::fs::metadata(&path)?.modified()?;
+            if latest.as_ref().is_none_or(|(time, _)| modified > *time) {
+                latest = Some((modified, path));
+            }
+        }
+        Ok(latest.map(|(_, path)| path))
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|err| anyhow::anyhow!("{}: {}", path.display(), err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sample_list() {
+        let text = "template,primer\n# plasmids\nK528-1\tT7\tSP6\n,,\nK528-2, T7\nGFP ctrl\n";
+        let samples = parse_sample_list(text, Some("M13F")).unwrap();
+        let pairs: Vec<(&str, &str)> = samples
+            .iter()
+            .map(|(t, p)| (t.as_str(), p.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("K528-1", "T7"),
+                ("K528-1", "SP6"),
+                ("K528-2", "T7"),
+                ("GFP ctrl", "M13F")
+            ]
+        );
+        assert!(parse_sample_list("K528-1\n", None).is_err());
+    }
+
+    #[test]
+    fn test_order_form_and_save() {
+        let samples: Vec<(String, String)> = (1..=3)
+            .map(|i| (format!("K528.{}", i), "T7".to_string()))
+            .collect();
+        let date = time::macros::date!(2025 - 06 - 01);
+        let start = Well::parse("G01").unwrap();
+        let order =
+            Order::new(Vendor::Sangon, date, PlateFormat::Wells96, start, &samples).unwrap();
+        let wells: Vec<&str> = order.samples.iter().map(|s| s.well.as_str()).collect();
+        assert_eq!(wells, ["G01", "H01", "A02"]);
+        assert_eq!(order.samples[0].template, "K528-1");
+        assert_eq!(
+            order.form().unwrap().lines().take(2).collect::<Vec<_>>(),
+            ["No.,Sample Name,Primer,Well", "0001,K528-1,T7,G01"]
+        );
+        let start = Well::parse("H12").unwrap();
+        assert!(Order::new(Vendor::Ruibio, date, PlateFormat::Wells96, start, &samples).is_err());
+
+        let dir = std::env::temp_dir().join("sanger_rename_order_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let first = order.save(&dir).unwrap();
+        let second = order.save(&dir).unwrap();
+        assert_eq!(first.file_name().unwrap(), "sangon_2025-06-01.json");
+        assert_eq!(second.file_name().unwrap(), "sangon_2025-06-01_2.json");
+        assert_eq!(Order::load(&second).unwrap(), order);
+        assert!(Order::latest(&dir, Some(Vendor::Sangon)).unwrap().is_some());
+        assert_eq!(Order::latest(&dir, Some(Vendor::Ruibio)).unwrap(), None);
+    }
+}
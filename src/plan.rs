@@ -0,0 +1,301 @@
+//! Planning a batch of renames without touching the files
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::field_rule::FieldRule;
+use crate::mapping::NameMappings;
+use crate::sanger_filename::SangerFilename;
+use crate::sanitize;
+use crate::watch;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlannedAction {
+    Rename,
+    /// The name is already standardized
+    Skip,
+    /// The file would be left alone because renaming it would fail
+    Fail,
+    /// The file does not exist
+    Missing,
+}
+
+/// What renaming would do to one file
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct PlannedRename {
+    pub source: PathBuf,
+    pub target: Option<PathBuf>,
+    pub vendor: Option<String>,
+    pub action: PlannedAction,
+    /// Template or primer names changed by sanitization
+    pub sanitized: bool,
+    /// Why the file fails, or for a rename, what may still stop it
+    pub problems: Vec<String>,
+}
+
+impl PlannedRename {
+    fn fail(source: &Path, vendor: Option<String>, problem: String) -> Self {
+        Self::failing(source, vendor, PlannedAction::Fail, problem)
+    }
+
+    fn failing(
+        source: &Path,
+        vendor: Option<String>,
+        action: PlannedAction,
+        problem: String,
+    ) -> Self {
+        Self {
+            source: source.to_path_buf(),
+            target: None,
+            vendor,
+            action,
+            sanitized: false,
+            problems: vec![problem],
+        }
+    }
+}
+
+/// Parse a file for renaming as [`plan`] does, or give the vendor (if known) and why it cannot be renamed
+pub fn prepare(
+    source: &Path,
+    config: &Config,
+    mappings: &NameMappings,
+    field_rule: Option<FieldRule>,
+    date: time::Date,
+) -> Result<SangerFilename, (Option<String>, String)> {
+    let mut sanger_fn = watch::prepare(source, config.vendor, field_rule)
+        .map_err(|reason| (config.vendor.map(|vendor| vendor.to_string()), reason))?;
+    config.apply(&mut sanger_fn);
+    mappings.apply(&mut sanger_fn);
+    sanger_fn
+        .set_date(date)
+        .map_err(|err| (Some(sanger_fn.get_vendor_name()), err.to_string()))?;
+    Ok(sanger_fn)
+}
+
+/// Plan the renames of `filenames` the way the TUI would carry them out
+///
+/// Each file is parsed with the configured vendor, or the one detected from
+/// its name, then the mappings, Genewiz rule and configured naming style are
+/// applied and the dates set to `date`. The targets are checked together for collisions and names
+/// that are not portable, as renaming would.
+pub fn plan(
+    filenames: &[String],
+    config: &Config,
+    mappings: &NameMappings,
+    field_rule: Option<FieldRule>,
+    date: time::Date,
+) -> Vec<PlannedRename> {
+    let mut plans = Vec::new();
+    let mut renames = Vec::new();
+    for filename in filenames {
+        let source = Path::new(filename);
+        if !source.is_file() {
+            let problem = "file not found".to_string();
+            plans.push(PlannedRename::failing(
+                source,
+                None,
+                PlannedAction::Missing,
+                problem,
+            ));
+            continue;
+        }
+        let name = source.file_name().unwrap_or_default().to_string_lossy();
+        if config.is_renamed(&name) {
+            plans.push(PlannedRename {
+                source: source.to_path_buf(),
+                target: None,
+                vendor: None,
+                action: PlannedAction::Skip,
+                sanitized: false,
+                problems: Vec::new(),
+            });
+            continue;
+        }
+        let sanger_fn = match prepare(source, config, mappings, field_rule, date) {
+            Ok(sanger_fn) => sanger_fn,
+            Err((vendor, reason)) => {
+                plans.push(PlannedRename::fail(source, vendor, reason));
+                continue;
+            }
+        };
+        let vendor = Some(sanger_fn.get_vendor_name());
+        let target = sanger_fn.get_standardized_path();
+        let problems = match folder_problem(&target) {
+            Some(FolderProblem::Fails(problem)) => {
+                plans.push(PlannedRename::fail(source, vendor, problem));
+                continue;
+            }
+            Some(FolderProblem::ReadOnly(problem)) => vec![problem],
+            None => Vec::new(),
+        };
+        renames.push((source.to_path_buf(), target.clone()));
+        plans.push(PlannedRename {
+            source: source.to_path_buf(),
+            target: Some(target),
+            vendor,
+            action: PlannedAction::Rename,
+            sanitized: sanger_fn.is_sanitized(),
+            problems,
+        });
+    }
+
+    let mut issues = sanitize::check_renames(&renames).into_iter();
+    for plan in plans
+        .iter_mut()
+        .filter(|plan| plan.action == PlannedAction::Rename)
+    {
+        let issues = issues.next().unwrap_or_default();
+        if !issues.is_empty() {
+            plan.action = PlannedAction::Fail;
+            plan.problems
+                .extend(issues.iter().map(|issue| issue.to_string()));
+        }
+    }
+    plans
+}
+
+enum FolderProblem {
+    /// The target cannot be written there
+    Fails(String),
+    /// The folder is marked read-only, which may or may not stop the rename,
+    /// e.g. depending on the user and the folder's ACLs
+    ReadOnly(String),
+}
+
+/// What stands in the way of writing `target`, judged from the nearest
+/// existing folder of its path, which renaming would create the rest of
+fn folder_problem(target: &Path) -> Option<FolderProblem> {
+    let folder = target
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())?;
+    let existing = folder.ancestors().find(|path| path.exists())?;
+    match std::fs::metadata(existing) {
+        Ok(metadata) if !metadata.is_dir() => Some(FolderProblem::Fails(format!(
+            "{} is not a folder",
+            existing.display()
+        ))),
+        Ok(metadata) if metadata.permissions().readonly() => Some(FolderProblem::ReadOnly(
+            format!("folder {} is read-only", existing.display()),
+        )),
+        Ok(_) => None,
+        Err(err) => Some(FolderProblem::Fails(format!(
+            "{}: {}",
+            existing.display(),
+            err
+        ))),
+    }
+}
+
+/// The plan as an aligned `old → new` table, one file per line
+pub fn to_table(plans: &[PlannedRename]) -> String {
+    let name = |path: &Path| {
+        path.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    };
+    let width = plans
+        .iter()
+        .map(|plan| name(&plan.source).chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut table = String::new();
+    for plan in plans {
+        let source = name(&plan.source);
+        let padding = " ".repeat(width - source.chars().count());
+        // Targets in another folder, e.g. the configured output folder, are shown in full
+        let target = |target: &Path| {
+            if target.parent() == plan.source.parent() {
+                name(target)
+            } else {
+                target.display().to_string()
+            }
+        };
+        let outcome = match (plan.action, &plan.target) {
+            (PlannedAction::Rename, Some(path)) => {
+                let mut notes = plan.problems.clone();
+                if plan.sanitized {
+                    notes.insert(0, "sanitized".to_string());
+                }
+                if notes.is_empty() {
+                    target(path)
+                } else {
+                    format!("{} ({})", target(path), notes.join("; "))
+                }
+            }
+            (PlannedAction::Skip, _) => "unchanged, already standardized".to_string(),
+            (PlannedAction::Missing, _) => "MISSING".to_string(),
+            _ => format!("FAILS: {}", plan.problems.join("; ")),
+        };
+        table.push_str(&format!("{}{} → {}\n", source, padding, outcome));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan() {
+        let dir = std::env::temp_dir().join("sanger_rename_plan");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let filenames: Vec<String> = [
+            "K528-1.C1.34781340.B08.ab1",
+            "K528-1.C1.34781341.B09.ab1",
+            "pGFP 2.T7.34781342.B10.ab1",
+            "250601.K528-2.T7.ab1",
+            "notes.ab1",
+            "K528-3.T7.34781343.B11.ab1",
+        ]
+        .iter()
+        .map(|name| dir.join(name).to_string_lossy().to_string())
+        .collect();
+        for filename in &filenames[..5] {
+            std::fs::write(filename, b"trace").unwrap();
+        }
+        let mappings: NameMappings = toml::from_str("[primers]\nC1 = \"T7\"\n").unwrap();
+        let date = time::macros::date!(2025 - 06 - 01);
+        let plans = plan(&filenames, &Config::default(), &mappings, None, date);
+
+        let actions: Vec<PlannedAction> = plans.iter().map(|plan| plan.action).collect();
+        use PlannedAction::*;
+        assert_eq!(actions, [Fail, Fail, Rename, Skip, Fail, Missing]);
+        assert_eq!(
+            plans[0].problems,
+            ["same name as K528-1.C1.34781341.B09.ab1"]
+        );
+        assert_eq!(plans[2].target, Some(dir.join("250601.pGFP_2.T7.ab1")));
+        assert!(plans[2].sanitized);
+        assert_eq!(plans[4].problems, ["vendor not recognized"]);
+        assert_eq!(plans[5].problems, ["file not found"]);
+        // Nothing was renamed
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 5);
+
+        let table = to_table(&plans);
+        assert!(table.contains("pGFP 2.T7.34781342.B10.ab1 → 250601.pGFP_2.T7.ab1 (sanitized)\n"));
+        assert!(table.contains("K528-3.T7.34781343.B11.ab1 → MISSING\n"));
+
+        // A read-only folder is noted, but the rename is not assumed to fail
+        let locked = dir.join("locked");
+        std::fs::create_dir(&locked).unwrap();
+        let mut permissions = std::fs::metadata(&locked).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&locked, permissions).unwrap();
+        let config = Config {
+            output_dir: Some(locked.join("renamed")),
+            ..Config::default()
+        };
+        let plans = plan(&filenames[2..3], &config, &mappings, None, date);
+        assert_eq!(plans[0].action, Rename);
+        assert_eq!(
+            plans[0].problems,
+            [format!("folder {} is read-only", locked.display())]
+        );
+        assert!(to_table(&plans).contains("(sanitized; folder "));
+    }
+}
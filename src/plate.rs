@@ -0,0 +1,147 @@
+//! Wells and plate layouts of a sequencing delivery
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A well such as `B08`, stored as 0-based row and column
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Well {
+    pub row: u8,
+    pub column: u8,
+}
+
+impl Well {
+    /// Parse a row letter A-P and a column 1-24, such as `B08` or `B8`
+    pub fn parse(token: &str) -> Option<Self> {
+        let mut chars = token.chars();
+        let row = chars.next().filter(|r| ('A'..='P').contains(r))?;
+        let column = chars.as_str();
+        if !(1..=2).contains(&column.len()) || !column.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let column: u8 = column.parse().ok().filter(|c| (1..=24).contains(c))?;
+        Some(Self {
+            row: row as u8 - b'A',
+            column: column - 1,
+        })
+    }
+
+    pub fn row_letter(self) -> char {
+        (b'A' + self.row) as char
+    }
+}
+
+impl fmt::Display for Well {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{:02}", self.row_letter(), self.column + 1)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PlateFormat {
+    /// 8 rows × 12 columns
+    #[default]
+    Wells96,
+    /// 16 rows × 24 columns
+    Wells384,
+}
+
+impl PlateFormat {
+    pub fn rows(self) -> u8 {
+        match self {
+            PlateFormat::Wells96 => 8,
+            PlateFormat::Wells384 => 16,
+        }
+    }
+
+    pub fn columns(self) -> u8 {
+        match self {
+            PlateFormat::Wells96 => 12,
+            PlateFormat::Wells384 => 24,
+        }
+    }
+
+    pub fn size(self) -> usize {
+        self.rows() as usize * self.columns() as usize
+    }
+
+    pub fn contains(self, well: Well) -> bool {
+        well.row < self.rows() && well.column < self.columns()
+    }
+
+    /// The smallest plate holding all `wells`
+    pub fn fitting(wells: impl IntoIterator<Item = Well>) -> Self {
+        if wells
+            .into_iter()
+            .all(|well| PlateFormat::Wells96.contains(well))
+        {
+            PlateFormat::Wells96
+        } else {
+            PlateFormat::Wells384
+        }
+    }
+
+    /// Well number `index` when filling the plate column by column (A01, B01, …)
+    pub fn well_at(self, index: usize) -> Option<Well> {
+        (index < self.size()).then(|| Well {
+            row: (index % self.rows() as usize) as u8,
+            column: (index / self.rows() as usize) as u8,
+        })
+    }
+
+    /// Position of `well` when filling the plate column by column
+    pub fn index_of(self, well: Well) -> Option<usize> {
+        self.contains(well)
+            .then(|| well.column as usize * self.rows() as usize + well.row as usize)
+    }
+}
+
+impl FromStr for PlateFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_end_matches("-well") {
+            "96" => Ok(PlateFormat::Wells96),
+            "384" => Ok(PlateFormat::Wells384),
+            _ => Err(format!("Unknown plate format: {} (expected 96 or 384)", s)),
+        }
+    }
+}
+
+impl fmt::Display for PlateFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-well", self.size())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_well() {
+        let well = Well::parse("B08").unwrap();
+        assert_eq!((well.row, well.column), (1, 7));
+        assert_eq!(well.to_string(), "B08");
+        assert_eq!(Well::parse("P24").unwrap().to_string(), "P24");
+        assert_eq!(Well::parse("C3").unwrap().to_string(), "C03");
+        for token in ["Q01", "A00", "A25", "A+1", "a01", "A", "A001"] {
+            assert_eq!(Well::parse(token), None, "{}", token);
+        }
+    }
+
+    #[test]
+    fn test_plate_format() {
+        let wells = ["A01", "H12"].map(|w| Well::parse(w).unwrap());
+        assert_eq!(PlateFormat::fitting(wells), PlateFormat::Wells96);
+        let wells = ["A01", "I01"].map(|w| Well::parse(w).unwrap());
+        assert_eq!(PlateFormat::fitting(wells), PlateFormat::Wells384);
+        let plate = PlateFormat::Wells96;
+        assert_eq!(plate.well_at(0).unwrap().to_string(), "A01");
+        assert_eq!(plate.well_at(9).unwrap().to_string(), "B02");
+        assert_eq!(plate.well_at(96), None);
+        assert_eq!(plate.index_of(Well::parse("B02").unwrap()), Some(9));
+        assert_eq!("384".parse(), Ok(PlateFormat::Wells384));
+        assert_eq!("96-well".parse(), Ok(PlateFormat::Wells96));
+    }
+}
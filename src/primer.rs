@@ -0,0 +1,406 @@
+//! Checking that a read's primer label matches where the read lies
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::abif::Trace;
+use crate::align::{self, Alignment, Strand};
+use crate::mixed_peaks;
+use crate::reference::{ReferenceLibrary, parse_fasta};
+
+/// Length of the primer 3' end searched for binding sites
+pub const CORE_LENGTH: usize = 18;
+/// Mismatches allowed in the 3' core of a binding site
+pub const MAX_MISMATCHES: usize = 2;
+/// Bases a read may start away from its primer, covering the unreadable first peaks
+pub const MAX_START_DISTANCE: usize = 150;
+/// Bases at the start of a read searched for the primer without a reference
+pub const READ_START_REGION: usize = 100;
+
+/// Primer sequences by name, case-insensitively
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct PrimerLibrary {
+    primers: HashMap<String, Vec<u8>>,
+}
+
+impl PrimerLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `primers.fasta` in the user's config directory
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("sanger_rename").join("primers.fasta"))
+    }
+
+    /// The library at [`Self::default_path`], if there is one
+    pub fn load_default() -> anyhow::Result<Option<Self>> {
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::load(&path).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Load a FASTA file, or a table with a name and a sequence per line
+    ///
+    /// Table columns may be separated by tabs, commas or spaces; lines
+    /// starting with `#` are comments.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let library = Self::parse(&text);
+        if library.is_empty() {
+            anyhow::bail!("{}: no primers", path.display());
+        }
+        Ok(library)
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut library = Self::new();
+        if text.trim_start().starts_with('>') {
+            for record in parse_fasta(text) {
+                library.insert(&record.name, &record.sequence);
+            }
+            return library;
+        }
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line
+                .split(['\t', ',', ' '])
+                .map(str::trim)
+                .filter(|f| !f.is_empty());
+            if let (Some(name), Some(sequence)) = (fields.next(), fields.next())
+                && sequence.bytes().all(|b| b.is_ascii_alphabetic())
+            {
+                library.insert(name, sequence.as_bytes());
+            }
+        }
+        library
+    }
+
+    pub fn insert(&mut self, name: &str, sequence: &[u8]) {
+        self.primers
+            .insert(name.to_lowercase(), sequence.to_ascii_uppercase());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.primers.get(&name.to_lowercase()).map(Vec::as_slice)
+    }
+
+    pub fn len(&self) -> usize {
+        self.primers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.primers.is_empty()
+    }
+}
+
+/// Where a primer anneals on a reference
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+pub struct PrimerSite {
+    /// `Forward` if the primer sequence is found on the given strand
+    pub strand: Strand,
+    /// Reference region covered by the primer, 0-based and end-exclusive
+    pub start: usize,
+    pub end: usize,
+}
+
+impl PrimerSite {
+    /// Whether a read primed here would align as `alignment` does
+    fn primes(&self, alignment: &Alignment) -> bool {
+        match self.strand {
+            Strand::Forward => {
+                alignment.strand == Strand::Forward
+                    && alignment.reference_start >= self.start
+                    && alignment.reference_start <= self.end + MAX_START_DISTANCE
+            }
+            Strand::Reverse => {
+                alignment.strand == Strand::Reverse
+                    && alignment.reference_end <= self.end
+                    && alignment.reference_end + MAX_START_DISTANCE >= self.start
+            }
+        }
+    }
+}
+
+fn mismatches(a: &[u8], b: &[u8]) -> usize {
+    a.iter()
+        .zip(b)
+        .filter(|(x, y)| !x.eq_ignore_ascii_case(y))
+        .count()
+}
+
+/// Binding sites of a primer's 3' end on both strands of `sequence`
+pub fn find_sites(primer: &[u8], sequence: &[u8]) -> Vec<PrimerSite> {
+    let core = &primer[primer.len().saturating_sub(CORE_LENGTH)..];
+    if core.is_empty() || core.len() > sequence.len() {
+        return Vec::new();
+    }
+    let reverse_core = align::reverse_complement(core);
+    let mut sites = Vec::new();
+    for (position, window) in sequence.windows(core.len()).enumerate() {
+        if mismatches(window, core) <= MAX_MISMATCHES {
+            let end = position + core.len();
+            sites.push(PrimerSite {
+                strand: Strand::Forward,
+                start: end.saturating_sub(primer.len()),
+                end,
+            });
+        }
+        if mismatches(window, &reverse_core) <= MAX_MISMATCHES {
+            sites.push(PrimerSite {
+                strand: Strand::Reverse,
+                start: position,
+                end: (position + primer.len()).min(sequence.len()),
+            });
+        }
+    }
+    sites
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrimerStatus {
+    /// The read lies where its primer anneals
+    Consistent,
+    /// The read does not come from the named primer
+    Inconsistent,
+    /// Nothing to check against
+    Unverified,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct PrimerCheck {
+    pub status: PrimerStatus,
+    /// Orientation of the read relative to the reference, when known
+    pub strand: Option<Strand>,
+    pub note: String,
+}
+
+impl PrimerCheck {
+    fn unverified(note: String) -> Self {
+        Self {
+            status: PrimerStatus::Unverified,
+            strand: None,
+            note,
+        }
+    }
+}
+
+impl std::fmt::Display for PrimerCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self.status {
+            PrimerStatus::Consistent => "ok",
+            PrimerStatus::Inconsistent => "WRONG",
+            PrimerStatus::Unverified => "?",
+        };
+        match self.strand {
+            Some(strand) => write!(f, "{} ({}) {}", status, strand, self.note),
+            None => write!(f, "{} {}", status, self.note),
+        }
+    }
+}
+
+/// What a primer is checked against
+pub enum Evidence<'a> {
+    /// The read's alignment to its reference
+    Alignment {
+        reference: &'a [u8],
+        alignment: &'a Alignment,
+    },
+    /// The read alone, whose start may still carry the primer's 3' end
+    Read(&'a [u8]),
+}
+
+pub fn check_primer(primer: &[u8], evidence: Evidence) -> PrimerCheck {
+    match evidence {
+        Evidence::Alignment {
+            reference,
+            alignment,
+        } => {
+            let strand = Some(alignment.strand);
+            let sites = find_sites(primer, reference);
+            if let Some(site) = sites.iter().find(|site| site.primes(alignment)) {
+                return PrimerCheck {
+                    status: PrimerStatus::Consistent,
+                    strand,
+                    note: format!("primer at {}", site.start + 1),
+                };
+            }
+            let note = match sites.first() {
+                Some(site) => format!(
+                    "read at {}..{}, primer at {} ({})",
+                    alignment.reference_start + 1,
+                    alignment.reference_end,
+                    site.start + 1,
+                    site.strand
+                ),
+                None => "primer does not anneal to the reference".to_string(),
+            };
+            PrimerCheck {
+                status: PrimerStatus::Inconsistent,
+                strand,
+                note,
+            }
+        }
+        Evidence::Read(read) => {
+            let start = &read[..read.len().min(READ_START_REGION + CORE_LENGTH)];
+            if find_sites(primer, start)
+                .iter()
+                .any(|site| site.strand == Strand::Forward)
+            {
+                PrimerCheck {
+                    status: PrimerStatus::Consistent,
+                    strand: None,
+                    note: "primer at read start".to_string(),
+                }
+            } else {
+                PrimerCheck::unverified("no reference".to_string())
+            }
+        }
+    }
+}
+
+/// Check the primer of the trace at `path`, aligning it to its template's reference if there is one
+pub fn check_trace(
+    path: &Path,
+    template_name: &str,
+    primer_name: &str,
+    primers: &PrimerLibrary,
+    references: Option<&ReferenceLibrary>,
+) -> PrimerCheck {
+    let Some(primer) = primers.get(primer_name) else {
+        return PrimerCheck::unverified(format!("no sequence for {}", primer_name));
+    };
+    let trace = match Trace::from_file(path) {
+        Ok(trace) => trace,
+        Err(err) => return PrimerCheck::unverified(err.to_string()),
+    };
+    let reference = references.and_then(|library| library.get(template_name));
+    match reference {
+        Some(reference) => match align::align(&trace.bases, &reference.sequence) {
+            Some(alignment) => check_primer(
+                primer,
+                Evidence::Alignment {
+                    reference: &reference.sequence,
+                    alignment: &alignment,
+                },
+            ),
+            None => PrimerCheck::unverified("read does not align".to_string()),
+        },
+        None => check_primer(primer, Evidence::Read(&trace.bases)),
+    }
+}
+
+/// FASTA of a read in reference orientation, reverse complemented for reverse reads
+pub fn oriented_fasta(name: &str, bases: &[u8], strand: Option<Strand>) -> String {
+    let bases = match strand {
+        Some(Strand::Reverse) => align::reverse_complement(bases),
+        _ => bases.to_vec(),
+    };
+    let mut fasta = format!(">{}\n", name);
+    for line in bases.chunks(70) {
+        fasta.push_str(&String::from_utf8_lossy(line));
+        fasta.push('\n');
+    }
+    fasta
+}
+
+/// Write the trace at `path` as `{file stem}.fasta` into `dir`, in reference orientation
+///
+/// With a `mixed_fraction`, mixed positions are written as IUPAC codes.
+pub fn write_oriented_fasta(
+    path: &Path,
+    strand: Option<Strand>,
+    mixed_fraction: Option<f64>,
+    dir: &Path,
+) -> anyhow::Result<PathBuf> {
+    let trace = Trace::from_file(path)?;
+    let bases = match mixed_fraction {
+        Some(fraction) => mixed_peaks::ambiguity_sequence(&trace, fraction),
+        None => trace.bases,
+    };
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let output = dir.join(format!("{}.fasta", stem));
+    std::fs::write(&output, oriented_fasta(&stem, &bases, strand))?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // pUC19 MCS region with the M13 forward and reverse primer sites around it
+    const REFERENCE: &str = "TTTTCCCAGTCACGACGTTGTAAAACGACGGCCAGTGAATTCGAGCTCGGTACCCGGGGATCCTCTAGAGTCGACCTGCAGGCATGCAAGCTTGGCGTAATCATGGTCATAGCTGTTTCCTGTGTGAAATTG";
+    const M13F: &[u8] = b"GTAAAACGACGGCCAGT";
+    const M13R: &[u8] = b"CAGGAAACAGCTATGAC";
+
+    #[test]
+    fn test_parse_library() {
+        let table = PrimerLibrary::parse(
+            "# name\tsequence\nM13F\tgtaaaacgacggccagt\nT7, TAATACGACTCACTATAGGG\n",
+        );
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get("m13f"), Some(M13F));
+        let fasta = PrimerLibrary::parse(">M13R\nCAGGAAACAGCTATGAC\n");
+        assert_eq!(fasta.get("M13R"), Some(M13R));
+    }
+
+    #[test]
+    fn test_find_sites() {
+        let reference = REFERENCE.as_bytes();
+        let sites = find_sites(M13F, reference);
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].strand, Strand::Forward);
+        assert_eq!(&reference[sites[0].start..sites[0].end], M13F);
+        let sites = find_sites(M13R, reference);
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].strand, Strand::Reverse);
+    }
+
+    #[test]
+    fn test_check_primer_against_alignment() {
+        let reference = REFERENCE.as_bytes();
+        let forward_read = &reference[40..110];
+        let alignment = align::align(forward_read, reference).unwrap();
+        let evidence = || Evidence::Alignment {
+            reference,
+            alignment: &alignment,
+        };
+        let check = check_primer(M13F, evidence());
+        assert_eq!(check.status, PrimerStatus::Consistent);
+        assert_eq!(check.strand, Some(Strand::Forward));
+        // A forward read labeled with the reverse primer
+        let check = check_primer(M13R, evidence());
+        assert_eq!(check.status, PrimerStatus::Inconsistent);
+
+        let reverse_read = align::reverse_complement(&reference[30..100]);
+        let alignment = align::align(&reverse_read, reference).unwrap();
+        let check = check_primer(
+            M13R,
+            Evidence::Alignment {
+                reference,
+                alignment: &alignment,
+            },
+        );
+        assert_eq!(check.status, PrimerStatus::Consistent);
+        assert_eq!(check.strand, Some(Strand::Reverse));
+        assert_eq!(
+            oriented_fasta("r", &reverse_read, check.strand),
+            format!(">r\n{}\n", &REFERENCE[30..100])
+        );
+    }
+
+    #[test]
+    fn test_check_primer_without_reference() {
+        let read = &REFERENCE.as_bytes()[10..90];
+        let check = check_primer(M13F, Evidence::Read(read));
+        assert_eq!(check.status, PrimerStatus::Consistent);
+        let check = check_primer(M13R, Evidence::Read(read));
+        assert_eq!(check.status, PrimerStatus::Unverified);
+    }
+}
@@ -0,0 +1,317 @@
+//! Checking a delivery against the order that was submitted
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::order::{Order, OrderSample};
+use crate::plate::Well;
+use crate::sanger_filename::{SangerFilename, Vendor};
+use crate::sanitize::SanitizeRules;
+
+/// What a delivered file says about the sample it holds
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct DeliveredFile {
+    pub name: String,
+    pub template: String,
+    pub primer: String,
+    pub vendor_id: String,
+    pub well: Option<String>,
+    /// Sample number, for vendors that put it in the file name instead of a well
+    pub index: Option<usize>,
+}
+
+impl DeliveredFile {
+    pub fn from_sanger_filename(sanger_fn: &SangerFilename) -> Self {
+        let index = match sanger_fn.get_vendor() {
+            // Sangon names start with the sample number, e.g. `0001_31225060307072_…`
+            Vendor::Sangon => sanger_fn
+                .get_file_stem()
+                .split('_')
+                .next()
+                .and_then(|n| n.parse().ok()),
+            _ => None,
+        };
+        Self {
+            name: sanger_fn.show_file_name(),
+            template: sanger_fn.get_template_name(),
+            primer: sanger_fn.get_primer_name(),
+            vendor_id: sanger_fn.get_vendor_id(),
+            well: sanger_fn.get_well(),
+            index,
+        }
+    }
+
+    /// Where the file says it came from, comparable with `sample`
+    ///
+    /// Wells are compared as plate positions, so `B8` matches `B08`.
+    fn position_matches(&self, sample: &OrderSample) -> Option<bool> {
+        match (&self.well, self.index) {
+            (Some(well), _) => {
+                let parse = |well: &str| Well::parse(&well.to_ascii_uppercase());
+                Some(match (parse(well), parse(&sample.well)) {
+                    (Some(delivered), Some(ordered)) => delivered == ordered,
+                    _ => well.eq_ignore_ascii_case(&sample.well),
+                })
+            }
+            (None, Some(index)) => Some(index == sample.index),
+            (None, None) => None,
+        }
+    }
+
+    fn position(&self) -> String {
+        match (&self.well, self.index) {
+            (Some(well), _) => well.clone(),
+            (None, Some(index)) => format!("#{}", index),
+            (None, None) => "?".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Discrepancy {
+    /// An ordered sample with no file
+    Missing { sample: OrderSample },
+    /// A file matching no ordered sample by name or position
+    Extra { file: DeliveredFile },
+    /// Several files named as the same ordered sample
+    Duplicate {
+        sample: OrderSample,
+        files: Vec<String>,
+    },
+    /// A file named as one sample but coming from another sample's position
+    Moved {
+        sample: OrderSample,
+        file: DeliveredFile,
+    },
+    /// A file in an ordered sample's position under another name
+    Renamed {
+        sample: OrderSample,
+        file: DeliveredFile,
+    },
+}
+
+impl std::fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Discrepancy::Missing { sample } => write!(
+                f,
+                "missing: {}.{} ({})",
+                sample.template, sample.primer, sample.well
+            ),
+            Discrepancy::Extra { file } => write!(f, "not ordered: {}", file.name),
+            Discrepancy::Duplicate { sample, files } => write!(
+                f,
+                "duplicate: {}.{} in {}",
+                sample.template,
+                sample.primer,
+                files.join(", ")
+            ),
+            Discrepancy::Moved { sample, file } => write!(
+                f,
+                "position: {} is from {}, ordered in {}",
+                file.name,
+                file.position(),
+                sample.well
+            ),
+            Discrepancy::Renamed { sample, file } => write!(
+                f,
+                "name: {} in {} was ordered as {}.{}",
+                file.name,
+                file.position(),
+                sample.template,
+                sample.primer
+            ),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct Reconciliation {
+    pub ordered: usize,
+    pub delivered: usize,
+    /// Ordered samples delivered under their name and position
+    pub matched: usize,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl Reconciliation {
+    pub fn is_clean(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} ordered, {} delivered, {} as ordered, {} discrepancies",
+            self.ordered,
+            self.delivered,
+            self.matched,
+            self.discrepancies.len()
+        )
+    }
+}
+
+/// A warning when the order was placed with another vendor than the one that delivered
+pub fn vendor_warning(order: &Order, vendor: Vendor) -> Option<String> {
+    match order.vendor.parse::<Vendor>() {
+        Ok(ordered) if ordered == vendor => None,
+        _ => Some(format!(
+            "The order was placed with {}, but the files are named by {}",
+            order.vendor, vendor
+        )),
+    }
+}
+
+/// Match delivered files to ordered samples by name, then by position
+///
+/// Names are compared case-insensitively after the same sanitization the
+/// order applied. A file named as an ordered sample is counted for it even
+/// when its position differs; a file whose name matches nothing is assigned
+/// to the sample ordered at its position, if any.
+pub fn reconcile(order: &Order, files: &[DeliveredFile]) -> Reconciliation {
+    let rules = SanitizeRules::default();
+    let key = |template: &str, primer: &str| {
+        (
+            rules.sanitize_field(template).to_lowercase(),
+            rules.sanitize_field(primer).to_lowercase(),
+        )
+    };
+    let mut by_name: HashMap<(String, String), Vec<usize>> = HashMap::new();
+    for (i, sample) in order.samples.iter().enumerate() {
+        by_name
+            .entry(key(&sample.template, &sample.primer))
+            .or_default()
+            .push(i);
+    }
+
+    let mut named: Vec<Vec<usize>> = vec![Vec::new(); order.samples.len()];
+    let mut positioned = vec![false; order.samples.len()];
+    let mut moved = vec![false; order.samples.len()];
+    let mut discrepancies = Vec::new();
+    for (f, file) in files.iter().enumerate() {
+        let candidates = by_name
+            .get(&key(&file.template, &file.primer))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        // The same template and primer may be ordered in several wells
+        let at_position = candidates
+            .iter()
+            .find(|&&i| file.position_matches(&order.samples[i]) == Some(true));
+        let unclaimed = candidates.iter().find(|&&i| named[i].is_empty());
+        if let Some(&i) = at_position.or(unclaimed).or(candidates.first()) {
+            let sample = &order.samples[i];
+            named[i].push(f);
+            if file.position_matches(sample) == Some(false) {
+                moved[i] = true;
+                discrepancies.push(Discrepancy::Moved {
+                    sample: sample.clone(),
+                    file: file.clone(),
+                });
+            }
+        } else if let Some(i) = (0..order.samples.len())
+            .find(|&i| file.position_matches(&order.samples[i]) == Some(true))
+        {
+            positioned[i] = true;
+            discrepancies.push(Discrepancy::Renamed {
+                sample: order.samples[i].clone(),
+                file: file.clone(),
+            });
+        } else {
+            discrepancies.push(Discrepancy::Extra { file: file.clone() });
+        }
+    }
+
+    for (i, sample) in order.samples.iter().enumerate() {
+        match named[i].len() {
+            0 if !positioned[i] => discrepancies.push(Discrepancy::Missing {
+                sample: sample.clone(),
+            }),
+            0 | 1 => {}
+            _ => discrepancies.push(Discrepancy::Duplicate {
+                sample: sample.clone(),
+                files: named[i].iter().map(|&f| files[f].name.clone()).collect(),
+            }),
+        }
+    }
+    let matched = (0..order.samples.len())
+        .filter(|&i| named[i].len() == 1 && !moved[i])
+        .count();
+    Reconciliation {
+        ordered: order.samples.len(),
+        delivered: files.len(),
+        matched,
+        discrepancies,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plate::{PlateFormat, Well};
+
+    fn delivered(name: &str) -> DeliveredFile {
+        DeliveredFile::from_sanger_filename(&SangerFilename::new(name, Vendor::Ruibio))
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let samples: Vec<(String, String)> = ["K528-1", "K528-2", "K528-3", "GFP", "PROT-1"]
+            .iter()
+            .map(|t| (t.to_string(), "T7".to_string()))
+            .collect();
+        let date = time::macros::date!(2025 - 06 - 01);
+        let start = Well::parse("A01").unwrap();
+        let order =
+            Order::new(Vendor::Ruibio, date, PlateFormat::Wells96, start, &samples).unwrap();
+        let files: Vec<DeliveredFile> = [
+            // Wells written without the leading zero are the same position
+            "K528-1.T7.34781340.A1.ab1",
+            "k528-2.t7.34781341.B01.ab1",
+            "K528-2.T7.34781345.B01.ab1",
+            "K528-3.T7.34781342.D01.ab1",
+            "PROT-I.T7.34781343.E01.ab1",
+            "ACTIN.T7.34781344.F01.ab1",
+        ]
+        .iter()
+        .map(|name| delivered(name))
+        .collect();
+        let result = reconcile(&order, &files);
+        assert_eq!(
+            (result.ordered, result.delivered, result.matched),
+            (5, 6, 1)
+        );
+        let report: Vec<String> = result.discrepancies.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            report,
+            [
+                "position: K528-3.T7.34781342.D01.ab1 is from D01, ordered in C01",
+                "name: PROT-I.T7.34781343.E01.ab1 in E01 was ordered as PROT-1.T7",
+                "not ordered: ACTIN.T7.34781344.F01.ab1",
+                "duplicate: K528-2.T7 in k528-2.t7.34781341.B01.ab1, K528-2.T7.34781345.B01.ab1",
+                "missing: GFP.T7 (D01)",
+            ]
+        );
+        assert!(!result.is_clean());
+
+        assert_eq!(vendor_warning(&order, Vendor::Ruibio), None);
+        assert_eq!(
+            vendor_warning(&order, Vendor::Genewiz).unwrap(),
+            "The order was placed with Ruibio, but the files are named by Genewiz"
+        );
+    }
+
+    #[test]
+    fn test_sangon_sample_numbers() {
+        let samples = vec![("TXPCR".to_string(), "SP1".to_string())];
+        let date = time::macros::date!(2025 - 06 - 01);
+        let start = Well::parse("A01").unwrap();
+        let order =
+            Order::new(Vendor::Sangon, date, PlateFormat::Wells96, start, &samples).unwrap();
+        let file = DeliveredFile::from_sanger_filename(&SangerFilename::new(
+            "0001_31225060307072_(TXPCR)_[SP1].ab1",
+            Vendor::Sangon,
+        ));
+        assert_eq!((file.index, file.well.as_deref()), (Some(1), None));
+        assert!(reconcile(&order, &[file]).is_clean());
+    }
+}
@@ -0,0 +1,212 @@
+//! Reference sequences from FASTA and GenBank files, looked up by template name
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// File extensions picked up when loading a directory of references
+pub const REFERENCE_EXTENSIONS: [&str; 7] = ["fa", "fasta", "fna", "fas", "gb", "gbk", "genbank"];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Reference {
+    pub name: String,
+    /// Upper case bases
+    pub sequence: Vec<u8>,
+}
+
+/// Parse every record of a FASTA file; the name is the first word of the header
+pub fn parse_fasta(text: &str) -> Vec<Reference> {
+    let mut references: Vec<Reference> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('>') {
+            references.push(Reference {
+                name: header
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                sequence: Vec::new(),
+            });
+        } else if let Some(reference) = references.last_mut() {
+            reference.sequence.extend(sequence_bytes(line));
+        }
+    }
+    references
+}
+
+/// Parse every record of a GenBank file; the name is the `LOCUS` name
+pub fn parse_genbank(text: &str) -> Vec<Reference> {
+    let mut references = Vec::new();
+    let mut current: Option<Reference> = None;
+    let mut in_origin = false;
+    for line in text.lines() {
+        if let Some(locus) = line.strip_prefix("LOCUS") {
+            current = Some(Reference {
+                name: locus
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                sequence: Vec::new(),
+            });
+            in_origin = false;
+        } else if line.starts_with("ORIGIN") {
+            in_origin = true;
+        } else if line.starts_with("//") {
+            references.extend(current.take());
+            in_origin = false;
+        } else if in_origin && let Some(reference) = current.as_mut() {
+            reference.sequence.extend(sequence_bytes(line));
+        }
+    }
+    // Tolerate a missing final `//`
+    references.extend(current);
+    references
+}
+
+fn sequence_bytes(line: &str) -> impl Iterator<Item = u8> + '_ {
+    line.bytes()
+        .filter(u8::is_ascii_alphabetic)
+        .map(|b| b.to_ascii_uppercase())
+}
+
+/// References indexed by name, case-insensitively
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ReferenceLibrary {
+    references: Vec<Reference>,
+    names: HashMap<String, usize>,
+}
+
+impl ReferenceLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a reference file, or every reference file in a directory
+    pub fn load(paths: &[impl AsRef<Path>]) -> anyhow::Result<Self> {
+        let mut library = Self::new();
+        for path in paths {
+            library.add_path(path.as_ref())?;
+        }
+        Ok(library)
+    }
+
+    pub fn add_path(&mut self, path: &Path) -> anyhow::Result<()> {
+        if !path.is_dir() {
+            return self.add_file(path);
+        }
+        let mut entries: Vec<_> = std::fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<_, _>>()?;
+        entries.sort();
+        for entry in entries {
+            let known = entry
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| REFERENCE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+            if entry.is_file() && known {
+                self.add_file(&entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the records of a FASTA or GenBank file
+    ///
+    /// A file holding a single record can also be found by its file stem, so
+    /// `K528-1.gb` serves template `K528-1` whatever its `LOCUS` name.
+    pub fn add_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        let text = std::fs::read_to_string(path)?;
+        let start = text.trim_start();
+        let references = if start.starts_with('>') {
+            parse_fasta(&text)
+        } else if start.starts_with("LOCUS") {
+            parse_genbank(&text)
+        } else {
+            anyhow::bail!("{}: neither FASTA nor GenBank", path.display());
+        };
+        if references.is_empty() {
+            anyhow::bail!("{}: no sequences", path.display());
+        }
+        let single = references.len() == 1;
+        for reference in references {
+            self.insert(reference);
+        }
+        if single && let Some(stem) = path.file_stem() {
+            let index = self.references.len() - 1;
+            self.names
+                .entry(stem.to_string_lossy().to_lowercase())
+                .or_insert(index);
+        }
+        Ok(())
+    }
+
+    /// Add a reference; an earlier one with the same name wins lookups
+    pub fn insert(&mut self, reference: Reference) {
+        self.names
+            .entry(reference.name.to_lowercase())
+            .or_insert(self.references.len());
+        self.references.push(reference);
+    }
+
+    pub fn get(&self, template_name: &str) -> Option<&Reference> {
+        let index = self.names.get(&template_name.to_lowercase())?;
+        self.references.get(*index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.references.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.references.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fasta_and_genbank() {
+        let fasta = parse_fasta(">K528-1 pUC19 backbone\nacgt\nACGG\n>GFP\nATG\n");
+        assert_eq!(fasta.len(), 2);
+        assert_eq!(fasta[0].name, "K528-1");
+        assert_eq!(fasta[0].sequence, b"ACGTACGG");
+
+        let genbank = parse_genbank(
+            "LOCUS       pGFP  12 bp    DNA     circular SYN 01-JAN-2025\n\
+             FEATURES             Location/Qualifiers\n\
+             \x20    gene            1..12\n\
+             ORIGIN\n\
+             \x20       1 atggtgagca ag\n\
+             //\n",
+        );
+        assert_eq!(genbank.len(), 1);
+        assert_eq!(genbank[0].name, "pGFP");
+        assert_eq!(genbank[0].sequence, b"ATGGTGAGCAAG");
+    }
+
+    #[test]
+    fn test_library_lookup() {
+        let dir = std::env::temp_dir().join("sanger_rename_reference_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("K528-1.gb"),
+            "LOCUS pK528 8 bp\nORIGIN\n 1 acgtacgt\n//\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("others.fasta"), ">GFP\nATG\n>mCherry\nATGG\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a reference").unwrap();
+
+        let library = ReferenceLibrary::load(&[&dir]).unwrap();
+        assert_eq!(library.len(), 3);
+        assert_eq!(library.get("k528-1").unwrap().name, "pK528");
+        assert_eq!(library.get("PK528").unwrap().sequence, b"ACGTACGT");
+        assert_eq!(library.get("mcherry").unwrap().sequence, b"ATGG");
+        // A multi-record file is not an alias for its records
+        assert!(library.get("others").is_none());
+        assert!(ReferenceLibrary::load(&[dir.join("notes.txt")]).is_err());
+    }
+}
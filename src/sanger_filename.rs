@@ -1,11 +1,21 @@
 use std::str::FromStr;
 use strum::EnumIter;
 
+use crate::catalog::{self, RenameRecord};
+use crate::field_rule::FieldRule;
+use crate::name_template::{NameTemplate, TemplateValues};
+use crate::plate::Well;
+use crate::sanitize::{self, SanitizeRules};
+use crate::standardized_name::StandardizedName;
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug, EnumIter)]
 pub enum Vendor {
     Sangon,
     Ruibio,
     Genewiz,
+    Tsingke,
+    Eurofins,
+    Macrogen,
 }
 
 impl FromStr for Vendor {
@@ -16,6 +26,9 @@ impl FromStr for Vendor {
             "sangon" => Ok(Vendor::Sangon),
             "ruibio" => Ok(Vendor::Ruibio),
             "genewiz" => Ok(Vendor::Genewiz),
+            "tsingke" => Ok(Vendor::Tsingke),
+            "eurofins" => Ok(Vendor::Eurofins),
+            "macrogen" => Ok(Vendor::Macrogen),
             _ => Err(format!("Unknown vendor: {}", s)),
         }
     }
@@ -27,42 +40,324 @@ impl std::fmt::Display for Vendor {
             Vendor::Sangon => write!(f, "Sangon"),
             Vendor::Ruibio => write!(f, "Ruibio"),
             Vendor::Genewiz => write!(f, "Genewiz"),
+            Vendor::Tsingke => write!(f, "Tsingke"),
+            Vendor::Eurofins => write!(f, "Eurofins"),
+            Vendor::Macrogen => write!(f, "Macrogen"),
         }
     }
 }
 
+impl<'de> serde::Deserialize<'de> for Vendor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Vendor {
+    /// Guess the vendor of a filename, trying the most distinctive schemes first
+    ///
+    /// Ruibio, Tsingke and Macrogen names must end their vendor ID with a
+    /// plate well; Genewiz, the loosest scheme, is tried last.
+    pub fn detect(filename: &str) -> Option<Vendor> {
+        const ORDER: [Vendor; 6] = [
+            Vendor::Sangon,
+            Vendor::Eurofins,
+            Vendor::Macrogen,
+            Vendor::Tsingke,
+            Vendor::Ruibio,
+            Vendor::Genewiz,
+        ];
+        ORDER.into_iter().find(|&vendor| {
+            let Ok(fields) = SangerFilename::parse(filename, vendor) else {
+                return false;
+            };
+            match vendor {
+                Vendor::Ruibio | Vendor::Tsingke | Vendor::Macrogen => fields
+                    .vendor_id
+                    .rsplit(['.', '_', '-'])
+                    .next()
+                    .is_some_and(is_well),
+                _ => true,
+            }
+        })
+    }
+}
+
+/// A part of a vendor filename that the parser tries to locate
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NameField {
+    Template,
+    Primer,
+    VendorId,
+}
+
+impl std::fmt::Display for NameField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NameField::Template => write!(f, "template name"),
+            NameField::Primer => write!(f, "primer name"),
+            NameField::VendorId => write!(f, "vendor ID"),
+        }
+    }
+}
+
+/// Explains which part of a filename did not match the vendor's naming scheme
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+    pub field: NameField,
+    pub vendor: Vendor,
+    pub filename: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "no {} found in {} filename {}",
+            self.field, self.vendor, self.filename
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The fields extracted from a vendor filename
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParsedFields {
+    pub template_name: String,
+    pub primer_name: String,
+    pub vendor_id: String,
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct SangerFilename {
     filename: String,
     primer_name: String,
     template_name: String,
     date: Option<time::Date>,
+    transliterate: bool,
+    sanitize_rules: SanitizeRules,
     vendor: Vendor,
+    field_rule: Option<FieldRule>,
+    parsed: Result<ParsedFields, ParseError>,
+    existing: Option<StandardizedName>,
+    renormalize: bool,
+    output_dir: Option<std::path::PathBuf>,
+    name_template: Option<NameTemplate>,
 }
 
 impl SangerFilename {
     /// Create a new SangerFilename with the specified vendor
+    ///
+    /// A filename that does not match the vendor is still accepted, so its
+    /// names can be filled in by hand; the failure is available from
+    /// [`SangerFilename::parse_result`]. Use [`SangerFilename::try_new`] to
+    /// reject such names instead.
     pub fn new(filename: &str, vendor: Vendor) -> Self {
+        let existing = StandardizedName::parse(filename).ok();
+        let parsed = Self::parse_fields(filename, vendor, existing.as_ref(), None);
         let mut sanger_filename = SangerFilename {
             filename: filename.to_string(),
             primer_name: String::new(),
             template_name: String::new(),
             date: None,
+            transliterate: false,
+            sanitize_rules: SanitizeRules::default(),
             vendor,
+            field_rule: None,
+            parsed,
+            existing,
+            renormalize: false,
+            output_dir: None,
+            name_template: None,
         };
+        sanger_filename.fill_parsed_names();
+        sanger_filename
+    }
 
-        // Extract primer and template names based on vendor
-        let primer_name = sanger_filename.get_primer_name();
-        let template_name = sanger_filename.get_template_name();
+    /// Like [`SangerFilename::new`], but fails on a filename that neither
+    /// matches the vendor nor is already standardized
+    pub fn try_new(filename: &str, vendor: Vendor) -> Result<Self, ParseError> {
+        let sanger_filename = Self::new(filename, vendor);
+        match sanger_filename.parse_error() {
+            Some(err) => Err(err.clone()),
+            None => Ok(sanger_filename),
+        }
+    }
 
-        sanger_filename
-            .set_primer_name(&primer_name)
-            .expect("Failed to set primer name");
-        sanger_filename
-            .set_template_name(&template_name)
-            .expect("Failed to set template name");
+    /// Parse a filename according to the naming scheme of `vendor`
+    pub fn parse(filename: &str, vendor: Vendor) -> Result<ParsedFields, ParseError> {
+        let file_stem = file_stem_of(filename);
+        let error = |field| ParseError {
+            field,
+            vendor,
+            filename: file_name_of(filename).to_string(),
+        };
+        let (template_name, primer_name, vendor_id) = match vendor {
+            Vendor::Sangon => (
+                extract_sangon_template_name(file_stem),
+                extract_sangon_primer_name(file_stem),
+                extract_sangon_vendor_id(file_stem),
+            ),
+            Vendor::Ruibio => (
+                extract_ruibio_template_name(file_stem),
+                extract_ruibio_primer_name(file_stem),
+                extract_ruibio_vendor_id(file_stem),
+            ),
+            Vendor::Genewiz => (
+                extract_genewiz_template_name(file_stem),
+                extract_genewiz_primer_name(file_stem),
+                // Genewiz names do not always carry a well, e.g. "BETA-ACTIN_T9"
+                Some(extract_genewiz_vendor_id(file_stem).unwrap_or_default()),
+            ),
+            Vendor::Tsingke => (
+                extract_tsingke_template_name(file_stem),
+                extract_tsingke_primer_name(file_stem),
+                extract_tsingke_vendor_id(file_stem),
+            ),
+            Vendor::Eurofins => (
+                extract_eurofins_template_name(file_stem),
+                extract_eurofins_primer_name(file_stem),
+                extract_eurofins_vendor_id(file_stem),
+            ),
+            Vendor::Macrogen => (
+                extract_macrogen_template_name(file_stem),
+                extract_macrogen_primer_name(file_stem),
+                extract_macrogen_vendor_id(file_stem),
+            ),
+        };
+        // Eurofins and Macrogen names are recognized by the barcode they start
+        // with, so without it the other fields are not looked for
+        if matches!(vendor, Vendor::Eurofins | Vendor::Macrogen) && vendor_id.is_none() {
+            return Err(error(NameField::VendorId));
+        }
+        Ok(ParsedFields {
+            template_name: template_name.ok_or_else(|| error(NameField::Template))?,
+            primer_name: primer_name.ok_or_else(|| error(NameField::Primer))?,
+            vendor_id: vendor_id.ok_or_else(|| error(NameField::VendorId))?,
+        })
+    }
 
-        sanger_filename
+    /// Parse a filename with a user-defined rule instead of the vendor's built-in scheme
+    pub fn parse_with_rule(
+        filename: &str,
+        vendor: Vendor,
+        rule: &FieldRule,
+    ) -> Result<ParsedFields, ParseError> {
+        rule.apply(file_stem_of(filename))
+            .map(|(template_name, primer_name, vendor_id)| ParsedFields {
+                template_name,
+                primer_name,
+                vendor_id,
+            })
+            .map_err(|field| ParseError {
+                field,
+                vendor,
+                filename: file_name_of(filename).to_string(),
+            })
+    }
+
+    /// Use `rule` instead of the vendor's scheme, re-parsing the filename
+    ///
+    /// A name the rule does not match is still parsed with the vendor's scheme.
+    pub fn set_field_rule(&mut self, rule: Option<FieldRule>) {
+        self.field_rule = rule;
+        self.reparse();
+    }
+
+    pub fn get_field_rule(&self) -> Option<&FieldRule> {
+        self.field_rule.as_ref()
+    }
+
+    fn parse_fields(
+        filename: &str,
+        vendor: Vendor,
+        existing: Option<&StandardizedName>,
+        rule: Option<&FieldRule>,
+    ) -> Result<ParsedFields, ParseError> {
+        match (existing, rule) {
+            // Already standardized names keep their fields whatever the vendor
+            (Some(existing), _) => Ok(ParsedFields {
+                template_name: existing.template_name.clone(),
+                primer_name: existing.primer_name.clone(),
+                vendor_id: String::new(),
+            }),
+            // Names the rule does not fit, e.g. a Genewiz name without a well
+            // when the rule was picked on one with a well, fall back to the
+            // vendor's scheme
+            (None, Some(rule)) => Self::parse_with_rule(filename, vendor, rule)
+                .or_else(|err| Self::parse(filename, vendor).map_err(|_| err)),
+            (None, None) => Self::parse(filename, vendor),
+        }
+    }
+
+    fn reparse(&mut self) {
+        self.parsed = Self::parse_fields(
+            &self.filename,
+            self.vendor,
+            self.existing.as_ref(),
+            self.field_rule.as_ref(),
+        );
+        self.fill_parsed_names();
+    }
+
+    fn fill_parsed_names(&mut self) {
+        match &self.parsed {
+            Ok(fields) => {
+                self.template_name = fields.template_name.clone();
+                self.primer_name = fields.primer_name.clone();
+            }
+            Err(_) => {
+                self.template_name = String::new();
+                self.primer_name = String::new();
+            }
+        }
+    }
+
+    /// The outcome of parsing the original filename with the current vendor
+    pub fn parse_result(&self) -> Result<&ParsedFields, &ParseError> {
+        self.parsed.as_ref()
+    }
+
+    pub fn parse_error(&self) -> Option<&ParseError> {
+        self.parsed.as_ref().err()
+    }
+
+    /// Whether the file name already follows the standardized scheme
+    pub fn is_standardized(&self) -> bool {
+        self.existing.is_some()
+    }
+
+    /// Rewrite an already standardized name instead of skipping it
+    ///
+    /// Only the date and the casing of the fields change; the template and
+    /// primer are taken from the existing name rather than the vendor scheme.
+    pub fn set_renormalize(&mut self, renormalize: bool) {
+        self.renormalize = renormalize;
+    }
+
+    pub fn get_renormalize(&self) -> bool {
+        self.renormalize
+    }
+
+    /// Whether renaming leaves this file alone because it is already standardized
+    pub fn is_skipped(&self) -> bool {
+        self.is_standardized() && !self.renormalize
+    }
+
+    /// Whether the file can be given a standardized name
+    ///
+    /// Both the template and primer names must be set, whether parsed or, for
+    /// a file whose name failed to parse, filled in by hand; a name cleared by
+    /// hand leaves the file unresolved again.
+    pub fn is_resolved(&self) -> bool {
+        !self.template_name.is_empty() && !self.primer_name.is_empty()
+    }
+
+    /// Whether renaming moves this file: it is resolved and not skipped
+    pub fn will_rename(&self) -> bool {
+        self.is_resolved() && !self.is_skipped()
     }
 
     pub fn get_full_path(&self) -> String {
@@ -70,24 +365,16 @@ impl SangerFilename {
     }
 
     pub fn get_file_stem(&self) -> String {
-        std::path::Path::new(&self.get_full_path())
-            .file_stem()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string()
+        file_stem_of(&self.filename).to_string()
     }
 
     /// Get the filename with extension but without the full path
     pub fn show_file_name(&self) -> String {
-        std::path::Path::new(&self.get_full_path())
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string()
+        file_name_of(&self.filename).to_string()
     }
 
     pub fn get_extension_name(&self) -> String {
-        std::path::Path::new(&self.get_full_path())
+        std::path::Path::new(file_name_of(&self.filename))
             .extension()
             .unwrap_or_default()
             .to_string_lossy()
@@ -95,27 +382,11 @@ impl SangerFilename {
     }
 
     pub fn get_template_name(&self) -> String {
-        if !self.template_name.is_empty() {
-            return self.template_name.clone();
-        }
-
-        match self.vendor {
-            Vendor::Sangon => self.extract_sangon_template_name(),
-            Vendor::Ruibio => self.extract_ruibio_template_name(),
-            Vendor::Genewiz => self.extract_genewiz_template_name(),
-        }
+        self.template_name.clone()
     }
 
     pub fn get_primer_name(&self) -> String {
-        if !self.primer_name.is_empty() {
-            return self.primer_name.clone();
-        }
-
-        match self.vendor {
-            Vendor::Sangon => self.extract_sangon_primer_name(),
-            Vendor::Ruibio => self.extract_ruibio_primer_name(),
-            Vendor::Genewiz => self.extract_genewiz_primer_name(),
-        }
+        self.primer_name.clone()
     }
 
     pub fn set_primer_name(&mut self, primer_name: &str) -> anyhow::Result<()> {
@@ -133,14 +404,56 @@ impl SangerFilename {
         Ok(())
     }
 
-    pub fn get_vendor_id(&self) -> String {
-        match self.vendor {
-            Vendor::Sangon => self.extract_sangon_vendor_id(),
-            Vendor::Ruibio => self.extract_ruibio_vendor_id(),
-            Vendor::Genewiz => self.extract_genewiz_vendor_id(),
+    /// Write non-ASCII template and primer names (e.g. Chinese) as ASCII in the
+    /// standardized name instead of keeping them as-is
+    pub fn set_transliterate(&mut self, transliterate: bool) {
+        self.transliterate = transliterate;
+    }
+
+    pub fn get_transliterate(&self) -> bool {
+        self.transliterate
+    }
+
+    /// Rules applied to the template and primer names in the standardized name
+    pub fn set_sanitize_rules(&mut self, rules: SanitizeRules) {
+        self.sanitize_rules = rules;
+    }
+
+    pub fn get_sanitize_rules(&self) -> &SanitizeRules {
+        &self.sanitize_rules
+    }
+
+    /// Whether sanitization changed the template or primer name
+    pub fn is_sanitized(&self) -> bool {
+        let (template_name, primer_name) = self.output_fields();
+        template_name != self.sanitize_field(&template_name)
+            || primer_name != self.sanitize_field(&primer_name)
+    }
+
+    /// A template or primer name as it goes into the new name: sanitized, and
+    /// without the separators of the name template if one is set
+    fn sanitize_field(&self, field: &str) -> String {
+        let sanitized = self.sanitize_rules.sanitize_field(field);
+        match &self.name_template {
+            Some(template) => template.escape_field(&sanitized),
+            None => sanitized,
         }
     }
 
+    pub fn get_vendor_id(&self) -> String {
+        self.parsed
+            .as_ref()
+            .map(|fields| fields.vendor_id.clone())
+            .unwrap_or_default()
+    }
+
+    /// Plate well encoded in the vendor ID, e.g. `B08` in Ruibio's `34781340.B08`
+    pub fn get_well(&self) -> Option<String> {
+        let vendor_id = self.get_vendor_id();
+        let last = vendor_id.rsplit(['.', '_', '-']).next()?;
+        is_well(last).then(|| last.to_string())
+    }
+
     pub fn get_vendor_name(&self) -> String {
         self.vendor.to_string()
     }
@@ -150,150 +463,378 @@ impl SangerFilename {
     }
     pub fn set_vendor(&mut self, vendor: Vendor) {
         self.vendor = vendor;
-        // Clear cached values and re-extract based on new vendor
-        self.primer_name = String::new();
-        self.template_name = String::new();
-        let primer_name = self.get_primer_name();
-        let template_name = self.get_template_name();
-        self.primer_name = primer_name;
-        self.template_name = template_name;
-    }
-
-    pub fn move_to_standardized_name(&self) -> anyhow::Result<()> {
-        let standardized_name = self.get_standardized_name();
-        let new_path =
-            std::path::Path::new(&self.get_full_path()).with_file_name(standardized_name);
-
-        let new_path = format!(
-            "{}.{}",
-            new_path.to_string_lossy(),
-            self.get_extension_name()
-        );
+        // Re-parse the original filename and drop any names set by hand, and
+        // the field rule, which was picked for the previous vendor's names
+        self.field_rule = None;
+        self.reparse();
+    }
 
-        // Rename the file on disk
-        std::fs::rename(self.get_full_path(), new_path)?;
-        Ok(())
+    /// Move renamed files into `dir` instead of leaving them where they are
+    pub fn set_output_dir(&mut self, dir: Option<std::path::PathBuf>) {
+        self.output_dir = dir;
     }
 
-    pub fn get_standardized_name(&self) -> String {
-        // if date is None, use today
-        let current_time = time::OffsetDateTime::now_local().unwrap();
-        let date = self.date.unwrap_or_else(|| {
-            time::Date::from_calendar_date(
-                current_time.year(),
-                current_time.month(),
-                current_time.day(),
-            )
-            .expect("Failed to get current date")
+    pub fn get_output_dir(&self) -> Option<&std::path::Path> {
+        self.output_dir.as_deref()
+    }
+
+    /// Write the new name with `template` instead of as `date.template.primer`
+    ///
+    /// Names already written with the template count as standardized.
+    pub fn set_name_template(&mut self, template: Option<NameTemplate>) {
+        self.existing = StandardizedName::parse(&self.filename).ok().or_else(|| {
+            template
+                .as_ref()
+                .and_then(|template| template.parse_name(&self.filename))
         });
-        let template_name = self.get_template_name();
-        let primer_name = self.get_primer_name();
-        // date of 2025 12m 06d to 251206
-        let date_str = format!(
-            "{:02}{:02}{:02}",
-            date.year() % 100, // last two digits of the year
-            date.month() as u8,
-            date.day()
-        );
-        format!("{}.{}.{}", date_str, template_name, primer_name)
+        self.name_template = template;
+        self.reparse();
     }
 
-    // Sangon-specific extraction methods
-    fn extract_sangon_template_name(&self) -> String {
-        // Extract template name from pattern like "0001_31225060307072_(TXPCR)_[SP1]"
-        if let Some(start) = self.filename.find('(') {
-            if let Some(end) = self.filename.find(')') {
-                if end > start {
-                    return self.filename[start + 1..end].to_string();
-                }
-            }
+    pub fn get_name_template(&self) -> Option<&NameTemplate> {
+        self.name_template.as_ref()
+    }
+
+    /// The new name without extension, as laid out by the name template if one is set
+    pub fn get_new_name(&self) -> String {
+        let standardized = self.get_standardized();
+        match &self.name_template {
+            Some(template) => template.render(&TemplateValues {
+                name: &standardized,
+                vendor: &self.get_vendor_name(),
+                vendor_id: &self.get_vendor_id(),
+                well: self.get_well().as_deref(),
+            }),
+            None => standardized.to_string(),
         }
-        String::new()
     }
 
-    fn extract_sangon_primer_name(&self) -> String {
-        let filestem = self.get_file_stem();
-        // Extract primer name from pattern like "0001_31225060307072_(TXPCR)_[SP1]"
-        if let Some(start) = filestem.find('[') {
-            if let Some(end) = filestem.find(']') {
-                if end > start {
-                    return filestem[start + 1..end].to_string();
-                }
-            }
+    /// The path the file is renamed to: the new name in the output directory,
+    /// or else in the same directory
+    pub fn get_standardized_path(&self) -> std::path::PathBuf {
+        let file_name = format!("{}.{}", self.get_new_name(), self.get_extension_name());
+        match &self.output_dir {
+            Some(dir) => dir.join(file_name),
+            None => std::path::Path::new(&self.get_full_path()).with_file_name(file_name),
         }
-        String::new()
     }
 
-    fn extract_sangon_vendor_id(&self) -> String {
-        // Extract vendor ID from pattern like "0001_31225060307072_(TXPCR)_[SP1]"
-        let filestem = self.get_file_stem();
-        let parts: Vec<&str> = filestem.split('_').collect();
-        if parts.len() >= 2 {
-            return parts[1].to_string();
+    /// Rename the file on disk, returning what was done for the catalog
+    ///
+    /// Skipped files are left untouched and return `None`.
+    pub fn move_to_standardized_name(&self) -> anyhow::Result<Option<RenameRecord>> {
+        if self.is_skipped() {
+            return Ok(None);
+        }
+        if !self.is_resolved() {
+            return Err(match self.parse_error() {
+                Some(err) => anyhow::anyhow!(err.clone()),
+                None => anyhow::anyhow!(
+                    "Cannot rename {}: the template or primer name is empty",
+                    self.show_file_name()
+                ),
+            });
         }
-        String::new()
-    } // Ruibio-specific extraction methods
-    fn extract_ruibio_template_name(&self) -> String {
-        // Extract template name from pattern like "K528-1.C1.34781340.B08"
-        // Template is everything before the first dot
-        let filestem = self.get_file_stem();
-        if let Some(first_dot) = filestem.find('.') {
-            return filestem[..first_dot].to_string();
+        let source = std::path::PathBuf::from(self.get_full_path());
+        let new_path = self.get_standardized_path();
+        let issues = sanitize::check_renames(&[(source.clone(), new_path.clone())]);
+        if let Some(issue) = issues[0].first() {
+            return Err(anyhow::anyhow!(
+                "Cannot rename {} to {}: {}",
+                self.show_file_name(),
+                new_path.display(),
+                issue
+            ));
         }
-        String::new()
+        let sha256 = catalog::file_sha256(&source)?;
+
+        // Rename the file on disk
+        if let Some(dir) = &self.output_dir {
+            std::fs::create_dir_all(dir)?;
+        }
+        move_file(&source, &new_path)?;
+        let standardized = self.get_standardized();
+        Ok(Some(RenameRecord {
+            original_name: self.show_file_name(),
+            // Absolute, so catalog entries stay valid from any working directory
+            new_path: std::fs::canonicalize(&new_path).unwrap_or(new_path),
+            vendor: self.get_vendor_name(),
+            vendor_id: self.get_vendor_id(),
+            well: self.get_well(),
+            date: standardized.date.to_string(),
+            template: standardized.template_name,
+            primer: standardized.primer_name,
+            sha256,
+        }))
     }
 
-    fn extract_ruibio_primer_name(&self) -> String {
-        let filestem = self.get_file_stem();
-        // Extract primer name from pattern like "K528-1.C1.34781340.B08"
-        // Primer is between first and second dot
-        let parts: Vec<&str> = filestem.split('.').collect();
-        if parts.len() >= 2 {
-            return parts[1].to_string();
+    /// Template and primer names as they go into the standardized name, before sanitization
+    fn output_fields(&self) -> (String, String) {
+        let mut template_name = self.get_template_name();
+        let mut primer_name = self.get_primer_name();
+        if self.transliterate {
+            template_name = transliterate(&template_name);
+            primer_name = transliterate(&primer_name);
         }
-        String::new()
+        (template_name, primer_name)
     }
 
-    fn extract_ruibio_vendor_id(&self) -> String {
-        let filestem = self.get_file_stem();
-        // Extract vendor ID from pattern like "K528-1.C1.34781340.B08"
-        // Vendor ID is the last two parts joined by dot
-        let parts: Vec<&str> = filestem.split('.').collect();
-        if parts.len() >= 3 {
-            return format!("{}.{}", parts[parts.len() - 2], parts[parts.len() - 1]);
+    /// The standardized name as a structured record, without extension
+    pub fn get_standardized(&self) -> StandardizedName {
+        // if date is None, keep the date of an already standardized name or use today
+        let date = self
+            .date
+            .or_else(|| self.existing.as_ref().map(|existing| existing.date))
+            .unwrap_or_else(|| time::OffsetDateTime::now_local().unwrap().date());
+        let (template_name, primer_name) = self.output_fields();
+        StandardizedName {
+            date,
+            template_name: self.sanitize_field(&template_name),
+            primer_name: self.sanitize_field(&primer_name),
+            extension: None,
         }
-        String::new()
-    } // Genewiz-specific extraction methods
-    fn extract_genewiz_template_name(&self) -> String {
-        let filestem = self.get_file_stem();
-        // Extract template name from pattern like "BETA-ACTIN_T7_F05" or "TL1_T25_A01"
-        // Template is everything before the first underscore
-        if let Some(underscore_pos) = filestem.find('_') {
-            return filestem[..underscore_pos].to_string();
+    }
+
+    pub fn get_standardized_name(&self) -> String {
+        self.get_standardized().to_string()
+    }
+}
+
+/// Move a file, copying it when the target is on another device
+///
+/// The copy never replaces an existing file, and is removed again if the
+/// source cannot be, so the file is never left in both places.
+pub fn move_file(source: &std::path::Path, target: &std::path::Path) -> std::io::Result<()> {
+    match std::fs::rename(source, target) {
+        Err(err) if err.kind() == std::io::ErrorKind::CrossesDevices => {
+            let mut copy = std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(target)?;
+            let moved = std::fs::File::open(source)
+                .and_then(|mut file| std::io::copy(&mut file, &mut copy))
+                .and_then(|_| copy.sync_all())
+                .and_then(|()| {
+                    std::fs::set_permissions(target, std::fs::metadata(source)?.permissions())
+                })
+                .and_then(|()| std::fs::remove_file(source));
+            if moved.is_err() {
+                let _ = std::fs::remove_file(target);
+            }
+            moved
         }
-        String::new()
+        result => result,
+    }
+}
+
+/// The final path component, accepting both `/` and `\` as separators so
+/// Windows paths are handled the same on every platform
+fn file_name_of(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+fn file_stem_of(path: &str) -> &str {
+    let file_name = file_name_of(path);
+    match file_name.rfind('.') {
+        Some(dot) if dot > 0 => &file_name[..dot],
+        _ => file_name,
     }
+}
 
-    fn extract_genewiz_primer_name(&self) -> String {
-        let filestem = self.get_file_stem();
-        // Extract primer name from pattern like "BETA-ACTIN_T7_F05" or "TL1_T25_A01"
-        // Primer is between the first and second underscore
-        let parts: Vec<&str> = filestem.split('_').collect();
-        if parts.len() >= 2 {
-            return parts[1].to_string();
+/// Transliterate non-ASCII characters, e.g. "质粒1" becomes "ZhiLi1"
+///
+/// Each transliterated syllable is capitalized and joined without spaces so
+/// the result stays a single field; ASCII text is left untouched.
+fn transliterate(field: &str) -> String {
+    let mut result = String::new();
+    for c in field.chars() {
+        if c.is_ascii() {
+            result.push(c);
+            continue;
         }
-        String::new()
-    }
-    fn extract_genewiz_vendor_id(&self) -> String {
-        let filestem = self.get_file_stem();
-        // Extract vendor ID from pattern like "BETA-ACTIN_T7_F05" or "TL1_T25_A01"
-        // Vendor ID is everything after the second underscore
-        let parts: Vec<&str> = filestem.split('_').collect();
-        if parts.len() >= 3 {
-            return parts[2].to_string();
+        let Some(ascii) = deunicode::deunicode_char(c) else {
+            result.push('_');
+            continue;
+        };
+        for word in ascii.split_whitespace() {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                result.push(first.to_ascii_uppercase());
+                result.push_str(chars.as_str());
+            }
         }
-        String::new()
     }
+    result
+}
+
+/// Whether a token looks like a plate well: a row letter A-P and a column 1-24
+fn is_well(token: &str) -> bool {
+    Well::parse(token).is_some()
+}
+
+/// Keep only non-empty extracted fields
+fn non_empty(field: &str) -> Option<String> {
+    (!field.is_empty()).then(|| field.to_string())
+}
+
+// Sangon-specific extraction functions
+fn extract_sangon_template_name(file_stem: &str) -> Option<String> {
+    // Extract template name from pattern like "0001_31225060307072_(TXPCR)_[SP1]"
+    let start = file_stem.find('(')?;
+    let end = file_stem.rfind(')')?;
+    (end > start)
+        .then(|| &file_stem[start + 1..end])
+        .and_then(non_empty)
+}
+
+fn extract_sangon_primer_name(file_stem: &str) -> Option<String> {
+    // Extract primer name from pattern like "0001_31225060307072_(TXPCR)_[SP1]"
+    let start = file_stem.rfind('[')?;
+    let end = file_stem.rfind(']')?;
+    (end > start)
+        .then(|| &file_stem[start + 1..end])
+        .and_then(non_empty)
+}
+
+fn extract_sangon_vendor_id(file_stem: &str) -> Option<String> {
+    // Extract vendor ID from pattern like "0001_31225060307072_(TXPCR)_[SP1]"
+    let id = file_stem.split('_').nth(1)?;
+    id.chars()
+        .all(|c| c.is_ascii_digit())
+        .then_some(id)
+        .and_then(non_empty)
+}
+
+// Ruibio-specific extraction functions
+fn extract_ruibio_template_name(file_stem: &str) -> Option<String> {
+    // Extract template name from pattern like "K528-1.C1.34781340.B08"
+    // Template is everything before the first dot
+    let (template, _) = file_stem.split_once('.')?;
+    non_empty(template)
+}
+
+fn extract_ruibio_primer_name(file_stem: &str) -> Option<String> {
+    // Extract primer name from pattern like "K528-1.C1.34781340.B08"
+    // Primer is between first and second dot
+    file_stem.split('.').nth(1).and_then(non_empty)
+}
+
+fn extract_ruibio_vendor_id(file_stem: &str) -> Option<String> {
+    // Extract vendor ID from pattern like "K528-1.C1.34781340.B08"
+    // Vendor ID is the last two parts joined by dot
+    let parts: Vec<&str> = file_stem.split('.').collect();
+    if parts.len() < 4 {
+        return None;
+    }
+    let (order, well) = (parts[parts.len() - 2], parts[parts.len() - 1]);
+    if order.is_empty() || well.is_empty() {
+        return None;
+    }
+    Some(format!("{}.{}", order, well))
+}
+
+// Genewiz-specific extraction functions
+fn extract_genewiz_template_name(file_stem: &str) -> Option<String> {
+    // Extract template name from pattern like "BETA-ACTIN_T7_F05" or "TL1_T25_A01"
+    // Template is everything before the first underscore
+    let (template, _) = file_stem.split_once('_')?;
+    non_empty(template)
+}
+
+fn extract_genewiz_primer_name(file_stem: &str) -> Option<String> {
+    // Extract primer name from pattern like "BETA-ACTIN_T7_F05" or "TL1_T25_A01"
+    // Primer is between the first and second underscore
+    file_stem.split('_').nth(1).and_then(non_empty)
+}
+
+fn extract_genewiz_vendor_id(file_stem: &str) -> Option<String> {
+    // Extract vendor ID from pattern like "BETA-ACTIN_T7_F05" or "TL1_T25_A01"
+    // Vendor ID is everything after the second underscore
+    file_stem.split('_').nth(2).and_then(non_empty)
+}
+
+// Tsingke-specific extraction functions
+fn extract_tsingke_template_name(file_stem: &str) -> Option<String> {
+    // Extract template name from pattern like "TSK2506010012-A01_质粒1_T7"
+    // Template is everything between the first and the last underscore
+    let (_, rest) = file_stem.split_once('_')?;
+    let (template, _) = rest.rsplit_once('_')?;
+    non_empty(template)
+}
+
+fn extract_tsingke_primer_name(file_stem: &str) -> Option<String> {
+    // Extract primer name from pattern like "TSK2506010012-A01_质粒1_T7"
+    // Primer is everything after the last underscore
+    let (_, rest) = file_stem.split_once('_')?;
+    let (_, primer) = rest.rsplit_once('_')?;
+    non_empty(primer)
+}
+
+fn extract_tsingke_vendor_id(file_stem: &str) -> Option<String> {
+    // Extract vendor ID from pattern like "TSK2506010012-A01_质粒1_T7"
+    // Vendor ID is the order number and well before the first underscore
+    let (vendor_id, _) = file_stem.split_once('_')?;
+    let (order, well) = vendor_id.split_once('-')?;
+    (order.is_ascii() && !order.is_empty() && !well.is_empty()).then(|| vendor_id.to_string())
+}
+
+// Eurofins-specific extraction functions
+fn extract_eurofins_template_name(file_stem: &str) -> Option<String> {
+    // Extract template name from pattern like "AE12345601_K528-1_T7"
+    // Template is everything between the barcode and the last underscore
+    extract_eurofins_vendor_id(file_stem)?;
+    let (_, rest) = file_stem.split_once('_')?;
+    let (template, _) = rest.rsplit_once('_')?;
+    non_empty(template)
+}
+
+fn extract_eurofins_primer_name(file_stem: &str) -> Option<String> {
+    // Extract primer name from pattern like "AE12345601_K528-1_T7"
+    // Primer is everything after the last underscore
+    extract_eurofins_vendor_id(file_stem)?;
+    let (_, rest) = file_stem.split_once('_')?;
+    let (_, primer) = rest.rsplit_once('_')?;
+    non_empty(primer)
+}
+
+fn extract_eurofins_vendor_id(file_stem: &str) -> Option<String> {
+    // Extract vendor ID from pattern like "AE12345601_K528-1_T7"
+    // Vendor ID is the barcode before the first underscore: two letters and at least six digits
+    let (barcode, _) = file_stem.split_once('_')?;
+    let (letters, digits) = barcode.split_at_checked(2)?;
+    let is_barcode = letters.chars().all(|c| c.is_ascii_uppercase())
+        && digits.len() >= 6
+        && digits.chars().all(|c| c.is_ascii_digit());
+    is_barcode.then(|| barcode.to_string())
+}
+
+// Macrogen-specific extraction functions
+fn extract_macrogen_template_name(file_stem: &str) -> Option<String> {
+    // Extract template name from pattern like "2506015432_A01_K528-1_T7"
+    // Template is everything between the well and the last underscore
+    extract_macrogen_vendor_id(file_stem)?;
+    let rest = file_stem.splitn(3, '_').nth(2)?;
+    let (template, _) = rest.rsplit_once('_')?;
+    non_empty(template)
+}
+
+fn extract_macrogen_primer_name(file_stem: &str) -> Option<String> {
+    // Extract primer name from pattern like "2506015432_A01_K528-1_T7"
+    // Primer is everything after the last underscore
+    extract_macrogen_vendor_id(file_stem)?;
+    let rest = file_stem.splitn(3, '_').nth(2)?;
+    let (_, primer) = rest.rsplit_once('_')?;
+    non_empty(primer)
+}
+
+fn extract_macrogen_vendor_id(file_stem: &str) -> Option<String> {
+    // Extract vendor ID from pattern like "2506015432_A01_K528-1_T7"
+    // Vendor ID is the numeric order number and the well joined by underscore
+    let mut parts = file_stem.splitn(3, '_');
+    let order = parts.next()?;
+    let well = parts.next()?;
+    let is_order = !order.is_empty() && order.chars().all(|c| c.is_ascii_digit());
+    (is_order && !well.is_empty()).then(|| format!("{}_{}", order, well))
 }
 
 // Implement From traits for backward compatibility
@@ -312,6 +853,26 @@ impl From<(&str, Vendor)> for SangerFilename {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sanitize::FieldCase;
+
+    #[test]
+    fn test_detect_vendor() {
+        for vendor in [
+            Vendor::Sangon,
+            Vendor::Ruibio,
+            Vendor::Genewiz,
+            Vendor::Tsingke,
+            Vendor::Eurofins,
+            Vendor::Macrogen,
+        ] {
+            let dir = format!("fixtures/{}", vendor.to_string().to_lowercase());
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let name = entry.unwrap().file_name().to_string_lossy().to_string();
+                assert_eq!(Vendor::detect(&name), Some(vendor), "{}", name);
+            }
+        }
+        assert_eq!(Vendor::detect("notes.txt"), None);
+    }
 
     #[test]
     fn test_sangon_extraction() {
@@ -393,6 +954,198 @@ mod tests {
         assert_eq!(genewiz_sanger_fn.get_template_name(), template_name);
         assert_eq!(genewiz_sanger_fn.get_primer_name(), primer_name);
     }
+    #[test]
+    fn test_tsingke_extraction() {
+        let filename = "TSK2506010012-A01_质粒1_T7.ab1";
+        let tsingke_sanger_fn = SangerFilename::new(filename, Vendor::Tsingke);
+        assert_eq!(tsingke_sanger_fn.get_vendor_id(), "TSK2506010012-A01");
+        assert_eq!(tsingke_sanger_fn.get_template_name(), "质粒1");
+        assert_eq!(tsingke_sanger_fn.get_primer_name(), "T7");
+
+        // Templates may contain underscores
+        let filename = "TSK2506010014-C03_GFP_CTRL_SP6.ab1";
+        let tsingke_sanger_fn = SangerFilename::new(filename, Vendor::Tsingke);
+        assert_eq!(tsingke_sanger_fn.get_template_name(), "GFP_CTRL");
+        assert_eq!(tsingke_sanger_fn.get_primer_name(), "SP6");
+    }
+
+    #[test]
+    fn test_tsingke_standardized_name() {
+        let filename = "TSK2506010015-D04_敲除株-3_通用引物F.ab1";
+        let mut tsingke_sanger_fn = SangerFilename::new(filename, Vendor::Tsingke);
+        let date = time::Date::from_calendar_date(2025, time::Month::June, 1)
+            .expect("Failed to create date");
+        tsingke_sanger_fn.set_date(date).unwrap();
+        assert_eq!(
+            tsingke_sanger_fn.get_standardized_name(),
+            "250601.敲除株-3.通用引物F"
+        );
+        tsingke_sanger_fn.set_transliterate(true);
+        assert_eq!(
+            tsingke_sanger_fn.get_standardized_name(),
+            "250601.QiaoChuZhu-3.TongYongYinWuF"
+        );
+    }
+
+    #[test]
+    fn test_eurofins_extraction() {
+        let filename = "AE12345601_K528-1_T7.ab1";
+        let eurofins_sanger_fn = SangerFilename::new(filename, Vendor::Eurofins);
+        assert_eq!(eurofins_sanger_fn.get_vendor_id(), "AE12345601");
+        assert_eq!(eurofins_sanger_fn.get_template_name(), "K528-1");
+        assert_eq!(eurofins_sanger_fn.get_primer_name(), "T7");
+
+        let filename = "AE12345603_PROT_X_SP6.ab1";
+        let eurofins_sanger_fn = SangerFilename::new(filename, Vendor::Eurofins);
+        assert_eq!(eurofins_sanger_fn.get_template_name(), "PROT_X");
+        assert_eq!(eurofins_sanger_fn.get_primer_name(), "SP6");
+
+        // Without a barcode prefix the name is not a Eurofins name
+        let err = SangerFilename::parse("TL1_T25_A01.ab1", Vendor::Eurofins).unwrap_err();
+        assert_eq!(err.field, NameField::VendorId);
+        let err = SangerFilename::parse("AE12345601_K528-1.ab1", Vendor::Eurofins).unwrap_err();
+        assert_eq!(err.field, NameField::Template);
+    }
+
+    #[test]
+    fn test_macrogen_extraction() {
+        let filename = "2506015432_A01_K528-1_T7.ab1";
+        let macrogen_sanger_fn = SangerFilename::new(filename, Vendor::Macrogen);
+        assert_eq!(macrogen_sanger_fn.get_vendor_id(), "2506015432_A01");
+        assert_eq!(macrogen_sanger_fn.get_template_name(), "K528-1");
+        assert_eq!(macrogen_sanger_fn.get_primer_name(), "T7");
+
+        let filename = "2506015432_C03_PROT_X_SP6.ab1";
+        let macrogen_sanger_fn = SangerFilename::new(filename, Vendor::Macrogen);
+        assert_eq!(macrogen_sanger_fn.get_template_name(), "PROT_X");
+        assert_eq!(macrogen_sanger_fn.get_primer_name(), "SP6");
+
+        let err = SangerFilename::parse("TL1_T25_A01.ab1", Vendor::Macrogen).unwrap_err();
+        assert_eq!(err.field, NameField::VendorId);
+    }
+
+    #[test]
+    fn test_macrogen_standardized_name() {
+        let filename = "2506015432_F06_k1-2_C1-R.ab1";
+        let mut macrogen_sanger_fn = SangerFilename::new(filename, Vendor::Macrogen);
+        let date = time::Date::from_calendar_date(2025, time::Month::June, 1)
+            .expect("Failed to create date");
+        macrogen_sanger_fn.set_date(date).unwrap();
+        assert_eq!(
+            macrogen_sanger_fn.get_standardized_name(),
+            "250601.k1-2.C1-R"
+        );
+    }
+
+    #[test]
+    fn test_standardized_name_is_sanitized() {
+        let mut sanger_fn = SangerFilename::new("K528-1.C1.34781340.B08.ab1", Vendor::Ruibio);
+        let date = time::Date::from_calendar_date(2025, time::Month::June, 1)
+            .expect("Failed to create date");
+        sanger_fn.set_date(date).unwrap();
+        assert!(!sanger_fn.is_sanitized());
+        sanger_fn.set_template_name("K528/3.v2").unwrap();
+        sanger_fn.set_primer_name("T7:fwd").unwrap();
+        assert!(sanger_fn.is_sanitized());
+        assert_eq!(sanger_fn.get_standardized_name(), "250601.K528_3-v2.T7_fwd");
+
+        sanger_fn.set_sanitize_rules(SanitizeRules::default().with_replacement('.', "_"));
+        assert_eq!(sanger_fn.get_standardized_name(), "250601.K528_3_v2.T7_fwd");
+    }
+
+    #[test]
+    fn test_standardized_name_round_trip() {
+        let date = time::Date::from_calendar_date(2025, time::Month::December, 6)
+            .expect("Failed to create date");
+        for (filename, vendor) in [
+            ("0001_31225060307072_(TXPCR)_[SP1].ab1", Vendor::Sangon),
+            ("K528-3.250604-mbp-s3.34810430.D07.ab1", Vendor::Ruibio),
+            ("k1-2_C1-R_G04.ab1", Vendor::Genewiz),
+            ("TSK2506010013-B02_pUC19-突变体_M13F.ab1", Vendor::Tsingke),
+            ("2506015432_C03_PROT_X_SP6.ab1", Vendor::Macrogen),
+        ] {
+            let mut sanger_fn = SangerFilename::new(filename, vendor);
+            sanger_fn.set_date(date).unwrap();
+            let standardized_name = sanger_fn.get_standardized_name();
+            let parsed = StandardizedName::parse(&standardized_name).unwrap();
+            assert_eq!(parsed, sanger_fn.get_standardized());
+            assert_eq!(parsed.to_string(), standardized_name);
+            assert_eq!(parsed.date, date);
+            assert_eq!(parsed.template_name, sanger_fn.get_template_name());
+
+            let path = sanger_fn.get_standardized_path();
+            let parsed = StandardizedName::parse(path.to_str().unwrap()).unwrap();
+            assert_eq!(parsed.extension.as_deref(), Some("ab1"));
+        }
+    }
+
+    #[test]
+    fn test_already_standardized_file() {
+        let mut sanger_fn = SangerFilename::new("/data/250601.gfp-ctrl.m13f.ab1", Vendor::Ruibio);
+        assert!(sanger_fn.is_standardized());
+        assert!(sanger_fn.is_skipped());
+        assert!(sanger_fn.parse_error().is_none());
+        assert_eq!(sanger_fn.get_template_name(), "gfp-ctrl");
+        assert_eq!(sanger_fn.get_primer_name(), "m13f");
+        // Switching vendor does not re-parse the fields
+        sanger_fn.set_vendor(Vendor::Genewiz);
+        assert_eq!(sanger_fn.get_template_name(), "gfp-ctrl");
+
+        sanger_fn.set_renormalize(true);
+        assert!(!sanger_fn.is_skipped());
+        assert_eq!(sanger_fn.get_standardized_name(), "250601.gfp-ctrl.m13f");
+        sanger_fn.set_sanitize_rules(SanitizeRules {
+            case: FieldCase::Upper,
+            ..SanitizeRules::default()
+        });
+        let date = time::Date::from_calendar_date(2025, time::Month::June, 3)
+            .expect("Failed to create date");
+        sanger_fn.set_date(date).unwrap();
+        assert_eq!(sanger_fn.get_standardized_name(), "250603.GFP-CTRL.M13F");
+
+        let fresh = SangerFilename::new("K528-1.C1.34781340.B08.ab1", Vendor::Ruibio);
+        assert!(!fresh.is_standardized());
+    }
+
+    #[test]
+    fn test_get_well() {
+        for (filename, vendor, well) in [
+            ("K528-1.C1.34781340.B08.ab1", Vendor::Ruibio, Some("B08")),
+            ("TL1_T25_A01.ab1", Vendor::Genewiz, Some("A01")),
+            ("BETA-ACTIN_T9.ab1", Vendor::Genewiz, None),
+            (
+                "TSK2506010012-A01_质粒1_T7.ab1",
+                Vendor::Tsingke,
+                Some("A01"),
+            ),
+            (
+                "2506015432_F06_k1-2_C1-R.ab1",
+                Vendor::Macrogen,
+                Some("F06"),
+            ),
+            (
+                "0001_31225060307072_(TXPCR)_[SP1].ab1",
+                Vendor::Sangon,
+                None,
+            ),
+            ("AE12345601_K528-1_T7.ab1", Vendor::Eurofins, None),
+        ] {
+            let sanger_fn = SangerFilename::new(filename, vendor);
+            assert_eq!(sanger_fn.get_well().as_deref(), well, "{}", filename);
+        }
+    }
+
+    #[test]
+    fn test_try_new() {
+        let sanger_fn = SangerFilename::try_new("K528-1.C1.34781340.B08.ab1", Vendor::Ruibio);
+        assert_eq!(sanger_fn.unwrap().get_template_name(), "K528-1");
+        assert!(SangerFilename::try_new("250601.K528-1.C1.ab1", Vendor::Sangon).is_ok());
+        let err =
+            SangerFilename::try_new("K528-1.C1.34781340.B08.ab1", Vendor::Sangon).unwrap_err();
+        assert_eq!(err.vendor, Vendor::Sangon);
+        assert_eq!(err.filename, "K528-1.C1.34781340.B08.ab1");
+    }
+
     #[test]
     fn test_vendor_switching() {
         let filename = "0001_31225060307072_(TXPCR)_[SP1].ab1";
@@ -409,6 +1162,81 @@ mod tests {
         // The filename doesn't match Ruibio pattern, so extraction will be empty
         assert_eq!(sanger_fn.get_template_name(), "");
         assert_eq!(sanger_fn.get_primer_name(), "");
+        assert_eq!(
+            sanger_fn.parse_error().map(|e| e.field),
+            Some(NameField::Template)
+        );
+        assert!(!sanger_fn.is_resolved());
+    }
+
+    #[test]
+    fn test_parse_result() {
+        let parsed = SangerFilename::parse("K528-1.C1.34781340.B08.ab1", Vendor::Ruibio).unwrap();
+        assert_eq!(
+            parsed,
+            ParsedFields {
+                template_name: "K528-1".to_string(),
+                primer_name: "C1".to_string(),
+                vendor_id: "34781340.B08".to_string(),
+            }
+        );
+
+        let err =
+            SangerFilename::parse("0001_31225060307072_(TXPCR).ab1", Vendor::Sangon).unwrap_err();
+        assert_eq!(err.field, NameField::Primer);
+        assert_eq!(err.vendor, Vendor::Sangon);
+        assert_eq!(err.filename, "0001_31225060307072_(TXPCR).ab1");
+
+        // Genewiz wells are optional
+        let parsed = SangerFilename::parse("BETA-ACTIN_T9.ab1", Vendor::Genewiz).unwrap();
+        assert_eq!(parsed.vendor_id, "");
+    }
+
+    #[test]
+    fn test_field_rule_overrides_vendor_scheme() {
+        let rule = FieldRule::from_example("MY_PLASMID_T7_A01", '_', 0..=1, 2..=2).unwrap();
+        let mut sanger_fn = SangerFilename::new("MY_PLASMID_T7_A01.ab1", Vendor::Genewiz);
+        assert_eq!(sanger_fn.get_template_name(), "MY");
+        sanger_fn.set_field_rule(Some(rule));
+        assert_eq!(sanger_fn.get_template_name(), "MY_PLASMID");
+        assert_eq!(sanger_fn.get_primer_name(), "T7");
+        assert_eq!(sanger_fn.get_vendor_id(), "A01");
+        sanger_fn.set_field_rule(None);
+        assert_eq!(sanger_fn.get_template_name(), "MY");
+
+        // A name without a well falls back to the built-in scheme
+        let picked = FieldRule::from_example("BETA-ACTIN_T7_F05", '_', 0..=0, 1..=1).unwrap();
+        let mut well_less = SangerFilename::new("BETA-ACTIN_T9.ab1", Vendor::Genewiz);
+        well_less.set_field_rule(Some(picked));
+        assert_eq!(well_less.get_template_name(), "BETA-ACTIN");
+        assert_eq!(well_less.get_primer_name(), "T9");
+
+        // Switching vendors drops the rule picked for the previous one
+        sanger_fn.set_field_rule(Some(rule));
+        sanger_fn.set_vendor(Vendor::Tsingke);
+        sanger_fn.set_vendor(Vendor::Genewiz);
+        assert!(sanger_fn.get_field_rule().is_none());
+        assert_eq!(sanger_fn.get_template_name(), "MY");
+    }
+
+    #[test]
+    fn test_unparsed_file_resolved_by_hand() {
+        let mut sanger_fn = SangerFilename::new("K528-1.C1.34781340.B08.ab1", Vendor::Sangon);
+        assert!(!sanger_fn.is_resolved());
+        sanger_fn.set_template_name("K528-1").unwrap();
+        assert!(!sanger_fn.is_resolved());
+        sanger_fn.set_primer_name("C1").unwrap();
+        assert!(sanger_fn.is_resolved());
+        assert!(sanger_fn.parse_error().is_some());
+
+        // Clearing a parsed name by hand unresolves the file
+        let mut sanger_fn =
+            SangerFilename::new("0001_31225060307072_(TXPCR)_[SP1].ab1", Vendor::Sangon);
+        assert!(sanger_fn.is_resolved());
+        sanger_fn.set_primer_name("").unwrap();
+        assert!(!sanger_fn.is_resolved());
+        let err = sanger_fn.move_to_standardized_name().unwrap_err();
+        assert!(err.to_string().contains("name is empty"), "{}", err);
     }
 
     #[test]
@@ -416,6 +1244,9 @@ mod tests {
         assert_eq!(Vendor::from_str("sangon").unwrap(), Vendor::Sangon);
         assert_eq!(Vendor::from_str("RUIBIO").unwrap(), Vendor::Ruibio);
         assert_eq!(Vendor::from_str("GenEwiz").unwrap(), Vendor::Genewiz);
+        assert_eq!(Vendor::from_str("tsingke").unwrap(), Vendor::Tsingke);
+        assert_eq!(Vendor::from_str("Eurofins").unwrap(), Vendor::Eurofins);
+        assert_eq!(Vendor::from_str("macrogen").unwrap(), Vendor::Macrogen);
         assert!(Vendor::from_str("unknown").is_err());
     }
 
@@ -442,6 +1273,8 @@ mod tests {
         // create the file at system temp dir
         let temp_dir = std::env::temp_dir();
         let full_path = temp_dir.join(filename);
+        // Renaming refuses to overwrite, so clear the target left by earlier runs
+        let _ = std::fs::remove_file(temp_dir.join("250601.TXPCR.SP1.ab1"));
         std::fs::write(&full_path, b"test content").expect("Failed to create test file");
         let mut sanger_fn =
             SangerFilename::new(full_path.to_string_lossy().as_ref(), Vendor::Sangon);
@@ -449,9 +1282,14 @@ mod tests {
             .expect("Failed to create date");
         sanger_fn.set_date(date).unwrap();
         // Move to standardized name
-        sanger_fn
+        let record = sanger_fn
             .move_to_standardized_name()
-            .expect("Failed to move file");
+            .expect("Failed to move file")
+            .expect("File should not be skipped");
+        assert_eq!(record.original_name, filename);
+        assert_eq!(record.vendor_id, "31225060307072");
+        assert_eq!(record.date, "2025-06-01");
+        assert_eq!(record.template, "TXPCR");
         // Check if the file was renamed correctly
         let standardized_name = sanger_fn.get_standardized_name();
         let new_full_path = temp_dir.join(format!("{}.ab1", standardized_name));
@@ -466,4 +1304,21 @@ mod tests {
         }
         assert!(new_full_path.exists(), "Standardized file does not exist");
     }
+
+    #[test]
+    fn test_move_refuses_to_overwrite() {
+        let temp_dir = std::env::temp_dir().join("sanger_rename_no_overwrite");
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        let source = temp_dir.join("K528-1.C1.34781340.B08.ab1");
+        let existing = temp_dir.join("250601.K528-1.C1.ab1");
+        std::fs::write(&source, b"new").unwrap();
+        std::fs::write(&existing, b"old").unwrap();
+        let mut sanger_fn = SangerFilename::new(source.to_string_lossy().as_ref(), Vendor::Ruibio);
+        let date = time::Date::from_calendar_date(2025, time::Month::June, 1)
+            .expect("Failed to create date");
+        sanger_fn.set_date(date).unwrap();
+        assert!(sanger_fn.move_to_standardized_name().is_err());
+        assert_eq!(std::fs::read(&existing).unwrap(), b"old");
+        assert!(source.exists());
+    }
 }
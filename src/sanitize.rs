@@ -0,0 +1,350 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use unicode_normalization::UnicodeNormalization;
+
+/// Characters that are not allowed in file names on Windows, and `/` on every platform
+const INVALID_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Device names that Windows reserves regardless of extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Longest file name, in bytes, accepted by common file systems
+pub const MAX_FILE_NAME_BYTES: usize = 255;
+
+/// Longest full path accepted by Windows without long path support
+pub const MAX_PATH_CHARS: usize = 260;
+
+/// Casing applied to template and primer names
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FieldCase {
+    #[default]
+    AsIs,
+    Upper,
+    Lower,
+}
+
+impl FieldCase {
+    /// The next casing, for toggling through the options
+    pub fn next(self) -> Self {
+        match self {
+            FieldCase::AsIs => FieldCase::Upper,
+            FieldCase::Upper => FieldCase::Lower,
+            FieldCase::Lower => FieldCase::AsIs,
+        }
+    }
+}
+
+impl std::fmt::Display for FieldCase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldCase::AsIs => write!(f, "as-is"),
+            FieldCase::Upper => write!(f, "upper"),
+            FieldCase::Lower => write!(f, "lower"),
+        }
+    }
+}
+
+/// Rules applied to template and primer names before they go into a file name
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SanitizeRules {
+    /// Replacements applied first, e.g. `'.'` to `"-"` so fields never contain
+    /// the separator of the standardized name
+    pub replacements: Vec<(char, String)>,
+    /// Replacement for characters that are invalid in file names
+    pub invalid_replacement: String,
+    /// Apply Unicode NFC normalization, so names typed on macOS and Windows compare equal
+    pub normalize_nfc: bool,
+    pub case: FieldCase,
+}
+
+impl Default for SanitizeRules {
+    fn default() -> Self {
+        Self {
+            replacements: vec![('.', "-".to_string()), (' ', "_".to_string())],
+            invalid_replacement: "_".to_string(),
+            normalize_nfc: true,
+            case: FieldCase::AsIs,
+        }
+    }
+}
+
+impl SanitizeRules {
+    /// Add or override the replacement for `from`
+    pub fn with_replacement(mut self, from: char, to: &str) -> Self {
+        self.replacements.retain(|(c, _)| *c != from);
+        self.replacements.push((from, to.to_string()));
+        self
+    }
+
+    /// Whether `to` can replace a character without breaking the `date.template.primer` scheme
+    pub fn is_safe_replacement(to: &str) -> bool {
+        !to.chars()
+            .any(|c| c == '.' || INVALID_CHARS.contains(&c) || c.is_control())
+    }
+
+    /// Make a template or primer name safe to use as a field of a file name
+    pub fn sanitize_field(&self, field: &str) -> String {
+        let normalized: String = if self.normalize_nfc {
+            field.nfc().collect()
+        } else {
+            field.to_string()
+        };
+        let mut result = String::with_capacity(normalized.len());
+        for c in normalized.chars() {
+            if let Some((_, to)) = self.replacements.iter().find(|(from, _)| *from == c) {
+                result.push_str(to);
+            } else if INVALID_CHARS.contains(&c) || c.is_control() {
+                result.push_str(&self.invalid_replacement);
+            } else {
+                result.push(c);
+            }
+        }
+        match self.case {
+            FieldCase::AsIs => result,
+            FieldCase::Upper => result.to_uppercase(),
+            FieldCase::Lower => result.to_lowercase(),
+        }
+    }
+}
+
+/// Reasons a planned file name cannot be used safely
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum NameIssue {
+    /// The name starts with a device name reserved on Windows, e.g. `CON`
+    Reserved(String),
+    /// The file name is longer than [`MAX_FILE_NAME_BYTES`]
+    TooLong(usize),
+    /// The full path is longer than [`MAX_PATH_CHARS`]
+    PathTooLong(usize),
+    /// Windows silently strips trailing dots and spaces
+    TrailingDotOrSpace,
+    /// Another file of the batch gets the same name, ignoring case
+    Collision(String),
+    /// A different file with the same name, ignoring case, already exists
+    Exists(PathBuf),
+}
+
+impl std::fmt::Display for NameIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NameIssue::Reserved(name) => write!(f, "{} is reserved on Windows", name),
+            NameIssue::TooLong(bytes) => write!(
+                f,
+                "name is {} bytes, more than {}",
+                bytes, MAX_FILE_NAME_BYTES
+            ),
+            NameIssue::PathTooLong(chars) => write!(
+                f,
+                "path is {} characters, more than {}",
+                chars, MAX_PATH_CHARS
+            ),
+            NameIssue::TrailingDotOrSpace => write!(f, "name ends with a dot or space"),
+            NameIssue::Collision(other) => write!(f, "same name as {}", other),
+            NameIssue::Exists(path) => write!(f, "{} already exists", path.display()),
+        }
+    }
+}
+
+/// Check a single target path for names that are not portable
+pub fn portability_issues(path: &Path) -> Vec<NameIssue> {
+    let mut issues = Vec::new();
+    let file_name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let base = file_name.split('.').next().unwrap_or_default();
+    if let Some(reserved) = RESERVED_NAMES
+        .iter()
+        .find(|name| name.eq_ignore_ascii_case(base.trim_end()))
+    {
+        issues.push(NameIssue::Reserved(reserved.to_string()));
+    }
+    if file_name.len() > MAX_FILE_NAME_BYTES {
+        issues.push(NameIssue::TooLong(file_name.len()));
+    }
+    let path_chars = path.to_string_lossy().chars().count();
+    if path_chars > MAX_PATH_CHARS {
+        issues.push(NameIssue::PathTooLong(path_chars));
+    }
+    if file_name.ends_with('.') || file_name.ends_with(' ') {
+        issues.push(NameIssue::TrailingDotOrSpace);
+    }
+    issues
+}
+
+/// Case-insensitive comparison key, so `a.ab1` and `A.AB1` collide as they
+/// would on Windows and macOS
+fn collision_key(path: &Path) -> String {
+    path.to_string_lossy()
+        .nfc()
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Files found in the folders renames go into, by [`collision_key`]
+///
+/// Each folder is read once, so a listing kept for a batch spares re-reading
+/// the folders every time its renames are checked.
+#[derive(Clone, Debug, Default)]
+pub struct DirListing {
+    existing: HashMap<String, PathBuf>,
+    listed: HashSet<PathBuf>,
+}
+
+impl DirListing {
+    /// Read the folders of `renames`' targets that were not read yet
+    fn add_targets(&mut self, renames: &[(PathBuf, PathBuf)]) {
+        for (_, target) in renames {
+            let dir = target.parent().unwrap_or(Path::new("")).to_path_buf();
+            if self.listed.contains(&dir) {
+                continue;
+            }
+            let entries = std::fs::read_dir(if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                &dir
+            });
+            for entry in entries.into_iter().flatten().flatten() {
+                let path = dir.join(entry.file_name());
+                self.existing.insert(collision_key(&path), path);
+            }
+            self.listed.insert(dir);
+        }
+    }
+}
+
+/// Check a planned batch of `(source, target)` renames
+///
+/// Returns the issues for each entry: its own portability issues, targets
+/// shared with another entry, and targets that already exist on disk as a
+/// different file. A target that another entry renames away still counts as
+/// existing, since the files are moved one at a time.
+pub fn check_renames(renames: &[(PathBuf, PathBuf)]) -> Vec<Vec<NameIssue>> {
+    check_renames_with(renames, &mut DirListing::default())
+}
+
+/// [`check_renames`] against the files in `listing`, reading only folders it lacks
+pub fn check_renames_with(
+    renames: &[(PathBuf, PathBuf)],
+    listing: &mut DirListing,
+) -> Vec<Vec<NameIssue>> {
+    let mut by_target: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, (_, target)) in renames.iter().enumerate() {
+        by_target.entry(collision_key(target)).or_default().push(i);
+    }
+    listing.add_targets(renames);
+    let existing = &listing.existing;
+
+    renames
+        .iter()
+        .enumerate()
+        .map(|(i, (source, target))| {
+            let mut issues = portability_issues(target);
+            let key = collision_key(target);
+            for &other in &by_target[&key] {
+                if other != i {
+                    issues.push(NameIssue::Collision(
+                        renames[other]
+                            .0
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string(),
+                    ));
+                }
+            }
+            let renames_itself = collision_key(source) == key;
+            if let Some(path) = existing.get(&key)
+                && !renames_itself
+            {
+                issues.push(NameIssue::Exists(path.clone()));
+            }
+            issues
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_field() {
+        let rules = SanitizeRules::default();
+        assert_eq!(rules.sanitize_field("K528-1"), "K528-1");
+        assert_eq!(rules.sanitize_field("a/b:c*"), "a_b_c_");
+        assert_eq!(rules.sanitize_field("250604.mbp s3"), "250604-mbp_s3");
+        // "é" typed as "e" + combining acute accent becomes a single code point
+        assert_eq!(rules.sanitize_field("cafe\u{301}"), "caf\u{e9}");
+
+        let rules = rules.with_replacement('.', "");
+        assert_eq!(rules.sanitize_field("a.b"), "ab");
+    }
+
+    #[test]
+    fn test_portability_issues() {
+        assert!(portability_issues(Path::new("250601.K528.T7.ab1")).is_empty());
+        assert_eq!(
+            portability_issues(Path::new("dir/con.T7.ab1")),
+            vec![NameIssue::Reserved("CON".to_string())]
+        );
+        assert_eq!(
+            portability_issues(Path::new("250601.K528.")),
+            vec![NameIssue::TrailingDotOrSpace]
+        );
+        let long_name = format!("250601.{}.T7.ab1", "A".repeat(300));
+        let issues = portability_issues(Path::new(&long_name));
+        assert!(issues.contains(&NameIssue::TooLong(long_name.len())));
+    }
+
+    #[test]
+    fn test_check_renames_collisions() {
+        let renames = vec![
+            (PathBuf::from("a.ab1"), PathBuf::from("250601.gfp.T7.ab1")),
+            (PathBuf::from("b.ab1"), PathBuf::from("250601.GFP.T7.ab1")),
+            (PathBuf::from("c.ab1"), PathBuf::from("250601.GFP.T3.ab1")),
+        ];
+        let issues = check_renames(&renames);
+        assert_eq!(issues[0], vec![NameIssue::Collision("b.ab1".to_string())]);
+        assert_eq!(issues[1], vec![NameIssue::Collision("a.ab1".to_string())]);
+        assert!(issues[2].is_empty());
+    }
+
+    #[test]
+    fn test_check_renames_existing_file() {
+        let dir = std::env::temp_dir().join("sanger_rename_check_renames");
+        std::fs::create_dir_all(&dir).unwrap();
+        let existing = dir.join("250601.K528.T7.ab1");
+        std::fs::write(&existing, b"test content").unwrap();
+        let renames = vec![(dir.join("new.ab1"), dir.join("250601.k528.T7.ab1"))];
+        assert_eq!(
+            check_renames(&renames)[0],
+            vec![NameIssue::Exists(existing.clone())]
+        );
+        // Renaming a file to a different case of its own name is fine
+        let renames = vec![(existing.clone(), dir.join("250601.k528.T7.ab1"))];
+        assert!(check_renames(&renames)[0].is_empty());
+        // A target that is moved away later in the batch is still in the way
+        let renames = vec![
+            (dir.join("new.ab1"), existing.clone()),
+            (existing.clone(), dir.join("250601.K528.SP6.ab1")),
+        ];
+        assert_eq!(
+            check_renames(&renames)[0],
+            vec![NameIssue::Exists(existing.clone())]
+        );
+
+        // A kept listing does not read the folder again
+        let renames = vec![(dir.join("new.ab1"), dir.join("250601.k528.T7.ab1"))];
+        let mut listing = DirListing::default();
+        assert!(!check_renames_with(&renames, &mut listing)[0].is_empty());
+        std::fs::remove_file(&existing).unwrap();
+        assert!(!check_renames_with(&renames, &mut listing)[0].is_empty());
+        assert!(check_renames(&renames)[0].is_empty());
+    }
+}
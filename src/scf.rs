@@ -0,0 +1,196 @@
+//! SCF version 3 output for tools that do not read ABIF, such as the Staden package
+//!
+//! All values are big-endian. Samples are stored per channel in A, C, G, T
+//! order as second differences, and each base carries the quality of its call
+//! in the probability of the called base.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::abif::Trace;
+
+const MAGIC: &[u8; 4] = b".scf";
+const HEADER_SIZE: usize = 128;
+const SCF_ORDER: [u8; 4] = *b"ACGT";
+
+/// Second differences of the samples, as SCF v3 stores them
+fn delta_delta(samples: &mut [u16]) {
+    for _ in 0..2 {
+        let mut previous = 0u16;
+        for sample in samples.iter_mut() {
+            let current = *sample;
+            *sample = current.wrapping_sub(previous);
+            previous = current;
+        }
+    }
+}
+
+/// The trace as an SCF v3 file
+pub fn encode(trace: &Trace) -> Vec<u8> {
+    let samples = trace.scan_count();
+    let bases = trace.bases.len().min(trace.peak_locations.len());
+    let mut comments = String::new();
+    if let Some(name) = &trace.sample_name {
+        comments.push_str(&format!("NAME={}\n", name));
+    }
+    comments.push_str("CONV=sanger_rename\n");
+    let mut comments = comments.into_bytes();
+    comments.push(0);
+
+    let samples_offset = HEADER_SIZE;
+    let bases_offset = samples_offset + samples * 4 * 2;
+    let comments_offset = bases_offset + bases * 12;
+    let mut out = Vec::with_capacity(comments_offset + comments.len());
+    out.extend_from_slice(MAGIC);
+    for value in [
+        samples,
+        samples_offset,
+        bases,
+        0, // left clip
+        0, // right clip
+        bases_offset,
+        comments.len(),
+        comments_offset,
+    ] {
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    }
+    out.extend_from_slice(b"3.00");
+    // 16-bit samples, default code set, no private data
+    for value in [2u32, 0, 0, 0] {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    out.resize(HEADER_SIZE, 0);
+
+    for base in SCF_ORDER {
+        let mut channel: Vec<u16> = match trace.channel(base) {
+            Some(channel) => channel.iter().map(|&v| v.max(0) as u16).collect(),
+            None => Vec::new(),
+        };
+        channel.resize(samples, 0);
+        delta_delta(&mut channel);
+        for value in channel {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+
+    for &peak in &trace.peak_locations[..bases] {
+        out.extend_from_slice(&(peak as u32).to_be_bytes());
+    }
+    for channel in SCF_ORDER {
+        for (i, &base) in trace.bases[..bases].iter().enumerate() {
+            let quality = trace.qualities.get(i).copied().unwrap_or(0);
+            out.push(if base.eq_ignore_ascii_case(&channel) {
+                quality
+            } else {
+                0
+            });
+        }
+    }
+    out.extend_from_slice(&trace.bases[..bases]);
+    out.resize(out.len() + bases * 3, 0);
+
+    out.extend_from_slice(&comments);
+    out
+}
+
+/// Convert the ABIF trace at `path` to `{stem}.scf` in `dir`
+///
+/// An existing SCF file is only replaced with `overwrite`.
+pub fn convert(path: &Path, dir: &Path, overwrite: bool) -> anyhow::Result<PathBuf> {
+    let trace = Trace::from_file(path)?;
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let output = dir.join(format!("{}.scf", stem));
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .create_new(!overwrite)
+        .open(&output)
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::AlreadyExists => {
+                anyhow::anyhow!("{} already exists", output.display())
+            }
+            _ => anyhow::anyhow!("{}: {}", output.display(), err),
+        })?;
+    file.write_all(&encode(&trace))?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> usize {
+        u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+    }
+
+    /// Undo the second differences of one channel
+    fn decode_channel(bytes: &[u8], offset: usize, samples: usize) -> Vec<u16> {
+        let mut values: Vec<u16> = (0..samples)
+            .map(|i| u16::from_be_bytes([bytes[offset + i * 2], bytes[offset + i * 2 + 1]]))
+            .collect();
+        for _ in 0..2 {
+            let mut previous = 0u16;
+            for value in values.iter_mut() {
+                *value = value.wrapping_add(previous);
+                previous = *value;
+            }
+        }
+        values
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let mut trace = Trace::synthetic("ACGTTGCA", 10, 30);
+        trace.sample_name = Some("K528-1_T7".to_string());
+        let bytes = encode(&trace);
+        assert_eq!(&bytes[..4], b".scf");
+        assert_eq!(&bytes[36..40], b"3.00");
+        let samples = u32_at(&bytes, 4);
+        let bases = u32_at(&bytes, 12);
+        assert_eq!((samples, bases), (trace.scan_count(), 8));
+
+        // Channels are stored in A, C, G, T order whatever the trace's order
+        let samples_offset = u32_at(&bytes, 8);
+        for (n, base) in SCF_ORDER.iter().enumerate() {
+            let channel = decode_channel(&bytes, samples_offset + n * samples * 2, samples);
+            let expected: Vec<u16> = trace
+                .channel(*base)
+                .unwrap()
+                .iter()
+                .map(|&v| v as u16)
+                .collect();
+            assert_eq!(channel, expected);
+        }
+
+        let bases_offset = u32_at(&bytes, 24);
+        assert_eq!(
+            u32_at(&bytes, bases_offset),
+            trace.peak_locations[0] as usize
+        );
+        let probabilities = bases_offset + bases * 4;
+        // The first base is A: its quality sits in the A probabilities only
+        assert_eq!(bytes[probabilities], 30);
+        assert_eq!(bytes[probabilities + bases], 0);
+        let calls = probabilities + bases * 4;
+        assert_eq!(&bytes[calls..calls + bases], b"ACGTTGCA");
+
+        let comments_offset = u32_at(&bytes, 32);
+        let comments = &bytes[comments_offset..comments_offset + u32_at(&bytes, 28)];
+        assert_eq!(comments, b"NAME=K528-1_T7\nCONV=sanger_rename\n\0");
+        assert_eq!(bytes.len(), comments_offset + comments.len());
+    }
+
+    #[test]
+    fn test_convert_keeps_existing_file() {
+        let dir = std::env::temp_dir().join("sanger_rename_scf_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let trace = dir.join("K528-1.T7.ab1");
+        std::fs::write(&trace, Trace::synthetic("ACGT", 10, 30).to_abif_bytes()).unwrap();
+        let output = convert(&trace, &dir, false).unwrap();
+        assert_eq!(output, dir.join("K528-1.T7.scf"));
+        assert!(convert(&trace, &dir, false).is_err());
+        assert!(convert(&trace, &dir, true).is_ok());
+    }
+}
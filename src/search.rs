@@ -0,0 +1,290 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::catalog::{Catalog, RenameRecord};
+use crate::name_template::NameTemplate;
+use crate::sanger_filename::Vendor;
+use crate::standardized_name::StandardizedName;
+
+/// A template or primer filter, matched against the whole field
+#[derive(Clone, Debug)]
+pub struct FieldPattern(Regex);
+
+impl FieldPattern {
+    /// A shell-style glob (`*`, `?`), matched case-insensitively
+    pub fn glob(pattern: &str) -> anyhow::Result<Self> {
+        let mut regex = String::from("(?i)^");
+        for c in pattern.chars() {
+            match c {
+                '*' => regex.push_str(".*"),
+                '?' => regex.push('.'),
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        regex.push('$');
+        Ok(Self(Regex::new(&regex)?))
+    }
+
+    /// A regular expression, which has to match the whole field, matched
+    /// case-insensitively like [`FieldPattern::glob`]
+    pub fn regex(pattern: &str) -> anyhow::Result<Self> {
+        Ok(Self(Regex::new(&format!("(?i)^(?:{})$", pattern))?))
+    }
+
+    pub fn is_match(&self, field: &str) -> bool {
+        self.0.is_match(field)
+    }
+}
+
+/// Filters for [`search_catalog`] and [`scan_roots`]; unset filters match everything
+#[derive(Clone, Debug, Default)]
+pub struct SearchQuery {
+    pub template: Option<FieldPattern>,
+    pub primer: Option<FieldPattern>,
+    /// Earliest date, inclusive
+    pub since: Option<time::Date>,
+    /// Latest date, inclusive
+    pub until: Option<time::Date>,
+    pub vendor: Option<Vendor>,
+}
+
+impl SearchQuery {
+    fn matches(&self, hit: &SearchHit) -> bool {
+        self.template
+            .as_ref()
+            .is_none_or(|p| p.is_match(&hit.template))
+            && self.primer.as_ref().is_none_or(|p| p.is_match(&hit.primer))
+            && self.since.is_none_or(|since| hit.date >= since)
+            && self.until.is_none_or(|until| hit.date <= until)
+            && self
+                .vendor
+                .is_none_or(|vendor| hit.vendor.as_deref() == Some(&vendor.to_string()))
+    }
+}
+
+/// A renamed trace found by a search
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct SearchHit {
+    pub path: PathBuf,
+    pub date: time::Date,
+    pub template: String,
+    pub primer: String,
+    /// Known only for files recorded in the catalog
+    pub vendor: Option<String>,
+    pub vendor_id: Option<String>,
+}
+
+impl SearchHit {
+    fn from_record(record: &RenameRecord) -> Option<Self> {
+        let date = time::Date::parse(
+            &record.date,
+            time::macros::format_description!("[year]-[month]-[day]"),
+        )
+        .ok()?;
+        Some(Self {
+            path: record.new_path.clone(),
+            date,
+            template: record.template.clone(),
+            primer: record.primer.clone(),
+            vendor: Some(record.vendor.clone()),
+            vendor_id: Some(record.vendor_id.clone()),
+        })
+    }
+}
+
+/// Search the rename catalog
+pub fn search_catalog(catalog: &Catalog, query: &SearchQuery) -> anyhow::Result<Vec<SearchHit>> {
+    Ok(catalog
+        .records()?
+        .iter()
+        .filter_map(SearchHit::from_record)
+        .filter(|hit| query.matches(hit))
+        .collect())
+}
+
+/// Scan directories for files with standardized names
+///
+/// Names written with `name_template` are recognized before the default
+/// layout. Files recorded in `catalog` get their vendor and vendor ID from
+/// there, so a vendor filter only matches cataloged files.
+pub fn scan_roots(
+    roots: &[PathBuf],
+    catalog: Option<&Catalog>,
+    name_template: Option<&NameTemplate>,
+    query: &SearchQuery,
+) -> anyhow::Result<Vec<SearchHit>> {
+    let known: HashMap<PathBuf, RenameRecord> = match catalog {
+        Some(catalog) => catalog
+            .records()?
+            .into_iter()
+            .map(|record| (canonical(&record.new_path), record))
+            .collect(),
+        None => HashMap::new(),
+    };
+    let mut hits = Vec::new();
+    for root in roots {
+        for entry in walkdir::WalkDir::new(root).sort_by_file_name() {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Some(hit) = scan_hit(entry.path(), &known, name_template) else {
+                continue;
+            };
+            if query.matches(&hit) {
+                hits.push(hit);
+            }
+        }
+    }
+    Ok(hits)
+}
+
+/// `path` made absolute with symlinks resolved, or as is if it does not exist
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn scan_hit(
+    path: &Path,
+    known: &HashMap<PathBuf, RenameRecord>,
+    name_template: Option<&NameTemplate>,
+) -> Option<SearchHit> {
+    let file_name = path.file_name()?.to_string_lossy();
+    let name = name_template
+        .and_then(|template| template.parse_name(&file_name))
+        .or_else(|| StandardizedName::parse(&file_name).ok())?;
+    let record = known.get(&canonical(path));
+    Some(SearchHit {
+        path: path.to_path_buf(),
+        date: name.date,
+        template: name.template_name,
+        primer: name.primer_name,
+        vendor: record.map(|r| r.vendor.clone()),
+        vendor_id: record.map(|r| r.vendor_id.clone()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_pattern() {
+        let glob = FieldPattern::glob("k528*").unwrap();
+        assert!(glob.is_match("K528-1"));
+        assert!(!glob.is_match("XK528"));
+        let glob = FieldPattern::glob("T?").unwrap();
+        assert!(glob.is_match("T7"));
+        assert!(!glob.is_match("T25"));
+        let regex = FieldPattern::regex("K528-[12]").unwrap();
+        assert!(regex.is_match("K528-2"));
+        assert!(FieldPattern::regex("k528-.*").unwrap().is_match("K528-1"));
+        assert!(!regex.is_match("K528-3"));
+    }
+
+    #[test]
+    fn test_scan_roots() {
+        let dir = std::env::temp_dir().join("sanger_rename_scan_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("2025")).unwrap();
+        for name in [
+            "250601.K528-1.T7.ab1",
+            "2025/250715.K528-2.T7.ab1",
+            "2025/250715.GFP.T7.ab1",
+            "K528-1.C1.34781340.B08.ab1",
+        ] {
+            std::fs::write(dir.join(name), b"test content").unwrap();
+        }
+        let query = SearchQuery {
+            template: Some(FieldPattern::glob("K528*").unwrap()),
+            primer: Some(FieldPattern::glob("t7").unwrap()),
+            ..SearchQuery::default()
+        };
+        let hits = scan_roots(std::slice::from_ref(&dir), None, None, &query).unwrap();
+        let templates: Vec<&str> = hits.iter().map(|h| h.template.as_str()).collect();
+        assert_eq!(templates, ["K528-2", "K528-1"]);
+
+        let query = SearchQuery {
+            since: Some(time::macros::date!(2025 - 07 - 01)),
+            ..SearchQuery::default()
+        };
+        let hits = scan_roots(std::slice::from_ref(&dir), None, None, &query).unwrap();
+        assert_eq!(hits.len(), 2);
+
+        let query = SearchQuery {
+            vendor: Some(Vendor::Ruibio),
+            ..SearchQuery::default()
+        };
+        assert!(scan_roots(&[dir], None, None, &query).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_scan_roots_with_name_template() {
+        use crate::config::Config;
+        use crate::sanger_filename::SangerFilename;
+        let dir = std::env::temp_dir().join("sanger_rename_scan_template_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let trace = dir.join("K528-1.C1.34781340.B08.ab1");
+        std::fs::write(&trace, b"test content").unwrap();
+        let config: Config =
+            toml::from_str("[name]\ntemplate = \"{well}_{date}_{template}_{primer}\"").unwrap();
+        let mut sanger_fn = SangerFilename::new(&trace.to_string_lossy(), Vendor::Ruibio);
+        config.apply(&mut sanger_fn);
+        sanger_fn
+            .set_date(time::macros::date!(2025 - 06 - 01))
+            .unwrap();
+        sanger_fn.move_to_standardized_name().unwrap();
+        assert!(dir.join("B08_250601_K528-1_C1.ab1").exists());
+
+        let query = SearchQuery {
+            template: Some(FieldPattern::glob("K528*").unwrap()),
+            ..SearchQuery::default()
+        };
+        let roots = std::slice::from_ref(&dir);
+        assert!(scan_roots(roots, None, None, &query).unwrap().is_empty());
+        let hits = scan_roots(roots, None, config.name.template.as_ref(), &query).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(
+            (hits[0].template.as_str(), hits[0].primer.as_str()),
+            ("K528-1", "C1")
+        );
+        assert_eq!(hits[0].date, time::macros::date!(2025 - 06 - 01));
+    }
+
+    #[test]
+    fn test_search_catalog() {
+        let dir = std::env::temp_dir().join("sanger_rename_search_catalog_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let catalog = Catalog::open(dir.join("catalog.jsonl"));
+        for (template, vendor, date) in [
+            ("K528-1", "Ruibio", "2025-06-01"),
+            ("K528-1", "Sangon", "2025-06-10"),
+            ("GFP", "Ruibio", "2025-06-01"),
+        ] {
+            catalog
+                .append(&RenameRecord {
+                    original_name: String::new(),
+                    new_path: dir.join(format!("{}.{}.T7.ab1", date, template)),
+                    vendor: vendor.to_string(),
+                    vendor_id: String::new(),
+                    well: None,
+                    date: date.to_string(),
+                    template: template.to_string(),
+                    primer: "T7".to_string(),
+                    sha256: String::new(),
+                })
+                .unwrap();
+        }
+        let query = SearchQuery {
+            template: Some(FieldPattern::regex("K528-\\d").unwrap()),
+            vendor: Some(Vendor::Ruibio),
+            ..SearchQuery::default()
+        };
+        let hits = search_catalog(&catalog, &query).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].date, time::macros::date!(2025 - 06 - 01));
+    }
+}
@@ -0,0 +1,153 @@
+use std::str::FromStr;
+
+/// A file name in the `YYMMDD.template.primer.ext` scheme produced by this tool
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StandardizedName {
+    pub date: time::Date,
+    pub template_name: String,
+    pub primer_name: String,
+    /// Extension without the dot, e.g. `ab1`
+    pub extension: Option<String>,
+}
+
+/// Explains why a name is not a standardized name
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum StandardizedNameError {
+    /// The first field is not a valid `YYMMDD` date
+    Date(String),
+    /// The name does not have 3 (without extension) or 4 dot-separated fields
+    FieldCount(usize),
+    /// The template or primer field is empty
+    EmptyField,
+}
+
+impl std::fmt::Display for StandardizedNameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StandardizedNameError::Date(date) => write!(f, "{} is not a YYMMDD date", date),
+            StandardizedNameError::FieldCount(count) => write!(
+                f,
+                "expected date.template.primer[.ext], found {} fields",
+                count
+            ),
+            StandardizedNameError::EmptyField => write!(f, "template or primer name is empty"),
+        }
+    }
+}
+
+impl std::error::Error for StandardizedNameError {}
+
+impl StandardizedName {
+    /// Parse a file name, or a path whose last component is a file name
+    pub fn parse(name: &str) -> Result<Self, StandardizedNameError> {
+        let file_name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+        let fields: Vec<&str> = file_name.split('.').collect();
+        let (date, template_name, primer_name, extension) = match fields[..] {
+            [date, template, primer] => (date, template, primer, None),
+            [date, template, primer, extension] => (date, template, primer, Some(extension)),
+            _ => return Err(StandardizedNameError::FieldCount(fields.len())),
+        };
+        if template_name.is_empty() || primer_name.is_empty() {
+            return Err(StandardizedNameError::EmptyField);
+        }
+        Ok(Self {
+            date: parse_date(date)?,
+            template_name: template_name.to_string(),
+            primer_name: primer_name.to_string(),
+            extension: extension.map(str::to_string),
+        })
+    }
+
+    /// The `YYMMDD` date field
+    pub fn date_field(&self) -> String {
+        format!(
+            "{:02}{:02}{:02}",
+            self.date.year() % 100, // last two digits of the year
+            self.date.month() as u8,
+            self.date.day()
+        )
+    }
+}
+
+/// Dates are written with two-digit years and read back as 20YY
+fn parse_date(field: &str) -> Result<time::Date, StandardizedNameError> {
+    let error = || StandardizedNameError::Date(field.to_string());
+    if field.len() != 6 || !field.chars().all(|c| c.is_ascii_digit()) {
+        return Err(error());
+    }
+    let year: i32 = field[0..2].parse().map_err(|_| error())?;
+    let month: u8 = field[2..4].parse().map_err(|_| error())?;
+    let day: u8 = field[4..6].parse().map_err(|_| error())?;
+    let month = time::Month::try_from(month).map_err(|_| error())?;
+    time::Date::from_calendar_date(2000 + year, month, day).map_err(|_| error())
+}
+
+impl FromStr for StandardizedName {
+    type Err = StandardizedNameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl std::fmt::Display for StandardizedName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}",
+            self.date_field(),
+            self.template_name,
+            self.primer_name
+        )?;
+        if let Some(extension) = &self.extension {
+            write!(f, ".{}", extension)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_standardized_name() {
+        let name = StandardizedName::parse("/data/traces/251206.K528-1.C1.ab1").unwrap();
+        assert_eq!(
+            name.date,
+            time::Date::from_calendar_date(2025, time::Month::December, 6).unwrap()
+        );
+        assert_eq!(name.template_name, "K528-1");
+        assert_eq!(name.primer_name, "C1");
+        assert_eq!(name.extension.as_deref(), Some("ab1"));
+        assert_eq!(name.to_string(), "251206.K528-1.C1.ab1");
+
+        let name: StandardizedName = "250601.质粒1.T7".parse().unwrap();
+        assert_eq!(name.extension, None);
+        assert_eq!(name.to_string(), "250601.质粒1.T7");
+    }
+
+    #[test]
+    fn test_parse_rejects_vendor_names() {
+        assert_eq!(
+            StandardizedName::parse("K528-1.C1.34781340.B08.ab1"),
+            Err(StandardizedNameError::FieldCount(5))
+        );
+        assert_eq!(
+            StandardizedName::parse("K528-1.C1.B08.ab1"),
+            Err(StandardizedNameError::Date("K528-1".to_string()))
+        );
+        assert_eq!(
+            StandardizedName::parse("251306.K528-1.C1.ab1"),
+            Err(StandardizedNameError::Date("251306".to_string()))
+        );
+        assert_eq!(
+            StandardizedName::parse("0001_31225060307072_(TXPCR)_[SP1].ab1"),
+            Err(StandardizedNameError::FieldCount(2))
+        );
+        assert_eq!(
+            StandardizedName::parse("250601..T7.ab1"),
+            Err(StandardizedNameError::EmptyField)
+        );
+    }
+}
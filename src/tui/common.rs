@@ -1,5 +1,17 @@
+use ratatui::{layout::Constraint, text::Span};
 use sanger_rename::SangerFilename;
 use sanger_rename::Vendor;
+use sanger_rename::abif::Trace;
+use sanger_rename::align::{self, Alignment, Strand};
+use sanger_rename::config;
+use sanger_rename::mixed_peaks;
+use sanger_rename::primer::{self, PrimerCheck, PrimerLibrary};
+use sanger_rename::reference::ReferenceLibrary;
+use sanger_rename::sanitize::{self, DirListing, NameIssue};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 // Enum to handle stage transitions
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -14,13 +26,60 @@ pub enum StageTransition {
 pub enum Stage {
     VendorSelection,
     PrimerRename,
+    FieldPicker,
     TemplateRename,
     DateSelection,
+    Reconcile,
     ConfirmRename,
+    TraceView,
+    PlateMap,
+}
+
+/// Column constraints proportional to the widest cell of each column
+///
+/// Widths are measured in terminal cells rather than characters, so names
+/// with wide characters such as Chinese get the room they need.
+pub fn fitted_widths<R: AsRef<[String]>>(rows: &[R]) -> Vec<Constraint> {
+    let columns = rows.iter().map(|row| row.as_ref().len()).max().unwrap_or(0);
+    (0..columns)
+        .map(|column| {
+            let width = rows
+                .iter()
+                .filter_map(|row| row.as_ref().get(column))
+                .map(|cell| Span::raw(cell.as_str()).width())
+                .max()
+                .unwrap_or(0);
+            Constraint::Fill(width.clamp(1, u16::MAX as usize) as u16)
+        })
+        .collect()
 }
 
 pub struct SangerFilenames {
     pub filenames: Vec<SangerFilename>,
+    /// Set once the batch has been renamed on disk
+    pub renamed: bool,
+    /// References to align the traces against, looked up by template name
+    pub references: Option<Rc<ReferenceLibrary>>,
+    /// Alignment outcome per (path, template name), computed on first use
+    alignments: HashMap<(String, String), Result<Option<Alignment>, String>>,
+    /// Primer sequences to check the primer names against
+    pub primers: Option<Rc<PrimerLibrary>>,
+    /// Primer check per (path, template name, primer name), computed on first use
+    primer_checks: HashMap<(String, String, String), PrimerCheck>,
+    /// Date chosen for the batch, also given to files renormalized afterwards
+    pub date: Option<time::Date>,
+    /// Secondary to primary peak height above which a position counts as mixed
+    pub mixed_fraction: f64,
+    /// Mean quality from which a read counts as good
+    pub good_quality: f64,
+    /// Mean quality below which a read counts as failed
+    pub poor_quality: f64,
+    /// Mixed position count per path, `None` for files that are not ABIF traces
+    mixed_counts: HashMap<String, Option<usize>>,
+    /// Mean base call quality per path, `None` for files that are not ABIF traces
+    mean_qualities: HashMap<String, Option<f64>>,
+    /// Files in the target folders, read once per batch for the rename checks
+    dir_listing: RefCell<DirListing>,
 }
 
 #[allow(dead_code)]
@@ -28,6 +87,18 @@ impl SangerFilenames {
     pub fn new() -> Self {
         Self {
             filenames: Vec::new(),
+            renamed: false,
+            references: None,
+            alignments: HashMap::new(),
+            primers: None,
+            primer_checks: HashMap::new(),
+            date: None,
+            mixed_fraction: mixed_peaks::DEFAULT_SECONDARY_FRACTION,
+            good_quality: config::DEFAULT_GOOD_QUALITY,
+            poor_quality: config::DEFAULT_POOR_QUALITY,
+            mixed_counts: HashMap::new(),
+            mean_qualities: HashMap::new(),
+            dir_listing: RefCell::new(DirListing::default()),
         }
     }
 
@@ -38,7 +109,148 @@ impl SangerFilenames {
             .any(|f| f.get_full_path() == filename.get_full_path())
         {
             self.filenames.push(filename);
+            self.forget_dir_listing();
+        }
+    }
+
+    /// Read the target folders again at the next rename check
+    pub fn forget_dir_listing(&self) {
+        self.dir_listing.take();
+    }
+
+    /// Files that still cannot be given a standardized name
+    pub fn unresolved_count(&self) -> usize {
+        self.filenames.iter().filter(|f| !f.is_resolved()).count()
+    }
+
+    /// Portability problems and name collisions of the planned renames, per file
+    pub fn rename_issues(&self) -> Vec<Vec<NameIssue>> {
+        if self.renamed {
+            return vec![Vec::new(); self.filenames.len()];
+        }
+        // Skipped and unresolved files stay where they are, so only the moving
+        // files are checked against each other and the files on disk
+        let moving: Vec<usize> = (0..self.filenames.len())
+            .filter(|&i| self.filenames[i].will_rename())
+            .collect();
+        let renames: Vec<(PathBuf, PathBuf)> = moving
+            .iter()
+            .map(|&i| {
+                let f = &self.filenames[i];
+                (PathBuf::from(f.get_full_path()), f.get_standardized_path())
+            })
+            .collect();
+        let mut issues = vec![Vec::new(); self.filenames.len()];
+        for (i, found) in moving.into_iter().zip(sanitize::check_renames_with(
+            &renames,
+            &mut self.dir_listing.borrow_mut(),
+        )) {
+            issues[i] = found;
+        }
+        issues
+    }
+
+    /// Files to be renamed whose planned name cannot be used
+    pub fn issue_count(&self) -> usize {
+        self.rename_issues()
+            .iter()
+            .filter(|issues| !issues.is_empty())
+            .count()
+    }
+
+    /// Files left alone because their names are already standardized
+    pub fn skipped_count(&self) -> usize {
+        self.filenames.iter().filter(|f| f.is_skipped()).count()
+    }
+
+    /// Drop files that still cannot be given a standardized name, returning how many were removed
+    pub fn exclude_unresolved(&mut self) -> usize {
+        let before = self.filenames.len();
+        self.filenames.retain(|f| f.is_resolved());
+        before - self.filenames.len()
+    }
+
+    /// Name under which the reference of the file at `index` is found
+    ///
+    /// The sanitized template name is tried too, as references may be named after it.
+    fn reference_name(&self, index: usize) -> String {
+        let sanger_fn = &self.filenames[index];
+        let template_name = sanger_fn.get_template_name();
+        let sanitized = sanger_fn.get_standardized().template_name;
+        match &self.references {
+            Some(references)
+                if references.get(&template_name).is_none()
+                    && references.get(&sanitized).is_some() =>
+            {
+                sanitized
+            }
+            _ => template_name,
+        }
+    }
+
+    /// Alignment of the trace at `index` to its template's reference
+    ///
+    /// `None` without references; the error explains a missing reference or
+    /// an unreadable trace.
+    pub fn alignment(&mut self, index: usize) -> Option<&Result<Option<Alignment>, String>> {
+        let references = self.references.clone()?;
+        let path = self.filenames.get(index)?.get_full_path();
+        let key = (path, self.reference_name(index));
+        if !self.alignments.contains_key(&key) {
+            let result = align::align_trace(Path::new(&key.0), &key.1, &references)
+                .map_err(|err| err.to_string());
+            self.alignments.insert(key.clone(), result);
+        }
+        self.alignments.get(&key)
+    }
+
+    /// Orientation of the trace at `index` relative to its reference, if it aligned
+    pub fn read_strand(&mut self, index: usize) -> Option<Strand> {
+        match self.alignment(index) {
+            Some(Ok(Some(alignment))) => Some(alignment.strand),
+            _ => None,
+        }
+    }
+
+    /// Whether the primer name of the file at `index` fits its trace; `None` without primers
+    pub fn primer_check(&mut self, index: usize) -> Option<&PrimerCheck> {
+        let primers = self.primers.clone()?;
+        let sanger_fn = self.filenames.get(index)?;
+        let key = (
+            sanger_fn.get_full_path(),
+            self.reference_name(index),
+            sanger_fn.get_primer_name(),
+        );
+        if !self.primer_checks.contains_key(&key) {
+            let check = primer::check_trace(
+                Path::new(&key.0),
+                &key.1,
+                &key.2,
+                &primers,
+                self.references.as_deref(),
+            );
+            self.primer_checks.insert(key.clone(), check);
         }
+        self.primer_checks.get(&key)
+    }
+
+    /// Number of mixed positions in the trace at `index`, `None` if it cannot be read
+    pub fn mixed_count(&mut self, index: usize) -> Option<usize> {
+        let path = self.filenames.get(index)?.get_full_path();
+        let fraction = self.mixed_fraction;
+        *self.mixed_counts.entry(path).or_insert_with_key(|path| {
+            let trace = Trace::from_file(Path::new(path)).ok()?;
+            Some(mixed_peaks::mixed_positions(&trace, fraction).len())
+        })
+    }
+
+    /// Mean quality of the base calls in the trace at `index`, `None` if it cannot be read
+    pub fn mean_quality(&mut self, index: usize) -> Option<f64> {
+        let path = self.filenames.get(index)?.get_full_path();
+        *self
+            .mean_qualities
+            .entry(path)
+            .or_insert_with_key(|path| Trace::from_file(Path::new(path)).ok()?.mean_quality())
     }
 
     pub fn from_str_filenames(str_filenames: Vec<String>, vendor: Vendor) -> Self {
@@ -48,6 +260,7 @@ impl SangerFilenames {
             .collect();
         Self {
             filenames: converted,
+            ..Self::new()
         }
     }
 }
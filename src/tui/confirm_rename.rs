@@ -8,13 +8,41 @@ use ratatui::{
     text::{Line, Text},
     widgets::{Block, Borders, Padding, Paragraph},
 };
-use std::{io::Stdout, rc::Rc, sync::Mutex};
+use sanger_rename::align::Strand;
+use sanger_rename::catalog::{Catalog, RenameRecord};
+use sanger_rename::{assembly, manifest, primer, scf};
+use std::{
+    io::Stdout,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Mutex,
+};
 
 use super::common::StageTransition;
 
 pub struct ConfirmRenameStage {
     pub renamed: bool,
     pub sanger_fns: Rc<Mutex<SangerFilenames>>,
+    /// Every successful rename is recorded here, if set
+    pub catalog: Option<Catalog>,
+    pub error: Option<String>,
+    /// A problem after the files were renamed, such as the catalog not being
+    /// written, which does not undo the renames
+    pub warning: Option<String>,
+    /// Write a consensus of each template's reads after renaming
+    pub assemble: bool,
+    /// Outcome of the assembly, shown once renaming is done
+    pub assembly_summary: Option<String>,
+    /// Write each read as FASTA in reference orientation after renaming
+    pub export_oriented: bool,
+    /// Outcome of the export, shown once renaming is done
+    pub export_summary: Option<String>,
+    /// Write an SCF copy of each trace after renaming
+    pub convert_scf: bool,
+    /// Outcome of the conversion, shown once renaming is done
+    pub scf_summary: Option<String>,
+    /// Where the batch's manifest was written, shown once renaming is done
+    pub manifest_summary: Option<String>,
 }
 
 impl ConfirmRenameStage {
@@ -22,6 +50,16 @@ impl ConfirmRenameStage {
         Self {
             renamed: false,
             sanger_fns: Rc::new(Mutex::new(SangerFilenames::new())),
+            catalog: None,
+            error: None,
+            warning: None,
+            assemble: false,
+            assembly_summary: None,
+            export_oriented: false,
+            export_summary: None,
+            convert_scf: false,
+            scf_summary: None,
+            manifest_summary: None,
         }
     }
     pub fn from_sanger_fns(sanger_fns: Rc<Mutex<SangerFilenames>>) -> Self {
@@ -36,11 +74,126 @@ impl ConfirmRenameStage {
         match key.code {
             KeyCode::Esc | KeyCode::Char('q') => StageTransition::Quit,
             // shift + enter to confirm renaming
-            KeyCode::Enter => {
-                for sanger_fn in self.sanger_fns.lock().unwrap().filenames.iter() {
-                    sanger_fn.move_to_standardized_name().unwrap();
+            KeyCode::Enter if !self.renamed => {
+                // Renaming is blocked while any file is still unparsed or has an unusable name
+                let mut sanger_fns = self.sanger_fns.lock().unwrap();
+                if sanger_fns.unresolved_count() > 0 || sanger_fns.issue_count() > 0 {
+                    return StageTransition::Stay;
+                }
+                // Every file is checked before the first one is moved
+                if let Some(missing) = sanger_fns
+                    .filenames
+                    .iter()
+                    .find(|f| f.will_rename() && !Path::new(&f.get_full_path()).is_file())
+                {
+                    self.error = Some(format!("{}: file not found", missing.show_file_name()));
+                    return StageTransition::Stay;
+                }
+                // Orientation is looked up under the old paths
+                let strands: Vec<Option<Strand>> = (0..sanger_fns.filenames.len())
+                    .map(|i| sanger_fns.read_strand(i))
+                    .collect();
+                let mut moved: Vec<(PathBuf, RenameRecord, Option<Strand>)> = Vec::new();
+                for (sanger_fn, strand) in sanger_fns.filenames.iter().zip(strands) {
+                    match sanger_fn.move_to_standardized_name() {
+                        Ok(Some(record)) => {
+                            moved.push((PathBuf::from(sanger_fn.get_full_path()), record, strand))
+                        }
+                        Ok(None) => {}
+                        Err(err) => {
+                            // Put the files moved so far back, so the batch is never half renamed
+                            let mut error = format!("{}: {}", sanger_fn.show_file_name(), err);
+                            for (source, record, _) in moved.iter().rev() {
+                                if let Err(err) = sanger_rename::move_file(&record.new_path, source)
+                                {
+                                    error.push_str(&format!(
+                                        "; could not move {} back: {}",
+                                        record.new_path.display(),
+                                        err
+                                    ));
+                                }
+                            }
+                            self.error = Some(error);
+                            sanger_fns.forget_dir_listing();
+                            return StageTransition::Stay;
+                        }
+                    }
                 }
+                self.error = None;
+                let mut traces: Vec<(String, PathBuf)> = Vec::new();
+                let mut oriented: Vec<(PathBuf, Option<Strand>)> = Vec::new();
+                let mut records = Vec::new();
+                let mixed_fraction = sanger_fns.mixed_fraction;
+                for (_, record, strand) in moved {
+                    traces.push((record.template.clone(), record.new_path.clone()));
+                    oriented.push((record.new_path.clone(), strand));
+                    if let Some(catalog) = &self.catalog
+                        && let Err(err) = catalog.append(&record)
+                    {
+                        self.warning = Some(format!("Could not record the renames: {}", err));
+                    }
+                    records.push(record);
+                }
+                sanger_fns.renamed = true;
                 self.renamed = true;
+                self.manifest_summary = write_manifest(&records);
+                if self.assemble {
+                    self.assembly_summary = Some(assemble_renamed(&traces));
+                }
+                if self.export_oriented {
+                    self.export_summary = Some(export_oriented(&oriented, mixed_fraction));
+                }
+                if self.convert_scf {
+                    let renamed: Vec<PathBuf> =
+                        traces.iter().map(|(_, path)| path.clone()).collect();
+                    self.scf_summary = Some(convert_scf(&renamed));
+                }
+                StageTransition::Stay
+            }
+            KeyCode::Char('t') if !self.renamed => {
+                for sanger_fn in self.sanger_fns.lock().unwrap().filenames.iter_mut() {
+                    sanger_fn.set_transliterate(!sanger_fn.get_transliterate());
+                }
+                StageTransition::Stay
+            }
+            KeyCode::Char('r') if !self.renamed => {
+                let mut sanger_fns = self.sanger_fns.lock().unwrap();
+                let date = sanger_fns.date;
+                for sanger_fn in sanger_fns.filenames.iter_mut() {
+                    if sanger_fn.is_standardized() {
+                        sanger_fn.set_renormalize(!sanger_fn.get_renormalize());
+                        // Renormalized files take the batch's date, if one was chosen
+                        if let Some(date) = date
+                            && sanger_fn.get_renormalize()
+                        {
+                            sanger_fn.set_date(date).unwrap();
+                        }
+                    }
+                }
+                StageTransition::Stay
+            }
+            KeyCode::Char('c') if !self.renamed => {
+                for sanger_fn in self.sanger_fns.lock().unwrap().filenames.iter_mut() {
+                    let mut rules = sanger_fn.get_sanitize_rules().clone();
+                    rules.case = rules.case.next();
+                    sanger_fn.set_sanitize_rules(rules);
+                }
+                StageTransition::Stay
+            }
+            KeyCode::Char('a') if !self.renamed => {
+                self.assemble = !self.assemble;
+                StageTransition::Stay
+            }
+            KeyCode::Char('o') if !self.renamed => {
+                self.export_oriented = !self.export_oriented;
+                StageTransition::Stay
+            }
+            KeyCode::Char('s') if !self.renamed => {
+                self.convert_scf = !self.convert_scf;
+                StageTransition::Stay
+            }
+            KeyCode::Char('x') if !self.renamed => {
+                self.sanger_fns.lock().unwrap().exclude_unresolved();
                 StageTransition::Stay
             }
             KeyCode::Char('p') | KeyCode::BackTab => {
@@ -71,15 +224,153 @@ impl ConfirmRenameStage {
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::Cyan))
             .padding(Padding::new(0, 0, area.height / 3, 0));
-        let content = if self.renamed {
-            "Renaming completed successfully! Press 'q' to exit."
+        let unresolved = self.sanger_fns.lock().unwrap().unresolved_count();
+        let issues = self.sanger_fns.lock().unwrap().issue_count();
+        let content = if let Some(error) = &self.error {
+            Text::from(vec![
+                Line::from("Renaming stopped with an error:")
+                    .style(Style::default().fg(Color::Red)),
+                Line::from(error.as_str()),
+                Line::from(self.manifest_summary.clone().unwrap_or_default()),
+                Line::from("Press 'q' to exit."),
+            ])
+        } else if self.renamed {
+            let mut lines = vec![Line::from(
+                "Renaming completed successfully! Press 'q' to exit.",
+            )];
+            if let Some(warning) = &self.warning {
+                lines.push(Line::from(warning.as_str()).style(Style::default().fg(Color::Yellow)));
+            }
+            for summary in [
+                &self.assembly_summary,
+                &self.export_summary,
+                &self.scf_summary,
+                &self.manifest_summary,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                lines.push(Line::from(summary.as_str()));
+            }
+            Text::from(lines)
+        } else if unresolved > 0 {
+            Text::from(vec![
+                Line::from(format!("{} file(s) could not be parsed", unresolved))
+                    .style(Style::default().fg(Color::Red)),
+                Line::from("Press 'p' to go back and fill in their names,"),
+                Line::from("or 'x' to exclude them from renaming"),
+            ])
+        } else if issues > 0 {
+            Text::from(vec![
+                Line::from(format!("{} file(s) cannot get their new name", issues))
+                    .style(Style::default().fg(Color::Red)),
+                Line::from("Press 'p' to go back and change their names"),
+            ])
         } else {
-            "Press 'Enter' to confirm renaming"
+            let sanger_fns = self.sanger_fns.lock().unwrap();
+            let case = sanger_fns
+                .filenames
+                .first()
+                .map(|f| f.get_sanitize_rules().case)
+                .unwrap_or_default();
+            let mut lines = vec![
+                Line::from("Press 'Enter' to confirm renaming"),
+                Line::from("Press 't' to toggle ASCII transliteration of names"),
+                Line::from(format!("Press 'c' to change field casing (now {})", case)),
+                Line::from(format!(
+                    "Press 'a' to toggle writing template consensus FASTA (now {})",
+                    if self.assemble { "on" } else { "off" }
+                )),
+                Line::from(format!(
+                    "Press 'o' to toggle exporting reads as FASTA, reverse reads flipped (now {})",
+                    if self.export_oriented { "on" } else { "off" }
+                )),
+                Line::from(format!(
+                    "Press 's' to toggle writing SCF copies of the traces (now {})",
+                    if self.convert_scf { "on" } else { "off" }
+                )),
+            ];
+            if sanger_fns.filenames.iter().any(|f| f.is_standardized()) {
+                lines.push(Line::from(format!(
+                    "{} already standardized file(s) skipped, press 'r' to re-normalize them",
+                    sanger_fns.skipped_count()
+                )));
+            }
+            Text::from(lines)
         };
-        let p = Paragraph::new(Text::from(Line::from(content)))
+        let p = Paragraph::new(content)
             .block(block)
             .alignment(Alignment::Center);
 
         frame.render_widget(p, area);
     }
 }
+
+/// Write a consensus per template of the renamed traces, summarizing the outcome
+fn assemble_renamed(traces: &[(String, PathBuf)]) -> String {
+    let assemblies = assembly::assemble_templates(traces, None, false);
+    let written = assemblies.iter().filter(|a| a.fasta.is_some()).count();
+    let skipped = assemblies
+        .iter()
+        .filter(|a| a.write_error.is_some())
+        .count();
+    let conflicts: usize = assemblies
+        .iter()
+        .filter_map(|a| a.assembly.contig.as_ref())
+        .map(|contig| contig.conflicts.len())
+        .sum();
+    let unreadable: usize = assemblies.iter().map(|a| a.assembly.unreadable.len()).sum();
+    format!(
+        "Wrote {} consensus file(s) with {} conflict(s); {} not written; {} trace(s) unreadable",
+        written, conflicts, skipped, unreadable
+    )
+}
+
+/// Write every renamed read as FASTA next to it, reverse complementing reverse reads
+/// and writing mixed positions as IUPAC codes
+fn export_oriented(reads: &[(PathBuf, Option<Strand>)], mixed_fraction: f64) -> String {
+    let mut written = 0;
+    let mut flipped = 0;
+    let mut failed = 0;
+    for (path, strand) in reads {
+        let dir = path.parent().unwrap_or(std::path::Path::new(""));
+        match primer::write_oriented_fasta(path, *strand, Some(mixed_fraction), dir) {
+            Ok(_) => {
+                written += 1;
+                if *strand == Some(Strand::Reverse) {
+                    flipped += 1;
+                }
+            }
+            Err(_) => failed += 1,
+        }
+    }
+    format!(
+        "Exported {} read(s) as FASTA, {} reverse complemented; {} unreadable",
+        written, flipped, failed
+    )
+}
+
+/// Write an SCF copy next to every renamed trace, summarizing the outcome
+fn convert_scf(traces: &[PathBuf]) -> String {
+    let mut written = 0;
+    let mut failed = 0;
+    for path in traces {
+        let dir = path.parent().unwrap_or(std::path::Path::new(""));
+        match scf::convert(path, dir, false) {
+            Ok(_) => written += 1,
+            Err(_) => failed += 1,
+        }
+    }
+    format!(
+        "Converted {} trace(s) to SCF; {} unreadable or already converted",
+        written, failed
+    )
+}
+
+/// Write the manifests of the renamed traces into their folders, summarizing the outcome
+fn write_manifest(records: &[RenameRecord]) -> Option<String> {
+    if records.is_empty() {
+        return None;
+    }
+    Some(manifest::summarize(&manifest::write_batch(records)).join("; "))
+}
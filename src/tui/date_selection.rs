@@ -36,9 +36,13 @@ impl DateSelectionStage {
         match key.code {
             KeyCode::Esc | KeyCode::Char('q') => StageTransition::Quit,
             KeyCode::Enter => {
-                //for all fn set the date
-                for sanger_fn in self.sanger_fns.lock().unwrap().filenames.iter_mut() {
-                    sanger_fn.set_date(self.selected_date).unwrap();
+                // Skipped files keep their date unless they are renormalized later
+                let mut sanger_fns = self.sanger_fns.lock().unwrap();
+                sanger_fns.date = Some(self.selected_date);
+                for sanger_fn in sanger_fns.filenames.iter_mut() {
+                    if !sanger_fn.is_skipped() {
+                        sanger_fn.set_date(self.selected_date).unwrap();
+                    }
                 }
                 StageTransition::Next(Stage::ConfirmRename)
             }
@@ -0,0 +1,245 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Paragraph, Row, Table},
+};
+use sanger_rename::{FieldRule, SangerFilename};
+use std::ops::RangeInclusive;
+use std::{io::Stdout, rc::Rc, sync::Mutex};
+
+use super::common::{SangerFilenames, Stage, StageTransition};
+
+const SEPARATOR: char = '_';
+
+/// Lets the user mark the template and primer on one example filename and
+/// turns that into a [`FieldRule`] for the whole batch
+pub struct FieldPickerStage {
+    pub sanger_fns: Rc<Mutex<SangerFilenames>>,
+    pub example: usize,
+    pub cursor: usize,
+    pub template: Option<RangeInclusive<usize>>,
+    pub primer: Option<RangeInclusive<usize>>,
+    pub applied_rule: Option<FieldRule>,
+    pub message: Option<String>,
+}
+
+impl FieldPickerStage {
+    pub fn init() -> Self {
+        Self {
+            sanger_fns: Rc::new(Mutex::new(SangerFilenames::new())),
+            example: 0,
+            cursor: 0,
+            template: None,
+            primer: None,
+            applied_rule: None,
+            message: None,
+        }
+    }
+    pub fn from_sanger_fns(sanger_fns: Rc<Mutex<SangerFilenames>>) -> Self {
+        let mut stage = Self::init();
+        stage.sanger_fns = sanger_fns.clone();
+        stage
+    }
+
+    fn example_stem(&self) -> String {
+        self.sanger_fns
+            .lock()
+            .unwrap()
+            .filenames
+            .get(self.example)
+            .map(|f| f.get_file_stem())
+            .unwrap_or_default()
+    }
+
+    fn token_count(&self) -> usize {
+        self.example_stem().split(SEPARATOR).count()
+    }
+
+    /// The rule described by the current selection, if it is complete and valid
+    pub fn current_rule(&self) -> Option<FieldRule> {
+        let template = self.template.clone()?;
+        let primer = self.primer.clone()?;
+        FieldRule::from_example(&self.example_stem(), SEPARATOR, template, primer).ok()
+    }
+
+    fn extend(span: &Option<RangeInclusive<usize>>, index: usize) -> RangeInclusive<usize> {
+        match span {
+            Some(span) => *span.start().min(&index)..=*span.end().max(&index),
+            None => index..=index,
+        }
+    }
+
+    fn select_example(&mut self, example: usize) {
+        self.example = example;
+        self.cursor = 0;
+        self.template = None;
+        self.primer = None;
+        self.message = None;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> StageTransition {
+        if key.kind != KeyEventKind::Press {
+            return StageTransition::Stay;
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                if self.example > 0 {
+                    self.select_example(self.example - 1);
+                }
+                StageTransition::Stay
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                let file_count = self.sanger_fns.lock().unwrap().filenames.len();
+                if self.example + 1 < file_count {
+                    self.select_example(self.example + 1);
+                }
+                StageTransition::Stay
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.cursor = self.cursor.saturating_sub(1);
+                StageTransition::Stay
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                if self.cursor + 1 < self.token_count() {
+                    self.cursor += 1;
+                }
+                StageTransition::Stay
+            }
+            KeyCode::Char('t') => {
+                self.template = Some(Self::extend(&self.template, self.cursor));
+                StageTransition::Stay
+            }
+            KeyCode::Char('r') => {
+                self.primer = Some(Self::extend(&self.primer, self.cursor));
+                StageTransition::Stay
+            }
+            KeyCode::Char('c') => {
+                self.template = None;
+                self.primer = None;
+                StageTransition::Stay
+            }
+            KeyCode::Char('s') => {
+                self.message = Some(match self.current_rule() {
+                    Some(rule) => match rule.save() {
+                        Ok(path) => format!("Rule saved to {}", path.display()),
+                        Err(err) => format!("Failed to save rule: {}", err),
+                    },
+                    None => "Mark a template and a primer first".to_string(),
+                });
+                StageTransition::Stay
+            }
+            KeyCode::Enter => match self.current_rule() {
+                Some(rule) => {
+                    for sanger_fn in self.sanger_fns.lock().unwrap().filenames.iter_mut() {
+                        sanger_fn.set_field_rule(Some(rule));
+                    }
+                    self.applied_rule = Some(rule);
+                    StageTransition::Previous(Stage::PrimerRename)
+                }
+                None => {
+                    self.message = Some("Mark a template and a primer first".to_string());
+                    StageTransition::Stay
+                }
+            },
+            KeyCode::Esc => StageTransition::Previous(Stage::PrimerRename),
+            _ => StageTransition::Stay,
+        }
+    }
+
+    pub fn render(&self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> anyhow::Result<()> {
+        terminal.draw(|frame| {
+            let chunks =
+                Layout::vertical([Constraint::Length(7), Constraint::Min(0)]).split(frame.area());
+            self.render_example(frame, chunks[0]);
+            self.render_rule_preview(frame, chunks[1]);
+        })?;
+        Ok(())
+    }
+
+    fn render_example(&self, frame: &mut Frame, area: Rect) {
+        let stem = self.example_stem();
+        let mut spans = Vec::new();
+        for (i, token) in stem.split(SEPARATOR).enumerate() {
+            if i > 0 {
+                spans.push(Span::raw(SEPARATOR.to_string()));
+            }
+            let mut style = if self.template.as_ref().is_some_and(|t| t.contains(&i)) {
+                Style::default().fg(Color::Black).bg(Color::Green)
+            } else if self.primer.as_ref().is_some_and(|p| p.contains(&i)) {
+                Style::default().fg(Color::Black).bg(Color::Magenta)
+            } else {
+                Style::default()
+            };
+            if i == self.cursor {
+                style = style.add_modifier(Modifier::UNDERLINED | Modifier::BOLD);
+            }
+            spans.push(Span::styled(token.to_string(), style));
+        }
+        let status = self.message.clone().unwrap_or_default();
+        let text = Text::from(vec![
+            Line::from(spans),
+            Line::from(""),
+            Line::from("h/l: move  t: mark template  r: mark primer  c: clear  j/k: other example"),
+            Line::from("Enter: apply to all files  s: save for future deliveries  Esc: back"),
+            Line::from(status).style(Style::default().fg(Color::Yellow)),
+        ]);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Pick Fields")
+            .title_alignment(Alignment::Center)
+            .border_style(Style::default().fg(Color::Cyan));
+        frame.render_widget(Paragraph::new(text).block(block), area);
+    }
+
+    fn render_rule_preview(&self, frame: &mut Frame, area: Rect) {
+        let rule = self.current_rule();
+        let header = Row::new(["Original", "Template", "Primer", "Vendor ID"])
+            .style(Style::default().add_modifier(Modifier::BOLD));
+        let rows = self
+            .sanger_fns
+            .lock()
+            .unwrap()
+            .filenames
+            .iter()
+            .map(|sf| {
+                let parsed = match &rule {
+                    Some(rule) => {
+                        SangerFilename::parse_with_rule(&sf.get_full_path(), *sf.get_vendor(), rule)
+                    }
+                    None => SangerFilename::parse(&sf.get_full_path(), *sf.get_vendor()),
+                };
+                match parsed {
+                    Ok(fields) => Row::new([
+                        sf.show_file_name(),
+                        fields.template_name,
+                        fields.primer_name,
+                        fields.vendor_id,
+                    ]),
+                    Err(err) => Row::new([
+                        sf.show_file_name(),
+                        format!("<no {}>", err.field),
+                        String::new(),
+                        String::new(),
+                    ])
+                    .style(Style::default().fg(Color::Red)),
+                }
+            })
+            .collect::<Vec<_>>();
+        let widths = [
+            Constraint::Percentage(40),
+            Constraint::Percentage(25),
+            Constraint::Percentage(20),
+            Constraint::Percentage(15),
+        ];
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Rule Preview")
+            .title_alignment(Alignment::Center)
+            .border_style(Style::default().fg(Color::Cyan));
+        frame.render_widget(Table::new(rows, widths).header(header).block(block), area);
+    }
+}
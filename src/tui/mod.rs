@@ -5,7 +5,14 @@ use ratatui::{
     prelude::*,
     widgets::{Block, Borders, Row, Table},
 };
-use sanger_rename::{SangerFilename, Vendor};
+use sanger_rename::catalog::Catalog;
+use sanger_rename::config::Config;
+use sanger_rename::mapping::NameMappings;
+use sanger_rename::mixed_peaks;
+use sanger_rename::order::Order;
+use sanger_rename::primer::PrimerLibrary;
+use sanger_rename::reference::ReferenceLibrary;
+use sanger_rename::{FieldRule, NameField, SangerFilename, Vendor};
 use std::io::Stdout;
 use std::rc::Rc;
 use std::sync::Mutex;
@@ -14,15 +21,23 @@ use strum::IntoEnumIterator;
 pub mod common;
 pub mod confirm_rename;
 pub mod date_selection;
+pub mod field_picker;
+pub mod plate_map;
 pub mod primer_rename;
+pub mod reconcile;
 pub mod template_rename;
+pub mod trace_view;
 pub mod vendor_selection;
 
-pub use common::{SangerFilenames, Stage, StageTransition, StrFilenames};
+pub use common::{SangerFilenames, Stage, StageTransition, StrFilenames, fitted_widths};
 pub use confirm_rename::ConfirmRenameStage;
 pub use date_selection::DateSelectionStage;
+pub use field_picker::FieldPickerStage;
+pub use plate_map::PlateMapStage;
 pub use primer_rename::PrimerRenameStage;
+pub use reconcile::ReconcileStage;
 pub use template_rename::TemplateRenameStage;
+pub use trace_view::TraceViewStage;
 pub use vendor_selection::VendorSelectionStage;
 
 // Extension trait for additional TUI-specific methods on Vendor
@@ -46,11 +61,23 @@ pub struct App {
     pub stage: Stage,
     sanger_fns: Rc<Mutex<SangerFilenames>>,
     str_fns: StrFilenames,
+    field_rule: Option<FieldRule>,
+    mappings: NameMappings,
+    config: Config,
+    catalog: Option<Catalog>,
+    references: Option<Rc<ReferenceLibrary>>,
+    primers: Option<Rc<PrimerLibrary>>,
+    mixed_fraction: f64,
+    order: Option<Rc<Order>>,
     vendor_selection: VendorSelectionStage,
     primer_rename: PrimerRenameStage,
+    field_picker: FieldPickerStage,
     template_rename: TemplateRenameStage,
     date_selection: DateSelectionStage,
+    reconcile: ReconcileStage,
     confirm_rename: ConfirmRenameStage,
+    trace_view: TraceViewStage,
+    plate_map: PlateMapStage,
 }
 
 impl Default for App {
@@ -62,11 +89,23 @@ impl Default for App {
             str_fns: StrFilenames {
                 filenames: Vec::new(),
             },
+            field_rule: None,
+            mappings: NameMappings::default(),
+            config: Config::default(),
+            catalog: None,
+            references: None,
+            primers: None,
+            mixed_fraction: mixed_peaks::DEFAULT_SECONDARY_FRACTION,
+            order: None,
             vendor_selection: VendorSelectionStage::new(),
             primer_rename: PrimerRenameStage::init(),
+            field_picker: FieldPickerStage::init(),
             template_rename: TemplateRenameStage::init(),
             date_selection: DateSelectionStage::init(),
+            reconcile: ReconcileStage::init(),
             confirm_rename: ConfirmRenameStage::init(),
+            trace_view: TraceViewStage::init(),
+            plate_map: PlateMapStage::init(),
         }
     }
 }
@@ -98,14 +137,71 @@ impl App {
     pub fn get_sanger_filenames(&self) -> Vec<SangerFilename> {
         self.sanger_fns.lock().unwrap().filenames.clone()
     }
+    /// Set the rule used to parse Genewiz filenames instead of splitting on `_`
+    pub fn set_field_rule(&mut self, rule: Option<FieldRule>) {
+        self.field_rule = rule;
+    }
+    /// Apply these primer and template renames to every new batch
+    pub fn set_mappings(&mut self, mappings: NameMappings) {
+        self.mappings = mappings;
+    }
+    /// Apply the configured naming style, output folder and quality thresholds
+    /// to every new batch, and highlight the configured vendor
+    pub fn set_config(&mut self, config: Config) {
+        if let Some(index) = config
+            .vendor
+            .and_then(|vendor| Vendor::all().iter().position(|v| *v == vendor))
+        {
+            self.set_vendor_highlighted(index);
+        }
+        self.config = config;
+    }
+    /// Record every rename in `catalog`
+    pub fn set_catalog(&mut self, catalog: Option<Catalog>) {
+        self.catalog = catalog;
+    }
+    /// Align every trace to its template's reference in the rename preview
+    pub fn set_references(&mut self, references: Option<ReferenceLibrary>) {
+        self.references = references.map(Rc::new);
+    }
+    /// Check every trace's primer name against these primer sequences
+    pub fn set_primers(&mut self, primers: Option<PrimerLibrary>) {
+        self.primers = primers.map(Rc::new);
+    }
+    /// Count positions whose secondary peak reaches this fraction of the primary as mixed
+    pub fn set_mixed_fraction(&mut self, fraction: f64) {
+        self.mixed_fraction = fraction;
+    }
+    /// Check the batch against this order before confirming the renames
+    pub fn set_order(&mut self, order: Option<Order>) {
+        self.order = order.map(Rc::new);
+    }
     pub fn filenames_string_to_sanger(&mut self) -> anyhow::Result<()> {
-        let vendor = self.vendor_selection.get_selected_vendor();
-        self.sanger_fns = Rc::new(Mutex::new(SangerFilenames::from_str_filenames(
-            self.str_fns.filenames.clone(),
-            vendor.unwrap(),
-        )));
+        let vendor = self.vendor_selection.get_selected_vendor().unwrap();
+        let mut sanger_fns =
+            SangerFilenames::from_str_filenames(self.str_fns.filenames.clone(), vendor);
+        if vendor == Vendor::Genewiz && self.field_rule.is_some() {
+            for sanger_fn in sanger_fns.filenames.iter_mut() {
+                sanger_fn.set_field_rule(self.field_rule);
+            }
+        }
+        for sanger_fn in sanger_fns.filenames.iter_mut() {
+            self.config.apply(sanger_fn);
+            self.mappings.apply(sanger_fn);
+        }
+        sanger_fns.references = self.references.clone();
+        sanger_fns.primers = self.primers.clone();
+        sanger_fns.mixed_fraction = self.mixed_fraction;
+        sanger_fns.good_quality = self.config.qc.good_quality();
+        sanger_fns.poor_quality = self.config.qc.poor_quality();
+        self.sanger_fns = Rc::new(Mutex::new(sanger_fns));
         Ok(())
     }
+    /// Go straight to the confirmation of the selected vendor's files, e.g. for queued traces
+    pub fn skip_to_confirmation(&mut self) {
+        self.handle_stage_transition(StageTransition::Next(Stage::PrimerRename));
+        self.handle_stage_transition(StageTransition::Next(Stage::ConfirmRename));
+    }
     pub fn get_selected_vendor(&self) -> Option<Vendor> {
         self.vendor_selection.get_selected_vendor()
     }
@@ -125,14 +221,24 @@ impl App {
     fn handle_stage_transition(&mut self, transition: StageTransition) {
         match transition {
             StageTransition::Stay => {}
+            // With an order, the delivery is checked against it before confirming
+            StageTransition::Next(Stage::ConfirmRename)
+                if self.stage == Stage::DateSelection && self.order.is_some() =>
+            {
+                self.handle_stage_transition(StageTransition::Next(Stage::Reconcile));
+            }
             StageTransition::Next(stage) => {
-                self.stage = stage;
+                let from = std::mem::replace(&mut self.stage, stage);
                 match self.stage {
                     Stage::PrimerRename => {
                         self.filenames_string_to_sanger().unwrap();
                         let sanger_fns = Rc::clone(&self.sanger_fns);
                         self.primer_rename = PrimerRenameStage::from_sanger_fns(sanger_fns);
                     }
+                    Stage::FieldPicker => {
+                        let sanger_fns = Rc::clone(&self.sanger_fns);
+                        self.field_picker = FieldPickerStage::from_sanger_fns(sanger_fns);
+                    }
                     Stage::TemplateRename => {
                         let sanger_fns = Rc::clone(&self.sanger_fns);
                         self.template_rename = TemplateRenameStage::from_sanger_fns(sanger_fns);
@@ -141,20 +247,50 @@ impl App {
                         let sanger_fns = Rc::clone(&self.sanger_fns);
                         self.date_selection = DateSelectionStage::from_sanger_fns(sanger_fns);
                     }
+                    Stage::Reconcile => {
+                        let sanger_fns = Rc::clone(&self.sanger_fns);
+                        let order = Rc::clone(self.order.as_ref().unwrap());
+                        self.reconcile = ReconcileStage::from_sanger_fns(sanger_fns, order);
+                    }
                     Stage::ConfirmRename => {
                         let sanger_fns = Rc::clone(&self.sanger_fns);
                         self.confirm_rename = ConfirmRenameStage::from_sanger_fns(sanger_fns);
+                        self.confirm_rename.catalog = self.catalog.clone();
+                    }
+                    Stage::TraceView => {
+                        let highlighted = match from {
+                            Stage::TemplateRename => self.template_rename.highlighted_file(),
+                            _ => self.primer_rename.highlighted_file(),
+                        };
+                        let Some(index) = highlighted else {
+                            self.stage = from;
+                            return;
+                        };
+                        let sanger_fns = Rc::clone(&self.sanger_fns);
+                        self.trace_view = TraceViewStage::from_sanger_fns(sanger_fns, from, index);
+                    }
+                    Stage::PlateMap => {
+                        let sanger_fns = Rc::clone(&self.sanger_fns);
+                        self.plate_map = PlateMapStage::from_sanger_fns(sanger_fns, from);
                     }
                     _ => {}
                 }
             }
             StageTransition::Previous(stage) => {
-                self.stage = stage;
+                // The viewer and plate map return to their stage as it was left
+                let from = std::mem::replace(&mut self.stage, stage);
+                if matches!(from, Stage::TraceView | Stage::PlateMap) {
+                    return;
+                }
                 match self.stage {
                     Stage::VendorSelection => {
                         self.vendor_selection = VendorSelectionStage::new();
                     }
                     Stage::PrimerRename => {
+                        // Keep a rule picked for this batch when moving back and forth
+                        if let Some(rule) = self.field_picker.applied_rule.take() {
+                            self.field_rule = Some(rule);
+                        }
                         let sanger_fns = Rc::clone(&self.sanger_fns);
                         self.primer_rename = PrimerRenameStage::from_sanger_fns(sanger_fns);
                     }
@@ -166,9 +302,14 @@ impl App {
                         let sanger_fns = Rc::clone(&self.sanger_fns);
                         self.date_selection = DateSelectionStage::from_sanger_fns(sanger_fns);
                     }
-                    Stage::ConfirmRename => {
+                    Stage::FieldPicker
+                    | Stage::Reconcile
+                    | Stage::ConfirmRename
+                    | Stage::TraceView
+                    | Stage::PlateMap => {
                         unreachable!(
-                            "ConfirmRename stage should not be able to be reached in this direction"
+                            "{:?} stage should not be able to be reached in this direction",
+                            self.stage
                         );
                     }
                 }
@@ -183,9 +324,13 @@ impl App {
         let transition = match self.stage {
             Stage::VendorSelection => self.vendor_selection.handle_key(key),
             Stage::PrimerRename => self.primer_rename.handle_key(key),
+            Stage::FieldPicker => self.field_picker.handle_key(key),
             Stage::DateSelection => self.date_selection.handle_key(key),
             Stage::TemplateRename => self.template_rename.handle_key(key),
+            Stage::Reconcile => self.reconcile.handle_key(key),
             Stage::ConfirmRename => self.confirm_rename.handle_key(key),
+            Stage::TraceView => self.trace_view.handle_key(key),
+            Stage::PlateMap => self.plate_map.handle_key(key),
         };
         self.handle_stage_transition(transition);
     }
@@ -223,27 +368,99 @@ impl App {
             .title("Rename Preview")
             .title_alignment(Alignment::Center)
             .border_style(Style::default().fg(Color::Cyan));
-        let header = Row::new(["Original", "-->", "Standardized"])
-            .style(Style::default().add_modifier(Modifier::BOLD));
+        let mut header = vec!["Original", "-->", "Standardized"];
 
-        let mut rows = vec![];
-        for sf in sanger_fns.lock().unwrap().filenames.iter() {
+        let mut cells = vec![];
+        let mut styles = vec![];
+        let mut sanger_fns = sanger_fns.lock().unwrap();
+        let rename_issues = sanger_fns.rename_issues();
+        for (sf, issues) in sanger_fns.filenames.iter().zip(rename_issues) {
             let original_name = sf.show_file_name();
-            let extname = sf.get_extension_name();
-            let standardized_name = format!("{}.{}", sf.get_standardized_name(), extname);
-            rows.push(Row::new([
-                original_name,
-                "-->".to_string(),
-                standardized_name,
-            ]));
+            let standardized_name = sf
+                .get_standardized_path()
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            if sf.is_skipped() {
+                cells.push([
+                    original_name,
+                    "---".to_string(),
+                    "<already standardized, skipped>".to_string(),
+                ]);
+                styles.push(Style::default().fg(Color::DarkGray));
+                continue;
+            }
+            match sf.parse_error() {
+                // Files stay highlighted until both names are filled in, whether
+                // they did not parse or a name was cleared by hand
+                err if !sf.is_resolved() => {
+                    let reason = match err {
+                        Some(err) => format!("<unparsed: no {}>", err.field),
+                        None if sf.get_template_name().is_empty() => {
+                            format!("<no {}>", NameField::Template)
+                        }
+                        None => format!("<no {}>", NameField::Primer),
+                    };
+                    cells.push([original_name, "-x-".to_string(), reason]);
+                    styles.push(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD));
+                }
+                _ => {
+                    if let Some(issue) = issues.first() {
+                        cells.push([
+                            original_name,
+                            "-x-".to_string(),
+                            format!("{} ({})", standardized_name, issue),
+                        ]);
+                        styles.push(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD));
+                    } else {
+                        cells.push([original_name, "-->".to_string(), standardized_name]);
+                        // Names changed by sanitization are shown in yellow
+                        styles.push(if sf.is_sanitized() {
+                            Style::default().fg(Color::Yellow)
+                        } else {
+                            Style::default()
+                        });
+                    }
+                }
+            }
         }
 
-        let table_width = [
-            Constraint::Percentage(45),
-            Constraint::Percentage(10),
-            Constraint::Percentage(45),
-        ];
-
+        let mut cells: Vec<Vec<String>> = cells.into_iter().map(Vec::from).collect();
+        if sanger_fns.references.is_some() {
+            header.push("Alignment");
+            for (i, row) in cells.iter_mut().enumerate() {
+                row.push(match sanger_fns.alignment(i) {
+                    Some(Ok(Some(alignment))) => alignment.summary(),
+                    Some(Ok(None)) => "<no alignment>".to_string(),
+                    Some(Err(err)) => format!("<{}>", err),
+                    None => String::new(),
+                });
+            }
+        }
+        if sanger_fns.primers.is_some() {
+            header.push("Primer");
+            for (i, row) in cells.iter_mut().enumerate() {
+                let check = sanger_fns.primer_check(i);
+                row.push(check.map(|c| c.to_string()).unwrap_or_default());
+            }
+        }
+        // Mixed peaks are only counted once there are actual traces in the batch
+        let mixed: Vec<Option<usize>> = (0..cells.len())
+            .map(|i| sanger_fns.mixed_count(i))
+            .collect();
+        if mixed.iter().any(Option::is_some) {
+            header.push("Mixed");
+            for (row, count) in cells.iter_mut().zip(mixed) {
+                row.push(count.map_or("-".to_string(), |c| c.to_string()));
+            }
+        }
+        let table_width = fitted_widths(&cells);
+        let header = Row::new(header).style(Style::default().add_modifier(Modifier::BOLD));
+        let rows = cells
+            .into_iter()
+            .zip(styles)
+            .map(|(cells, style)| Row::new(cells).style(style));
         let table = Table::new(rows, table_width).header(header).block(block);
 
         frame.render_widget(table, area);
@@ -259,15 +476,27 @@ impl App {
                 Stage::PrimerRename => {
                     self.primer_rename_page(&mut term)?;
                 }
+                Stage::FieldPicker => {
+                    self.field_picker.render(&mut term)?;
+                }
                 Stage::TemplateRename => {
                     self.template_rename_page(&mut term)?;
                 }
                 Stage::DateSelection => {
                     self.date_selection_page(&mut term)?;
                 }
+                Stage::Reconcile => {
+                    self.reconcile.render(&mut term)?;
+                }
                 Stage::ConfirmRename => {
                     self.confirm_rename.render(&mut term)?;
                 }
+                Stage::TraceView => {
+                    self.trace_view.render(&mut term)?;
+                }
+                Stage::PlateMap => {
+                    self.plate_map.render(&mut term)?;
+                }
             }
             if let Some(ev) = event::read()?.as_key_press_event() {
                 self.handle_key(ev);
@@ -292,19 +521,20 @@ mod tests {
             Vendor::Sangon => "fixtures/sangon",
             Vendor::Ruibio => "fixtures/ruibio",
             Vendor::Genewiz => "fixtures/genewiz",
+            Vendor::Tsingke => "fixtures/tsingke",
+            Vendor::Eurofins => "fixtures/eurofins",
+            Vendor::Macrogen => "fixtures/macrogen",
         };
 
         // Read all .ab1 files from the fixture directory
         let mut filenames = Vec::new();
         if let Ok(entries) = fs::read_dir(fixture_dir) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.extension().and_then(|s| s.to_str()) == Some("ab1") {
-                        if let Some(path_str) = path.to_str() {
-                            filenames.push(path_str.to_string());
-                        }
-                    }
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|s| s.to_str()) == Some("ab1")
+                    && let Some(path_str) = path.to_str()
+                {
+                    filenames.push(path_str.to_string());
                 }
             }
         }
@@ -380,6 +610,515 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_highlighted_file_after_rename() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        let mut app = App::new();
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(setup_test_filenames(Vendor::Ruibio));
+        app.handle_stage_transition(StageTransition::Next(Stage::PrimerRename));
+        for highlighted in 0..app.primer_rename.rename_map.len() {
+            app.primer_rename.highlighted = highlighted;
+            let index = app.primer_rename.highlighted_file().unwrap();
+            let press =
+                |app: &mut App, code| app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+            press(&mut app, KeyCode::Enter);
+            for c in format!("P{}", highlighted).chars() {
+                press(&mut app, KeyCode::Char(c));
+            }
+            press(&mut app, KeyCode::Enter);
+            assert_eq!(app.primer_rename.highlighted_file(), Some(index));
+            assert_eq!(
+                app.get_sanger_filenames()[index].get_primer_name(),
+                format!("P{}", highlighted)
+            );
+        }
+        app.primer_rename.highlighted = app.primer_rename.rename_map.len();
+        assert_eq!(app.primer_rename.highlighted_file(), None);
+    }
+
+    #[test]
+    fn test_unparsed_files_renamed_one_by_one() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        let mut app = App::new();
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(setup_test_filenames(Vendor::Sangon));
+        app.handle_stage_transition(StageTransition::Next(Stage::PrimerRename));
+        let unparsed = app.get_sanger_filenames().len();
+        assert!(unparsed > 1);
+        // Each unparsed file has its own row rather than sharing the empty name
+        assert_eq!(app.primer_rename.rename_map.len(), unparsed);
+        assert!(!app.primer_rename.rename_map.contains_key(""));
+
+        let press = |app: &mut App, code| app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        let index = app.primer_rename.highlighted_file().unwrap();
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('T'));
+        press(&mut app, KeyCode::Char('7'));
+        press(&mut app, KeyCode::Enter);
+        let primers: Vec<String> = app
+            .get_sanger_filenames()
+            .iter()
+            .map(|f| f.get_primer_name())
+            .collect();
+        assert_eq!(primers[index], "T7");
+        assert_eq!(
+            primers.iter().filter(|p| p.is_empty()).count(),
+            unparsed - 1
+        );
+    }
+
+    #[test]
+    fn test_unparsed_files_block_confirmation() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        let mut app = App::new();
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(setup_test_filenames(Vendor::Ruibio));
+        app.add_filenames(setup_test_filenames(Vendor::Sangon));
+        app.filenames_string_to_sanger().unwrap();
+        app.handle_stage_transition(StageTransition::Next(Stage::ConfirmRename));
+        assert_eq!(app.sanger_fns.lock().unwrap().unresolved_count(), 5);
+
+        // Enter does nothing while Sangon files are unparsed as Ruibio
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(!app.confirm_rename.renamed);
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+        assert_eq!(app.sanger_fns.lock().unwrap().unresolved_count(), 0);
+        assert_eq!(app.get_sanger_filenames().len(), 6);
+    }
+
+    #[test]
+    fn test_name_collisions_block_confirmation() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        let mut app = App::new();
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(setup_test_filenames(Vendor::Ruibio));
+        app.filenames_string_to_sanger().unwrap();
+        for (i, sanger_fn) in app
+            .sanger_fns
+            .lock()
+            .unwrap()
+            .filenames
+            .iter_mut()
+            .enumerate()
+        {
+            // Differently cased names still collide
+            let template_name = if i % 2 == 0 { "same" } else { "SAME" };
+            sanger_fn.set_template_name(template_name).unwrap();
+            sanger_fn.set_primer_name("T7").unwrap();
+        }
+        app.handle_stage_transition(StageTransition::Next(Stage::ConfirmRename));
+        assert_eq!(app.sanger_fns.lock().unwrap().issue_count(), 6);
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(!app.confirm_rename.renamed);
+    }
+
+    #[test]
+    fn test_standardized_files_skipped_by_default() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        let mut app = App::new();
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(setup_test_filenames(Vendor::Ruibio));
+        app.add_filenames(vec!["fixtures/250601.K528-1.C1.ab1".to_string()]);
+        app.filenames_string_to_sanger().unwrap();
+        app.handle_stage_transition(StageTransition::Next(Stage::ConfirmRename));
+        assert_eq!(app.sanger_fns.lock().unwrap().skipped_count(), 1);
+        assert_eq!(app.sanger_fns.lock().unwrap().unresolved_count(), 0);
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE));
+        assert_eq!(app.sanger_fns.lock().unwrap().skipped_count(), 0);
+        app.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::NONE));
+        let sanger_fns = app.get_sanger_filenames();
+        let renormalized = sanger_fns.last().unwrap();
+        assert_eq!(renormalized.get_standardized_name(), "250601.K528-1.C1");
+        assert!(
+            sanger_fns
+                .iter()
+                .any(|f| f.get_standardized_name().ends_with(".ACTIN-BETA.T7"))
+        );
+    }
+
+    #[test]
+    fn test_skipped_files_stay_in_place_when_checking() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        let dir = std::env::temp_dir().join("sanger_rename_tui_skipped");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut filenames = Vec::new();
+        for name in [
+            // Skipped, and already named as the first fresh file's target
+            "250601.K528-1.T7.ab1",
+            // Skipped, would only collide with the second one if it were redated
+            "250501.GFP.T7.ab1",
+            "K528-1.T7.34781340.B08.ab1",
+            "GFP.T7.34781341.B09.ab1",
+        ] {
+            let path = dir.join(name);
+            fs::write(&path, b"trace").unwrap();
+            filenames.push(path.to_string_lossy().to_string());
+        }
+
+        let mut app = App::new();
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(filenames);
+        app.filenames_string_to_sanger().unwrap();
+        app.handle_stage_transition(StageTransition::Next(Stage::DateSelection));
+        app.date_selection.selected_date = time::macros::date!(2025 - 06 - 01);
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(app.stage, Stage::ConfirmRename);
+
+        let issues = app.sanger_fns.lock().unwrap().rename_issues();
+        assert!(issues[0].is_empty() && issues[1].is_empty());
+        assert_eq!(
+            issues[2],
+            [sanger_rename::sanitize::NameIssue::Exists(
+                dir.join("250601.K528-1.T7.ab1")
+            )]
+        );
+        assert!(issues[3].is_empty());
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(!app.confirm_rename.renamed);
+        assert!(dir.join("GFP.T7.34781341.B09.ab1").exists());
+    }
+
+    #[test]
+    fn test_confirmed_renames_are_cataloged() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        let dir = std::env::temp_dir().join("sanger_rename_tui_catalog");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut filenames = Vec::new();
+        for fixture in setup_test_filenames(Vendor::Ruibio) {
+            let path = dir.join(std::path::Path::new(&fixture).file_name().unwrap());
+            fs::copy(&fixture, &path).unwrap();
+            filenames.push(path.to_string_lossy().to_string());
+        }
+        let catalog = Catalog::open(dir.join("catalog.jsonl"));
+
+        let mut app = App::new();
+        app.set_catalog(Some(catalog.clone()));
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(filenames);
+        app.filenames_string_to_sanger().unwrap();
+        app.handle_stage_transition(StageTransition::Next(Stage::ConfirmRename));
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(app.confirm_rename.renamed);
+        assert_eq!(app.confirm_rename.error, None);
+
+        let records = catalog.records().unwrap();
+        assert_eq!(records.len(), 6);
+        for record in &records {
+            assert!(record.new_path.is_absolute() && record.new_path.exists());
+            assert_eq!(record.vendor, "Ruibio");
+        }
+        assert!(
+            records
+                .iter()
+                .any(|r| r.well.as_deref() == Some("B08") && r.template == "K528-1")
+        );
+    }
+
+    #[test]
+    fn test_catalog_failure_does_not_stop_renaming() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        let dir = std::env::temp_dir().join("sanger_rename_tui_catalog_failure");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("catalog.jsonl")).unwrap();
+        let mut filenames = Vec::new();
+        for fixture in setup_test_filenames(Vendor::Ruibio) {
+            let path = dir.join(std::path::Path::new(&fixture).file_name().unwrap());
+            fs::copy(&fixture, &path).unwrap();
+            filenames.push(path.to_string_lossy().to_string());
+        }
+
+        // The catalog path is a folder, so recording the renames fails
+        let mut app = App::new();
+        app.set_catalog(Some(Catalog::open(dir.join("catalog.jsonl"))));
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(filenames);
+        app.filenames_string_to_sanger().unwrap();
+        app.handle_stage_transition(StageTransition::Next(Stage::ConfirmRename));
+        app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::NONE));
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(app.confirm_rename.renamed);
+        assert_eq!(app.confirm_rename.error, None);
+        assert!(app.confirm_rename.warning.is_some());
+        // The fixtures are placeholders, so converting them fails, but it is attempted
+        assert!(app.confirm_rename.scf_summary.is_some());
+    }
+
+    #[test]
+    fn test_preview_aligns_to_references() {
+        use sanger_rename::abif::Trace;
+        use sanger_rename::align::{Strand, reverse_complement};
+        let dir = std::env::temp_dir().join("sanger_rename_tui_align");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let reference = "GACCTGAGCGTACGATCGGATCCAAGCTTGCATGCCTGCAGGTCGACTCTAGAGGATC";
+        fs::write(dir.join("K528-1.fasta"), format!(">pK528\n{}\n", reference)).unwrap();
+        let read = String::from_utf8(reverse_complement(&reference.as_bytes()[5..50])).unwrap();
+        let trace_path = dir.join("K528-1.C1.34781340.B08.ab1");
+        fs::write(&trace_path, Trace::synthetic(&read, 10, 40).to_abif_bytes()).unwrap();
+
+        let mut app = App::new();
+        app.set_references(Some(ReferenceLibrary::load(&[&dir]).unwrap()));
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(vec![
+            trace_path.to_string_lossy().to_string(),
+            "fixtures/ruibio/K528-1.C1.34781340.B08.ab1".to_string(),
+            "fixtures/ruibio/GFP-CTRL.M13F.34781343.E12.ab1".to_string(),
+        ]);
+        app.filenames_string_to_sanger().unwrap();
+        let mut sanger_fns = app.sanger_fns.lock().unwrap();
+        let alignment = sanger_fns.alignment(0).unwrap().clone().unwrap().unwrap();
+        assert_eq!(alignment.strand, Strand::Reverse);
+        assert_eq!(
+            (alignment.reference_start, alignment.reference_end),
+            (5, 50)
+        );
+        assert_eq!(alignment.identity(), 1.0);
+        // Fixture traces are placeholders rather than ABIF files
+        assert!(sanger_fns.alignment(1).unwrap().is_err());
+        assert_eq!(sanger_fns.mixed_count(0), Some(0));
+        assert_eq!(sanger_fns.mixed_count(1), None);
+        assert_eq!(
+            sanger_fns.alignment(2).unwrap().clone().unwrap_err(),
+            "no reference for GFP-CTRL"
+        );
+    }
+
+    #[test]
+    fn test_confirmed_renames_assembled() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        use sanger_rename::abif::Trace;
+        let dir = std::env::temp_dir().join("sanger_rename_tui_assembly");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let plasmid = "GACCTGAGCGTACGATCGGATCCAAGCTTGCATGCCTGCAGGTCGACTCTAGAGGATCCCCGGGTACCGAGCTCG";
+        let mut filenames = Vec::new();
+        for (name, bases) in [
+            ("K528-1.T7.34781340.B08.ab1", &plasmid[..50]),
+            ("K528-1.SP6.34781341.B09.ab1", &plasmid[20..]),
+        ] {
+            let path = dir.join(name);
+            fs::write(&path, Trace::synthetic(bases, 10, 40).to_abif_bytes()).unwrap();
+            filenames.push(path.to_string_lossy().to_string());
+        }
+
+        let mut app = App::new();
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(filenames);
+        app.filenames_string_to_sanger().unwrap();
+        app.handle_stage_transition(StageTransition::Next(Stage::ConfirmRename));
+        app.handle_key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE));
+        app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::NONE));
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(app.confirm_rename.renamed);
+        assert_eq!(
+            app.confirm_rename.assembly_summary.as_deref(),
+            Some(
+                "Wrote 1 consensus file(s) with 0 conflict(s); 0 not written; 0 trace(s) unreadable"
+            )
+        );
+        let fasta = fs::read_to_string(dir.join("K528-1.consensus.fasta")).unwrap();
+        assert_eq!(fasta.lines().skip(1).collect::<String>(), plasmid);
+        // SCF copies carry the new names
+        assert_eq!(
+            app.confirm_rename.scf_summary.as_deref(),
+            Some("Converted 2 trace(s) to SCF; 0 unreadable or already converted")
+        );
+        let sanger_fns = app.sanger_fns.lock().unwrap();
+        let renamed = sanger_fns.filenames[0].get_standardized_path();
+        assert!(renamed.with_extension("scf").exists());
+        // The batch manifest lists both traces
+        assert!(app.confirm_rename.manifest_summary.is_some());
+        let manifest = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .find(|path| path.extension().is_some_and(|ext| ext == "csv"))
+            .unwrap();
+        assert_eq!(fs::read_to_string(manifest).unwrap().lines().count(), 3);
+    }
+
+    #[test]
+    fn test_primer_check_and_oriented_export() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        use sanger_rename::abif::Trace;
+        use sanger_rename::align::reverse_complement;
+        use sanger_rename::primer::PrimerStatus;
+        let dir = std::env::temp_dir().join("sanger_rename_tui_primers");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        // pUC19 MCS between the M13 forward and reverse primer sites
+        let reference = "TTTTCCCAGTCACGACGTTGTAAAACGACGGCCAGTGAATTCGAGCTCGGTACCCGGGGATCCTCTAGAGTCGACCTGCAGGCATGCAAGCTTGGCGTAATCATGGTCATAGCTGTTTCCTGTGTGAAATTG";
+        fs::write(dir.join("pUC19.fasta"), format!(">K528-1\n{}\n", reference)).unwrap();
+        let mut primers = PrimerLibrary::new();
+        primers.insert("M13F", b"GTAAAACGACGGCCAGT");
+        primers.insert("M13R", b"CAGGAAACAGCTATGAC");
+        primers.insert("M13RR", b"CAGGAAACAGCTATGAC");
+
+        let forward = &reference[40..110];
+        let reverse =
+            String::from_utf8(reverse_complement(&reference.as_bytes()[30..100])).unwrap();
+        let mut filenames = Vec::new();
+        for (name, bases) in [
+            ("K528-1.M13F.34781340.B08.ab1", forward),
+            ("K528-1.M13R.34781341.B09.ab1", reverse.as_str()),
+            // Labeled with the wrong primer
+            ("K528-1.M13RR.34781342.B10.ab1", forward),
+        ] {
+            let path = dir.join(name);
+            fs::write(&path, Trace::synthetic(bases, 10, 40).to_abif_bytes()).unwrap();
+            filenames.push(path.to_string_lossy().to_string());
+        }
+
+        let mut app = App::new();
+        app.set_references(Some(ReferenceLibrary::load(&[&dir]).unwrap()));
+        app.set_primers(Some(primers));
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(filenames);
+        app.filenames_string_to_sanger().unwrap();
+        {
+            let mut sanger_fns = app.sanger_fns.lock().unwrap();
+            let statuses: Vec<PrimerStatus> = (0..3)
+                .map(|i| sanger_fns.primer_check(i).unwrap().status)
+                .collect();
+            assert_eq!(
+                statuses,
+                [
+                    PrimerStatus::Consistent,
+                    PrimerStatus::Consistent,
+                    PrimerStatus::Inconsistent
+                ]
+            );
+        }
+
+        app.handle_stage_transition(StageTransition::Next(Stage::ConfirmRename));
+        app.handle_key(KeyEvent::new(KeyCode::Char('o'), KeyModifiers::NONE));
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(app.confirm_rename.error, None);
+        assert_eq!(
+            app.confirm_rename.export_summary.as_deref(),
+            Some("Exported 3 read(s) as FASTA, 1 reverse complemented; 0 unreadable")
+        );
+        let renamed = app.get_sanger_filenames()[1].get_standardized_path();
+        let fasta = fs::read_to_string(renamed.with_extension("fasta")).unwrap();
+        assert_eq!(
+            fasta.lines().skip(1).collect::<String>(),
+            &reference[30..100]
+        );
+    }
+
+    #[test]
+    fn test_field_picker_applies_rule() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        let press = |app: &mut App, c: char| {
+            app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+        };
+        let mut app = App::new();
+        app.set_selected_vendor(Some(Vendor::Genewiz));
+        app.add_filenames(setup_test_filenames(Vendor::Genewiz));
+        app.handle_stage_transition(StageTransition::Next(Stage::PrimerRename));
+        press(&mut app, 'f');
+        assert_eq!(app.stage, Stage::FieldPicker);
+
+        // "BETA-ACTIN_T7_F05": template is the first token, primer the second
+        press(&mut app, 't');
+        press(&mut app, 'l');
+        press(&mut app, 'r');
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(app.stage, Stage::PrimerRename);
+        assert!(app.field_rule.is_some());
+
+        // Names the rule does not fit, like the well-less "BETA-ACTIN_T9",
+        // are still parsed with the built-in scheme
+        let sanger_fns = app.get_sanger_filenames();
+        assert!(sanger_fns.iter().all(|f| f.is_resolved()));
+        let well_less = sanger_fns
+            .iter()
+            .find(|f| f.show_file_name() == "BETA-ACTIN_T9.ab1")
+            .unwrap();
+        assert_eq!(well_less.get_template_name(), "BETA-ACTIN");
+        assert_eq!(well_less.get_primer_name(), "T9");
+        assert!(
+            sanger_fns
+                .iter()
+                .any(|f| f.get_template_name() == "k1-2" && f.get_vendor_id() == "G04")
+        );
+    }
+
+    #[test]
+    fn test_field_picker_only_for_genewiz() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        let mut app = App::new();
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(setup_test_filenames(Vendor::Ruibio));
+        app.handle_stage_transition(StageTransition::Next(Stage::PrimerRename));
+        app.handle_key(KeyEvent::new(KeyCode::Char('f'), KeyModifiers::NONE));
+        assert_eq!(app.stage, Stage::PrimerRename);
+    }
+
+    #[test]
+    fn test_tsingke_filenames() {
+        let mut app = App::new();
+        app.set_selected_vendor(Some(Vendor::Tsingke));
+        app.add_filenames(setup_test_filenames(Vendor::Tsingke));
+        app.filenames_string_to_sanger().unwrap();
+        let sanger_fns = app.get_sanger_filenames();
+        assert_eq!(sanger_fns.len(), 5);
+        assert!(sanger_fns.iter().all(|f| f.parse_error().is_none()));
+
+        let template_names: Vec<String> =
+            sanger_fns.iter().map(|f| f.get_template_name()).collect();
+        assert!(template_names.contains(&"质粒1".to_string()));
+        assert!(template_names.contains(&"pUC19-突变体".to_string()));
+        assert!(template_names.contains(&"GFP_CTRL".to_string()));
+    }
+
+    #[test]
+    fn test_eurofins_filenames() {
+        let mut app = App::new();
+        app.set_selected_vendor(Some(Vendor::Eurofins));
+        app.add_filenames(setup_test_filenames(Vendor::Eurofins));
+        app.filenames_string_to_sanger().unwrap();
+        let sanger_fns = app.get_sanger_filenames();
+        assert_eq!(sanger_fns.len(), 5);
+        assert!(sanger_fns.iter().all(|f| f.parse_error().is_none()));
+
+        let vendor_ids: Vec<String> = sanger_fns.iter().map(|f| f.get_vendor_id()).collect();
+        assert!(vendor_ids.contains(&"AE12345601".to_string()));
+        let primer_names: Vec<String> = sanger_fns.iter().map(|f| f.get_primer_name()).collect();
+        assert!(primer_names.contains(&"M13F".to_string()));
+    }
+
+    #[test]
+    fn test_macrogen_filenames() {
+        let mut app = App::new();
+        app.set_selected_vendor(Some(Vendor::Macrogen));
+        app.add_filenames(setup_test_filenames(Vendor::Macrogen));
+        app.filenames_string_to_sanger().unwrap();
+        let sanger_fns = app.get_sanger_filenames();
+        assert_eq!(sanger_fns.len(), 6);
+        assert!(sanger_fns.iter().all(|f| f.parse_error().is_none()));
+
+        let template_names: Vec<String> =
+            sanger_fns.iter().map(|f| f.get_template_name()).collect();
+        assert!(template_names.contains(&"PROT_X".to_string()));
+        assert!(template_names.contains(&"k1-2".to_string()));
+    }
+
+    #[test]
+    fn test_fitted_widths_count_wide_characters() {
+        let widths = fitted_widths(&[
+            ["质粒1".to_string(), "-->".to_string()],
+            ["T7".to_string(), String::new()],
+        ]);
+        assert_eq!(widths, [Constraint::Fill(5), Constraint::Fill(3)]);
+        let widths = fitted_widths(&[vec!["T7".to_string()], vec![]]);
+        assert_eq!(widths, [Constraint::Fill(2)]);
+    }
+
     #[test]
     fn test_sangon_filenames() {
         let mut app = App::new();
@@ -421,4 +1160,310 @@ mod tests {
         assert!(primer_names.contains(&"T25".to_string()));
         assert!(primer_names.contains(&"T7".to_string()));
     }
+
+    #[test]
+    fn test_trace_view() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        use ratatui::backend::TestBackend;
+        use sanger_rename::abif::Trace;
+        let dir = std::env::temp_dir().join("sanger_rename_tui_trace_view");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let read = "GACCTGAGCGTACGATCGGATCCAAGCTTGCATGCC";
+        let trace_path = dir.join("K528-1.C1.34781340.B08.ab1");
+        fs::write(&trace_path, Trace::synthetic(read, 10, 40).to_abif_bytes()).unwrap();
+
+        let mut app = App::new();
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(vec![
+            trace_path.to_string_lossy().to_string(),
+            "fixtures/ruibio/GFP-CTRL.M13F.34781343.E12.ab1".to_string(),
+        ]);
+        app.handle_stage_transition(StageTransition::Next(Stage::PrimerRename));
+        app.primer_rename.highlighted = 1;
+        let highlighted = app.primer_rename.highlighted_file().unwrap();
+        let press = |app: &mut App, code| app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        press(&mut app, KeyCode::Char('v'));
+        assert_eq!(app.stage, Stage::TraceView);
+        assert_eq!(app.trace_view.index, highlighted);
+        if highlighted == 1 {
+            press(&mut app, KeyCode::Up);
+        }
+        assert_eq!(app.trace_view.index, 0);
+
+        let draw = |stage: &TraceViewStage| {
+            let mut terminal = Terminal::new(TestBackend::new(60, 12)).unwrap();
+            terminal
+                .draw(|frame| stage.render_trace(frame, frame.area()))
+                .unwrap();
+            let buffer = terminal.backend().buffer().clone();
+            (0..buffer.area.height)
+                .map(|y| {
+                    (0..buffer.area.width)
+                        .map(|x| buffer[(x, y)].symbol().to_string())
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+        };
+        // Base calls sit on the second to last row inside the border
+        let lines = draw(&app.trace_view);
+        let bases: String = lines[9].chars().filter(char::is_ascii_alphabetic).collect();
+        assert!(read.starts_with(&bases) && bases.len() >= 15, "{:?}", lines);
+        press(&mut app, KeyCode::Char('e'));
+        let status = app.trace_view.status.clone().unwrap();
+        assert!(status.starts_with("Saved"), "{}", status);
+        let pngs = |dir: &std::path::Path| {
+            fs::read_dir(dir)
+                .unwrap()
+                .filter(|entry| entry.as_ref().unwrap().path().extension() == Some("png".as_ref()))
+                .count()
+        };
+        // Images go into their own folder, not among the traces
+        assert_eq!(pngs(&dir.join(trace_view::EXPORT_DIR)), 1);
+        assert_eq!(pngs(&dir), 0);
+        press(&mut app, KeyCode::Right);
+        assert_eq!(app.trace_view.position, 1);
+        press(&mut app, KeyCode::End);
+        assert_eq!(app.trace_view.position, read.len() - 1);
+        draw(&app.trace_view);
+
+        // The fixture placeholder cannot be shown, but does not break the viewer
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.trace_view.position, 0);
+        assert!(draw(&app.trace_view)[1].contains("Cannot show trace"));
+
+        // Leaving the viewer keeps the rename stage as it was
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.stage, Stage::PrimerRename);
+        assert_eq!(app.primer_rename.highlighted, 1);
+    }
+
+    #[test]
+    fn test_trace_view_ruler() {
+        assert_eq!(
+            trace_view::ruler(5, 25, 3),
+            format!("{}10{}20", " ".repeat(12), " ".repeat(28))
+        );
+        assert_eq!(
+            trace_view::ruler(8, 21, 1),
+            format!(" 10{}20", " ".repeat(8))
+        );
+        // Numbers only start within the view
+        assert_eq!(trace_view::ruler(10, 19, 2), "");
+        assert_eq!(trace_view::ruler(0, 0, 3), "");
+    }
+
+    #[test]
+    fn test_trace_view_scroll_bounds() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        use sanger_rename::abif::Trace;
+        let dir = std::env::temp_dir().join("sanger_rename_tui_trace_bounds");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut sanger_fns = SangerFilenames::new();
+        for (name, read) in [
+            ("long.ab1", "ACGT".repeat(10)),
+            ("empty.ab1", String::new()),
+        ] {
+            let path = dir.join(name);
+            fs::write(&path, Trace::synthetic(&read, 10, 40).to_abif_bytes()).unwrap();
+            sanger_fns.add_filename(SangerFilename::new(&path.to_string_lossy(), Vendor::Ruibio));
+        }
+        let sanger_fns = std::rc::Rc::new(std::sync::Mutex::new(sanger_fns));
+        let mut stage = TraceViewStage::from_sanger_fns(sanger_fns, Stage::PrimerRename, 0);
+        let press = |stage: &mut TraceViewStage, code| {
+            stage.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+        };
+
+        // Scrolling stops at the first and last base
+        press(&mut stage, KeyCode::Left);
+        assert_eq!(stage.position, 0);
+        press(&mut stage, KeyCode::PageDown);
+        assert_eq!(stage.position, 20);
+        press(&mut stage, KeyCode::PageDown);
+        press(&mut stage, KeyCode::PageDown);
+        assert_eq!(stage.position, 39);
+        press(&mut stage, KeyCode::Home);
+        press(&mut stage, KeyCode::End);
+        assert_eq!(stage.position, 39);
+        press(&mut stage, KeyCode::Right);
+        assert_eq!(stage.position, 39);
+        press(&mut stage, KeyCode::PageUp);
+        assert_eq!(stage.position, 19);
+
+        // Switching files starts at the first base and stays within the batch
+        press(&mut stage, KeyCode::Up);
+        assert_eq!(stage.index, 0);
+        press(&mut stage, KeyCode::Down);
+        assert_eq!((stage.index, stage.position), (1, 0));
+        press(&mut stage, KeyCode::Down);
+        assert_eq!(stage.index, 1);
+
+        // A trace without base calls cannot be scrolled
+        press(&mut stage, KeyCode::End);
+        press(&mut stage, KeyCode::Right);
+        assert_eq!(stage.position, 0);
+        press(&mut stage, KeyCode::Up);
+        assert_eq!((stage.index, stage.position), (0, 0));
+    }
+
+    #[test]
+    fn test_plate_map() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        use ratatui::backend::TestBackend;
+        use sanger_rename::plate::{PlateFormat, Well};
+        let mut app = App::new();
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(setup_test_filenames(Vendor::Ruibio));
+        app.handle_stage_transition(StageTransition::Next(Stage::PrimerRename));
+        let press = |app: &mut App, code| app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        press(&mut app, KeyCode::Char('w'));
+        assert_eq!(app.stage, Stage::PlateMap);
+        assert_eq!(app.plate_map.format, PlateFormat::Wells96);
+
+        let wells = app.plate_map.wells();
+        assert_eq!(wells.len(), 6);
+        // E12 is the last filled well, so every other empty well counts as missing
+        let missing = app.plate_map.missing_wells(&wells);
+        assert_eq!(missing.len(), 93 - 6);
+        assert!(missing.contains(&Well::parse("A01").unwrap()));
+        assert!(!missing.contains(&Well::parse("H12").unwrap()));
+
+        press(&mut app, KeyCode::Down);
+        for _ in 0..7 {
+            press(&mut app, KeyCode::Right);
+        }
+        assert_eq!(app.plate_map.cursor.to_string(), "B08");
+        let draw = |stage: &PlateMapStage| {
+            let mut terminal = Terminal::new(TestBackend::new(100, 16)).unwrap();
+            terminal
+                .draw(|frame| stage.render_plate(frame, frame.area()))
+                .unwrap();
+            let buffer = terminal.backend().buffer().clone();
+            (0..buffer.area.height)
+                .map(|y| {
+                    (0..buffer.area.width)
+                        .map(|x| buffer[(x, y)].symbol().to_string())
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let screen = draw(&app.plate_map);
+        assert!(screen.contains("Well B08"), "{}", screen);
+        assert!(screen.contains("template K528-1, primer C1"), "{}", screen);
+        assert!(
+            screen.contains("6 well(s) filled, 87 missing"),
+            "{}",
+            screen
+        );
+        press(&mut app, KeyCode::Char('c'));
+        assert!(draw(&app.plate_map).contains("no trace data"));
+
+        // The larger layout can always be shown, and the cursor stays on the plate
+        press(&mut app, KeyCode::Char('z'));
+        assert_eq!(app.plate_map.format, PlateFormat::Wells384);
+        for _ in 0..20 {
+            press(&mut app, KeyCode::Right);
+        }
+        press(&mut app, KeyCode::Char('z'));
+        assert_eq!(app.plate_map.format, PlateFormat::Wells96);
+        assert_eq!(app.plate_map.cursor.to_string(), "B12");
+
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.stage, Stage::PrimerRename);
+    }
+
+    #[test]
+    fn test_reconcile_before_confirmation() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        use sanger_rename::order::OrderSample;
+        let sample = |index, well: &str, template: &str, primer: &str| OrderSample {
+            index,
+            well: well.to_string(),
+            template: template.to_string(),
+            primer: primer.to_string(),
+        };
+        let order = Order {
+            vendor: "Ruibio".to_string(),
+            date: "2025-12-01".to_string(),
+            plate: "96-well".to_string(),
+            samples: vec![
+                sample(1, "A02", "K528-2", "T7"),
+                sample(2, "B08", "K528-1", "C1"),
+                sample(3, "E12", "GFP-CTRL", "M13F"),
+                sample(4, "F10", "ACTIN-BETA", "T7"),
+                sample(5, "G01", "NEW-1", "T7"),
+            ],
+        };
+        let mut app = App::new();
+        app.set_order(Some(order));
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(setup_test_filenames(Vendor::Ruibio));
+        app.handle_stage_transition(StageTransition::Next(Stage::PrimerRename));
+        app.handle_stage_transition(StageTransition::Next(Stage::DateSelection));
+        let press = |app: &mut App, code| app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(app.stage, Stage::Reconcile);
+        let result = app.reconcile.result.clone().unwrap();
+        assert_eq!(result.matched, 3);
+        let report: Vec<String> = result.discrepancies.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            report,
+            [
+                "position: ACTIN-BETA.T7.34781344.F09.ab1 is from F09, ordered in F10",
+                "not ordered: K528-3.250604-mbp-s3.34810430.D07.ab1",
+                "not ordered: PROT-1.SP6.34781342.C05.ab1",
+                "missing: NEW-1.T7 (G01)",
+            ]
+        );
+
+        press(&mut app, KeyCode::Char('p'));
+        assert_eq!(app.stage, Stage::DateSelection);
+        press(&mut app, KeyCode::Char('n'));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.stage, Stage::ConfirmRename);
+    }
+
+    #[test]
+    fn test_mappings_and_skip_to_confirmation() {
+        let mappings: NameMappings =
+            toml::from_str("[primers]\nC1 = \"T7\"\n[templates]\nK528-1 = \"pK528\"\n").unwrap();
+        let mut app = App::new();
+        app.set_mappings(mappings);
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(vec![
+            "fixtures/ruibio/K528-1.C1.34781340.B08.ab1".to_string(),
+        ]);
+        app.skip_to_confirmation();
+        assert_eq!(app.stage, Stage::ConfirmRename);
+        let sanger_fns = app.get_sanger_filenames();
+        assert_eq!(sanger_fns[0].get_template_name(), "pK528");
+        assert_eq!(sanger_fns[0].get_primer_name(), "T7");
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config: Config = toml::from_str(
+            "vendor = \"genewiz\"\noutput_dir = \"/tmp/renamed\"\n[name]\ncase = \"lower\"\n\
+             [qc]\ngood_quality = 35.0\n",
+        )
+        .unwrap();
+        let mut app = App::new();
+        app.set_config(config);
+        assert_eq!(
+            Vendor::from_index(app.get_vendor_highlighted()),
+            Some(Vendor::Genewiz)
+        );
+        app.set_selected_vendor(Some(Vendor::Ruibio));
+        app.add_filenames(vec![
+            "fixtures/ruibio/K528-1.C1.34781340.B08.ab1".to_string(),
+        ]);
+        app.filenames_string_to_sanger().unwrap();
+        let sanger_fns = app.sanger_fns.lock().unwrap();
+        assert_eq!(sanger_fns.good_quality, 35.0);
+        let path = sanger_fns.filenames[0].get_standardized_path();
+        assert_eq!(path.parent(), Some(std::path::Path::new("/tmp/renamed")));
+        assert!(path.to_string_lossy().ends_with(".k528-1.c1.ab1"));
+    }
 }
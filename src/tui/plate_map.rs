@@ -0,0 +1,302 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap},
+};
+use sanger_rename::plate::{PlateFormat, Well};
+use std::collections::BTreeMap;
+use std::{io::Stdout, rc::Rc, sync::Mutex};
+
+use super::common::{SangerFilenames, Stage, StageTransition};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlateColoring {
+    /// Whether each file got a usable name
+    Status,
+    /// Mean base call quality of each trace
+    Quality,
+}
+
+/// The batch laid out on its plate by the wells in the file names
+pub struct PlateMapStage {
+    pub sanger_fns: Rc<Mutex<SangerFilenames>>,
+    /// Stage to return to when leaving the plate map
+    pub origin: Stage,
+    pub format: PlateFormat,
+    pub coloring: PlateColoring,
+    /// Selected well
+    pub cursor: Well,
+}
+
+impl PlateMapStage {
+    pub fn init() -> Self {
+        Self {
+            sanger_fns: Rc::new(Mutex::new(SangerFilenames::new())),
+            origin: Stage::PrimerRename,
+            format: PlateFormat::default(),
+            coloring: PlateColoring::Status,
+            cursor: Well { row: 0, column: 0 },
+        }
+    }
+    pub fn from_sanger_fns(sanger_fns: Rc<Mutex<SangerFilenames>>, origin: Stage) -> Self {
+        let mut stage = Self::init();
+        stage.sanger_fns = sanger_fns;
+        stage.origin = origin;
+        stage.format = PlateFormat::fitting(stage.wells().into_keys());
+        stage
+    }
+
+    /// Indices of the files in each occupied well
+    pub fn wells(&self) -> BTreeMap<Well, Vec<usize>> {
+        let mut wells: BTreeMap<Well, Vec<usize>> = BTreeMap::new();
+        let sanger_fns = self.sanger_fns.lock().unwrap();
+        for (i, sanger_fn) in sanger_fns.filenames.iter().enumerate() {
+            if let Some(well) = sanger_fn.get_well().and_then(|w| Well::parse(&w)) {
+                wells.entry(well).or_default().push(i);
+            }
+        }
+        wells
+    }
+
+    /// Empty wells before the last occupied one, filling the plate column by column
+    pub fn missing_wells(&self, wells: &BTreeMap<Well, Vec<usize>>) -> Vec<Well> {
+        let last = wells
+            .keys()
+            .filter_map(|&well| self.format.index_of(well))
+            .max();
+        let Some(last) = last else {
+            return Vec::new();
+        };
+        (0..last)
+            .filter_map(|index| self.format.well_at(index))
+            .filter(|well| !wells.contains_key(well))
+            .collect()
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> StageTransition {
+        if key.kind != KeyEventKind::Press {
+            return StageTransition::Stay;
+        }
+        let Well { row, column } = self.cursor;
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('w') => {
+                return StageTransition::Previous(self.origin);
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.cursor.row = row.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.cursor.row = (row + 1).min(self.format.rows() - 1)
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.cursor.column = column.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.cursor.column = (column + 1).min(self.format.columns() - 1)
+            }
+            KeyCode::Char('c') => {
+                self.coloring = match self.coloring {
+                    PlateColoring::Status => PlateColoring::Quality,
+                    PlateColoring::Quality => PlateColoring::Status,
+                }
+            }
+            // A 96-well layout is only offered when every well fits on it
+            KeyCode::Char('z') => {
+                self.format = match self.format {
+                    PlateFormat::Wells96 => PlateFormat::Wells384,
+                    PlateFormat::Wells384 => PlateFormat::fitting(self.wells().into_keys()),
+                };
+                self.cursor.row = self.cursor.row.min(self.format.rows() - 1);
+                self.cursor.column = self.cursor.column.min(self.format.columns() - 1);
+            }
+            _ => {}
+        }
+        StageTransition::Stay
+    }
+
+    /// Color of a file: its naming status, or its read quality
+    fn file_color(&self, sanger_fns: &mut SangerFilenames, index: usize, has_issue: bool) -> Color {
+        match self.coloring {
+            PlateColoring::Status => {
+                let sanger_fn = &sanger_fns.filenames[index];
+                if !sanger_fn.is_resolved() {
+                    Color::Red
+                } else if sanger_fn.is_skipped() {
+                    Color::Blue
+                } else if has_issue {
+                    Color::Magenta
+                } else {
+                    Color::Green
+                }
+            }
+            PlateColoring::Quality => match sanger_fns.mean_quality(index) {
+                Some(quality) if quality >= sanger_fns.good_quality => Color::Green,
+                Some(quality) if quality >= sanger_fns.poor_quality => Color::Yellow,
+                Some(_) => Color::Red,
+                None => Color::DarkGray,
+            },
+        }
+    }
+
+    pub fn render(&self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> anyhow::Result<()> {
+        terminal.draw(|frame| self.render_plate(frame, frame.area()))?;
+        Ok(())
+    }
+
+    pub fn render_plate(&self, frame: &mut Frame, area: Rect) {
+        let wells = self.wells();
+        let missing = self.missing_wells(&wells);
+        let grid_width = 2 + 3 * self.format.columns() as u16 + 2;
+        let chunks =
+            Layout::horizontal([Constraint::Length(grid_width), Constraint::Min(20)]).split(area);
+
+        let mut sanger_fns = self.sanger_fns.lock().unwrap();
+        let issues: Vec<bool> = sanger_fns
+            .rename_issues()
+            .iter()
+            .map(|issues| !issues.is_empty())
+            .collect();
+
+        let header = Row::new(
+            std::iter::once(Cell::from(""))
+                .chain((1..=self.format.columns()).map(|c| Cell::from(format!("{:>2}", c)))),
+        )
+        .style(Style::default().add_modifier(Modifier::BOLD));
+        let mut rows = Vec::new();
+        for row in 0..self.format.rows() {
+            let mut cells = vec![Cell::from(((b'A' + row) as char).to_string())];
+            for column in 0..self.format.columns() {
+                let well = Well { row, column };
+                let (symbol, mut style) = match wells.get(&well) {
+                    Some(files) => {
+                        // A well shows the worst of its files
+                        let colors: Vec<Color> = files
+                            .iter()
+                            .map(|&i| self.file_color(&mut sanger_fns, i, issues[i]))
+                            .collect();
+                        let color = [Color::Red, Color::Magenta, Color::Yellow, Color::DarkGray]
+                            .into_iter()
+                            .find(|c| colors.contains(c))
+                            .unwrap_or(colors[0]);
+                        let symbol = if files.len() > 1 {
+                            files.len().min(9).to_string()
+                        } else {
+                            "●".to_string()
+                        };
+                        (symbol, Style::default().fg(color))
+                    }
+                    None if missing.contains(&well) => {
+                        ("○".to_string(), Style::default().fg(Color::Red))
+                    }
+                    None => ("·".to_string(), Style::default().fg(Color::DarkGray)),
+                };
+                if well == self.cursor {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                cells.push(Cell::from(format!(" {}", symbol)).style(style));
+            }
+            rows.push(Row::new(cells));
+        }
+        let widths = std::iter::once(Constraint::Length(1))
+            .chain((0..self.format.columns()).map(|_| Constraint::Length(2)))
+            .collect::<Vec<_>>();
+        let grid = Table::new(rows, widths).header(header).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("{} plate", self.format))
+                .title_alignment(Alignment::Center)
+                .border_style(Style::default().fg(Color::Cyan)),
+        );
+        frame.render_widget(grid, chunks[0]);
+
+        let mut lines = vec![
+            Line::from(format!("Well {}", self.cursor))
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        ];
+        match wells.get(&self.cursor) {
+            None if missing.contains(&self.cursor) => lines.push(
+                Line::from("No file, but later wells are filled")
+                    .style(Style::default().fg(Color::Red)),
+            ),
+            None => lines.push(Line::from("No file")),
+            Some(files) => {
+                if files.len() > 1 {
+                    lines.push(
+                        Line::from(format!("{} files share this well", files.len()))
+                            .style(Style::default().fg(Color::Yellow)),
+                    );
+                }
+                for &i in files {
+                    let quality = sanger_fns.mean_quality(i);
+                    let mixed = sanger_fns.mixed_count(i);
+                    let sanger_fn = &sanger_fns.filenames[i];
+                    let status = match sanger_fn.parse_error() {
+                        Some(err) if !sanger_fn.is_resolved() => {
+                            format!("unparsed: no {}", err.field)
+                        }
+                        _ if sanger_fn.is_skipped() => "already standardized".to_string(),
+                        _ if issues[i] => "new name cannot be used".to_string(),
+                        _ => format!("→ {}", sanger_fn.get_new_name()),
+                    };
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(sanger_fn.show_file_name()));
+                    lines.push(Line::from(format!(
+                        "  template {}, primer {}",
+                        sanger_fn.get_template_name(),
+                        sanger_fn.get_primer_name()
+                    )));
+                    lines.push(Line::from(format!("  {}", status)));
+                    lines.push(Line::from(match (quality, mixed) {
+                        (Some(quality), Some(mixed)) => {
+                            format!("  mean quality {:.1}, {} mixed position(s)", quality, mixed)
+                        }
+                        (Some(quality), None) => format!("  mean quality {:.1}", quality),
+                        _ => "  no trace data".to_string(),
+                    }));
+                }
+            }
+        }
+
+        let without_well = sanger_fns.filenames.len() - wells.values().map(Vec::len).sum::<usize>();
+        lines.push(Line::from(""));
+        lines.push(Line::from(format!(
+            "{} well(s) filled, {} missing, {} file(s) without a well",
+            wells.len(),
+            missing.len(),
+            without_well
+        )));
+        lines.push(Line::from(match self.coloring {
+            PlateColoring::Status => vec![
+                Span::styled("● named ", Style::default().fg(Color::Green)),
+                Span::styled("● unparsed ", Style::default().fg(Color::Red)),
+                Span::styled("● name issue ", Style::default().fg(Color::Magenta)),
+                Span::styled("● skipped ", Style::default().fg(Color::Blue)),
+                Span::styled("○ missing", Style::default().fg(Color::Red)),
+            ],
+            PlateColoring::Quality => vec![
+                Span::styled(
+                    format!("● ≥{} ", sanger_fns.good_quality),
+                    Style::default().fg(Color::Green),
+                ),
+                Span::styled(
+                    format!("● ≥{} ", sanger_fns.poor_quality),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::styled(
+                    format!("● <{} ", sanger_fns.poor_quality),
+                    Style::default().fg(Color::Red),
+                ),
+                Span::styled("● no trace ", Style::default().fg(Color::DarkGray)),
+                Span::styled("○ missing", Style::default().fg(Color::Red)),
+            ],
+        }));
+        let details = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Arrows to select, c to color by status/quality, z plate size, Esc back")
+                .title_alignment(Alignment::Center)
+                .border_style(Style::default().fg(Color::Cyan)),
+        );
+        frame.render_widget(details, chunks[1]);
+    }
+}
@@ -7,28 +7,39 @@ use ratatui::{
     style::{Color, Modifier, Style},
     widgets::{Block, Borders, Row, Table},
 };
+use sanger_rename::Vendor;
+use sanger_rename::mapping::NameMappings;
 use std::io::Stdout;
 use std::sync::Mutex;
 use std::{collections::HashMap, rc::Rc};
 
-use super::common::{SangerFilenames, Stage, StageTransition};
+use super::common::{SangerFilenames, Stage, StageTransition, fitted_widths};
 
 pub struct PrimerRenameStage {
     pub sanger_fns: Rc<Mutex<SangerFilenames>>,
     pub rename_map: HashMap<String, Option<String>>,
+    /// First file with each primer name as it was before renaming
+    file_index: HashMap<String, usize>,
+    /// Rows of files without a primer name, each edited on its own
+    unparsed: HashMap<String, usize>,
     pub highlighted: usize,
     pub editing: bool,
     pub current_input: String,
+    /// Outcome of saving the renames for future batches
+    pub notice: Option<String>,
 }
 
 impl PrimerRenameStage {
     pub fn init() -> Self {
         Self {
             rename_map: HashMap::new(),
+            file_index: HashMap::new(),
+            unparsed: HashMap::new(),
             sanger_fns: Rc::new(Mutex::new(SangerFilenames::new())),
             highlighted: 0,
             editing: false,
             current_input: String::new(),
+            notice: None,
         }
     }
     pub fn from_sanger_fns(sanger_fns: Rc<Mutex<SangerFilenames>>) -> Self {
@@ -39,15 +50,62 @@ impl PrimerRenameStage {
     }
     pub fn fill_names(&mut self) {
         let sanger_fns = self.sanger_fns.lock().unwrap();
-        for sanger_fn in sanger_fns.filenames.iter() {
+        for (index, sanger_fn) in sanger_fns.filenames.iter().enumerate() {
             let primer_name = sanger_fn.get_primer_name();
-            self.rename_map.insert(primer_name.clone(), None);
+            // Files whose name did not parse all have an empty primer name,
+            // so each gets its own row instead of sharing one
+            let key = if primer_name.is_empty() {
+                let key = format!("(unparsed) {}", sanger_fn.show_file_name());
+                self.unparsed.insert(key.clone(), index);
+                key
+            } else {
+                primer_name
+            };
+            self.file_index.entry(key.clone()).or_insert(index);
+            self.rename_map.insert(key, None);
         }
     }
+    /// Whether the field picker applies, which it only does to Genewiz names
+    fn picks_fields(&self) -> bool {
+        self.sanger_fns
+            .lock()
+            .unwrap()
+            .filenames
+            .first()
+            .is_some_and(|f| *f.get_vendor() == Vendor::Genewiz)
+    }
+    /// Index of the first file with the highlighted primer name, also after it was renamed
+    pub fn highlighted_file(&self) -> Option<usize> {
+        let primer_name = self.rename_map.keys().nth(self.highlighted)?;
+        self.file_index.get(primer_name).copied()
+    }
     pub fn set_rename(&mut self, primer_name: String, new_name: Option<String>) {
         self.rename_map.insert(primer_name, new_name);
     }
 
+    /// Save the renames set here so that future batches get them automatically
+    ///
+    /// Names given to files without a primer name are not remembered.
+    fn remember_renames(&mut self) {
+        let renames: HashMap<String, Option<String>> = self
+            .rename_map
+            .iter()
+            .filter(|(name, _)| !self.unparsed.contains_key(*name))
+            .map(|(name, new_name)| (name.clone(), new_name.clone()))
+            .collect();
+        self.notice = Some(
+            match NameMappings::remember(&renames, |mappings| &mut mappings.primers) {
+                Ok(Some((count, path))) => format!(
+                    "Remembered {} primer rename(s) in {}",
+                    count,
+                    path.display()
+                ),
+                Ok(None) => "No renames to remember".to_string(),
+                Err(err) => format!("Could not save the renames: {}", err),
+            },
+        );
+    }
+
     pub fn handle_key(&mut self, key: KeyEvent) -> StageTransition {
         if key.kind != KeyEventKind::Press {
             return StageTransition::Stay;
@@ -64,14 +122,21 @@ impl PrimerRenameStage {
                         } else {
                             Some(self.current_input.clone())
                         };
+                        if let (Some(&index), Some(new_name)) =
+                            (self.unparsed.get(primer_name), &new_name)
+                        {
+                            self.sanger_fns.lock().unwrap().filenames[index]
+                                .set_primer_name(new_name)
+                                .unwrap();
+                        }
                         self.set_rename(primer_name.clone(), new_name);
                     }
                     for sanger_fn in self.sanger_fns.lock().unwrap().filenames.iter_mut() {
                         let old_primer_name = sanger_fn.get_primer_name();
-                        if let Some(new_name) = self.rename_map.get(&old_primer_name) {
-                            if let Some(new_name_str) = new_name {
-                                sanger_fn.set_primer_name(new_name_str).unwrap();
-                            }
+                        if let Some(new_name) = self.rename_map.get(&old_primer_name)
+                            && let Some(new_name_str) = new_name
+                        {
+                            sanger_fn.set_primer_name(new_name_str).unwrap();
                         }
                     }
                     self.editing = false;
@@ -111,14 +176,25 @@ impl PrimerRenameStage {
                     self.editing = true;
                     // Pre-fill with existing name if any
                     let primer_names: Vec<String> = self.rename_map.keys().cloned().collect();
-                    if let Some(primer_name) = primer_names.get(self.highlighted) {
-                        if let Some(existing_name) = &self.rename_map[primer_name] {
-                            self.current_input = existing_name.clone();
-                        }
+                    if let Some(primer_name) = primer_names.get(self.highlighted)
+                        && let Some(existing_name) = &self.rename_map[primer_name]
+                    {
+                        self.current_input = existing_name.clone();
                     }
                     StageTransition::Stay
                 }
                 KeyCode::Esc | KeyCode::Char('q') => StageTransition::Quit,
+                KeyCode::Char('v') if self.highlighted_file().is_some() => {
+                    StageTransition::Next(Stage::TraceView)
+                }
+                KeyCode::Char('w') => StageTransition::Next(Stage::PlateMap),
+                KeyCode::Char('m') => {
+                    self.remember_renames();
+                    StageTransition::Stay
+                }
+                KeyCode::Char('f') if self.picks_fields() => {
+                    StageTransition::Next(Stage::FieldPicker)
+                }
                 KeyCode::Tab | KeyCode::Char('n') => StageTransition::Next(Stage::TemplateRename),
                 KeyCode::BackTab | KeyCode::Char('p') => {
                     StageTransition::Previous(Stage::VendorSelection)
@@ -129,6 +205,7 @@ impl PrimerRenameStage {
     }
     pub fn render(&self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> anyhow::Result<()> {
         let primer_names: Vec<String> = self.rename_map.keys().cloned().collect();
+        let picks_fields = self.picks_fields();
 
         terminal.draw(|f| {
             let chunks = Layout::horizontal([
@@ -138,7 +215,7 @@ impl PrimerRenameStage {
             .split(f.area());
 
             // Left panel: Primer names with rename inputs
-            let left_rows = primer_names
+            let left_cells = primer_names
                 .iter()
                 .enumerate()
                 .map(|(i, name)| {
@@ -149,30 +226,32 @@ impl PrimerRenameStage {
                     } else {
                         new_name.map_or("<not set>".to_string(), |n| n.clone())
                     };
-                    let row_content = [name.clone(), "-->".to_string(), current_input_display];
-                    
-                    Row::new(row_content).style(if is_highlighted {
-                        Style::default()
-                            .bg(Color::DarkGray)
-                            .fg(Color::Yellow)
-                            .add_modifier(Modifier::BOLD)
-                    } else {
-                        Style::default()
-                    })
+                    [name.clone(), "-->".to_string(), current_input_display]
                 })
                 .collect::<Vec<_>>();
-
-            let left_table_width = [
-                Constraint::Percentage(45),
-                Constraint::Percentage(10),
-                Constraint::Percentage(45),
-            ];
+            let left_table_width = fitted_widths(&left_cells);
+            let left_rows = left_cells.into_iter().enumerate().map(|(i, row_content)| {
+                Row::new(row_content).style(if i == self.highlighted {
+                    Style::default()
+                        .bg(Color::DarkGray)
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                })
+            });
 
             let left_block = Block::default()
                 .borders(Borders::ALL)
                 .style(Style::default().fg(Color::Cyan))
-                .title("Primer Names (Enter to edit, Tab to continue)")
-                .title_alignment(Alignment::Center);
+                .title(format!(
+                    "Primer Names (Enter to edit, v to view trace, w for plate map, {}Tab to continue)",
+                    if picks_fields { "f to pick fields, " } else { "" }
+                ))
+                .title_alignment(Alignment::Center)
+                .title_bottom(self.notice.clone().unwrap_or_else(|| {
+                    "m to remember these renames for future batches".to_string()
+                }));
             let left_header = Row::new(["Primer Name", "-->", "New Name"])
                 .style(Style::default().add_modifier(Modifier::BOLD));
             let primer_rename_view = Table::new(left_rows, left_table_width)
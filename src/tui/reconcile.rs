@@ -0,0 +1,137 @@
+use crate::tui::{App, SangerFilenames};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+use sanger_rename::order::Order;
+use sanger_rename::reconcile::{self, DeliveredFile, Discrepancy, Reconciliation};
+use std::{io::Stdout, rc::Rc, sync::Mutex};
+
+use super::common::{Stage, StageTransition};
+
+/// The delivery checked against the submitted order, before renaming
+pub struct ReconcileStage {
+    pub sanger_fns: Rc<Mutex<SangerFilenames>>,
+    pub order: Option<Rc<Order>>,
+    pub result: Option<Reconciliation>,
+    /// Set when the order went to another vendor than the batch's
+    pub vendor_warning: Option<String>,
+    /// First discrepancy shown
+    pub scroll: usize,
+}
+
+impl ReconcileStage {
+    pub fn init() -> Self {
+        Self {
+            sanger_fns: Rc::new(Mutex::new(SangerFilenames::new())),
+            order: None,
+            result: None,
+            vendor_warning: None,
+            scroll: 0,
+        }
+    }
+    pub fn from_sanger_fns(sanger_fns: Rc<Mutex<SangerFilenames>>, order: Rc<Order>) -> Self {
+        let (files, vendor_warning) = {
+            let sanger_fns = sanger_fns.lock().unwrap();
+            let files: Vec<DeliveredFile> = sanger_fns
+                .filenames
+                .iter()
+                .map(DeliveredFile::from_sanger_filename)
+                .collect();
+            let vendor_warning = sanger_fns
+                .filenames
+                .first()
+                .and_then(|f| reconcile::vendor_warning(&order, *f.get_vendor()));
+            (files, vendor_warning)
+        };
+        let result = reconcile::reconcile(&order, &files);
+        Self {
+            sanger_fns,
+            order: Some(order),
+            result: Some(result),
+            vendor_warning,
+            scroll: 0,
+        }
+    }
+    pub fn handle_key(&mut self, key: KeyEvent) -> StageTransition {
+        if key.kind != KeyEventKind::Press {
+            return StageTransition::Stay;
+        }
+        let count = self
+            .result
+            .as_ref()
+            .map_or(0, |result| result.discrepancies.len());
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => StageTransition::Quit,
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.scroll = self.scroll.saturating_sub(1);
+                StageTransition::Stay
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.scroll = (self.scroll + 1).min(count.saturating_sub(1));
+                StageTransition::Stay
+            }
+            KeyCode::Enter | KeyCode::Char('n') | KeyCode::Tab => {
+                StageTransition::Next(Stage::ConfirmRename)
+            }
+            KeyCode::Char('p') | KeyCode::BackTab => {
+                StageTransition::Previous(Stage::DateSelection)
+            }
+            _ => StageTransition::Stay,
+        }
+    }
+
+    pub fn render(&self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> anyhow::Result<()> {
+        terminal.draw(|frame| {
+            let chunks =
+                Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .split(frame.area());
+            self.render_report(frame, chunks[0]);
+            App::render_rename_preview_table(frame, chunks[1], &self.sanger_fns);
+        })?;
+        Ok(())
+    }
+
+    pub fn render_report(&self, frame: &mut Frame, area: Rect) {
+        let mut lines = Vec::new();
+        if let (Some(order), Some(result)) = (&self.order, &self.result) {
+            lines.push(Line::from(format!(
+                "Order from {} on {}, {}",
+                order.vendor, order.date, order.plate
+            )));
+            if let Some(warning) = &self.vendor_warning {
+                lines.push(Line::from(warning.as_str()).style(Style::default().fg(Color::Yellow)));
+            }
+            lines.push(Line::from(result.summary()));
+            lines.push(Line::from(""));
+            if result.is_clean() {
+                lines.push(
+                    Line::from("Every ordered sample was delivered as ordered.")
+                        .style(Style::default().fg(Color::Green)),
+                );
+            }
+            for discrepancy in result.discrepancies.iter().skip(self.scroll) {
+                let color = match discrepancy {
+                    Discrepancy::Missing { .. } => Color::Red,
+                    Discrepancy::Duplicate { .. } => Color::Yellow,
+                    Discrepancy::Extra { .. } => Color::Blue,
+                    Discrepancy::Moved { .. } | Discrepancy::Renamed { .. } => Color::Magenta,
+                };
+                lines.push(Line::from(discrepancy.to_string()).style(Style::default().fg(color)));
+            }
+        }
+        let report = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .title("Order Check (↑/↓ to scroll, Enter to continue, p to go back)")
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        );
+        frame.render_widget(report, area);
+    }
+}
@@ -0,0 +1,367 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Paragraph,
+        canvas::{Canvas, Line as CanvasLine},
+    },
+};
+use sanger_rename::abif::Trace;
+use sanger_rename::chromatogram::{self, ImageFormat, Region};
+use std::path::Path;
+use std::{cell::Cell, io::Stdout, rc::Rc, sync::Mutex};
+
+use super::common::{SangerFilenames, Stage, StageTransition};
+
+/// Terminal columns per base call at the default zoom
+const DEFAULT_CELLS_PER_BASE: usize = 3;
+const MAX_CELLS_PER_BASE: usize = 8;
+/// Bases moved by PageUp/PageDown
+const PAGE: usize = 20;
+/// Folder beside the traces that exported images go into
+pub const EXPORT_DIR: &str = "chromatograms";
+
+/// Conventional chromatogram color of a base
+fn base_color(base: u8) -> Color {
+    match base.to_ascii_uppercase() {
+        b'A' => Color::Green,
+        b'C' => Color::Blue,
+        b'G' => Color::Yellow,
+        b'T' => Color::Red,
+        _ => Color::DarkGray,
+    }
+}
+
+fn quality_color(quality: u8) -> Color {
+    match quality {
+        0..20 => Color::Red,
+        20..30 => Color::Yellow,
+        _ => Color::Green,
+    }
+}
+
+/// Chromatogram of one trace of the batch at a time, scrolled by base position
+pub struct TraceViewStage {
+    pub sanger_fns: Rc<Mutex<SangerFilenames>>,
+    /// Stage to return to when leaving the viewer
+    pub origin: Stage,
+    /// Index of the shown file in the batch
+    pub index: usize,
+    /// First base call in view
+    pub position: usize,
+    pub cells_per_base: usize,
+    trace: Option<Result<Trace, String>>,
+    /// Bases that fit on screen at the last render
+    visible: Cell<usize>,
+    /// Outcome of the last image export
+    pub status: Option<String>,
+}
+
+impl TraceViewStage {
+    pub fn init() -> Self {
+        Self {
+            sanger_fns: Rc::new(Mutex::new(SangerFilenames::new())),
+            origin: Stage::PrimerRename,
+            index: 0,
+            position: 0,
+            cells_per_base: DEFAULT_CELLS_PER_BASE,
+            trace: None,
+            visible: Cell::new(PAGE),
+            status: None,
+        }
+    }
+    pub fn from_sanger_fns(
+        sanger_fns: Rc<Mutex<SangerFilenames>>,
+        origin: Stage,
+        index: usize,
+    ) -> Self {
+        let mut stage = Self::init();
+        stage.sanger_fns = sanger_fns;
+        stage.origin = origin;
+        stage.show(index);
+        stage
+    }
+
+    /// Load the file at `index` and scroll back to its first base
+    fn show(&mut self, index: usize) {
+        let path = self
+            .sanger_fns
+            .lock()
+            .unwrap()
+            .filenames
+            .get(index)
+            .map(|f| f.get_full_path());
+        self.index = index;
+        self.position = 0;
+        self.status = None;
+        self.trace =
+            path.map(|path| Trace::from_file(Path::new(&path)).map_err(|err| err.to_string()));
+    }
+
+    fn base_count(&self) -> usize {
+        match &self.trace {
+            Some(Ok(trace)) => trace.bases.len().min(trace.peak_locations.len()),
+            _ => 0,
+        }
+    }
+
+    fn scroll_to(&mut self, position: usize) {
+        self.position = position.min(self.base_count().saturating_sub(1));
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> StageTransition {
+        if key.kind != KeyEventKind::Press {
+            return StageTransition::Stay;
+        }
+        let file_count = self.sanger_fns.lock().unwrap().filenames.len();
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('v') => {
+                StageTransition::Previous(self.origin)
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.scroll_to(self.position.saturating_sub(1));
+                StageTransition::Stay
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.scroll_to(self.position + 1);
+                StageTransition::Stay
+            }
+            KeyCode::PageUp => {
+                self.scroll_to(self.position.saturating_sub(PAGE));
+                StageTransition::Stay
+            }
+            KeyCode::PageDown => {
+                self.scroll_to(self.position + PAGE);
+                StageTransition::Stay
+            }
+            KeyCode::Home => {
+                self.scroll_to(0);
+                StageTransition::Stay
+            }
+            KeyCode::End => {
+                self.scroll_to(usize::MAX);
+                StageTransition::Stay
+            }
+            KeyCode::Up | KeyCode::Char('k') if self.index > 0 => {
+                self.show(self.index - 1);
+                StageTransition::Stay
+            }
+            KeyCode::Down | KeyCode::Char('j') if self.index + 1 < file_count => {
+                self.show(self.index + 1);
+                StageTransition::Stay
+            }
+            KeyCode::Char('+') => {
+                self.cells_per_base = (self.cells_per_base + 1).min(MAX_CELLS_PER_BASE);
+                StageTransition::Stay
+            }
+            KeyCode::Char('e') => {
+                self.status = Some(match self.export_visible() {
+                    Ok(paths) => format!("Saved {}", paths.join(", ")),
+                    Err(err) => format!("Export failed: {}", err),
+                });
+                StageTransition::Stay
+            }
+            KeyCode::Char('-') => {
+                self.cells_per_base = self.cells_per_base.saturating_sub(1).max(1);
+                StageTransition::Stay
+            }
+            _ => StageTransition::Stay,
+        }
+    }
+
+    /// Write the bases in view as SVG and PNG into [`EXPORT_DIR`] beside the
+    /// trace, named with its standardized name
+    fn export_visible(&self) -> anyhow::Result<Vec<String>> {
+        let Some(Ok(trace)) = &self.trace else {
+            anyhow::bail!("no trace to export");
+        };
+        let (path, name) = {
+            let sanger_fns = self.sanger_fns.lock().unwrap();
+            let sanger_fn = &sanger_fns.filenames[self.index];
+            (sanger_fn.get_full_path(), sanger_fn.get_new_name())
+        };
+        let dir = Path::new(&path)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(EXPORT_DIR);
+        std::fs::create_dir_all(&dir)?;
+        let region = Region {
+            start: self.position,
+            end: (self.position + self.visible.get()).min(self.base_count()),
+        };
+        let written = chromatogram::export(
+            trace,
+            &name,
+            Some(region),
+            None,
+            &[ImageFormat::Svg, ImageFormat::Png],
+            &dir,
+        )?;
+        Ok(written
+            .iter()
+            .map(|p| {
+                Path::new(EXPORT_DIR)
+                    .join(p.file_name().unwrap_or_default())
+                    .display()
+                    .to_string()
+            })
+            .collect())
+    }
+
+    pub fn render(&self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> anyhow::Result<()> {
+        terminal.draw(|frame| self.render_trace(frame, frame.area()))?;
+        Ok(())
+    }
+
+    pub fn render_trace(&self, frame: &mut Frame, area: Rect) {
+        let name = self
+            .sanger_fns
+            .lock()
+            .unwrap()
+            .filenames
+            .get(self.index)
+            .map(|f| f.show_file_name())
+            .unwrap_or_default();
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(
+                "{} (←/→ scroll, ↑/↓ file, +/- zoom, e export, Esc back)",
+                name
+            ))
+            .title_alignment(Alignment::Center)
+            .border_style(Style::default().fg(Color::Cyan))
+            .title_bottom(self.status.clone().unwrap_or_default());
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let trace = match &self.trace {
+            Some(Ok(trace)) if self.base_count() > 0 => trace,
+            Some(Err(err)) => {
+                frame.render_widget(
+                    Paragraph::new(format!("Cannot show trace: {}", err))
+                        .style(Style::default().fg(Color::Red)),
+                    inner,
+                );
+                return;
+            }
+            _ => {
+                frame.render_widget(Paragraph::new("No base calls in this trace"), inner);
+                return;
+            }
+        };
+
+        let chunks = Layout::vertical([
+            Constraint::Min(3),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+        let cells = self.cells_per_base;
+        let visible = (inner.width as usize / cells).max(1);
+        self.visible.set(visible);
+        let start = self.position;
+        let end = (start + visible).min(self.base_count());
+
+        frame.render_widget(self.chromatogram(trace, start, visible), chunks[0]);
+        frame.render_widget(Paragraph::new(ruler(start, end, cells)), chunks[1]);
+
+        let bases = (start..end)
+            .map(|i| {
+                let base = trace.bases[i];
+                Span::styled(
+                    format!("{:^cells$}", base as char),
+                    Style::default()
+                        .fg(base_color(base))
+                        .add_modifier(Modifier::BOLD),
+                )
+            })
+            .collect::<Vec<_>>();
+        frame.render_widget(Paragraph::new(Line::from(bases)), chunks[2]);
+
+        let bars = (start..end)
+            .map(|i| match trace.qualities.get(i) {
+                Some(&quality) => {
+                    let bar =
+                        ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'][(quality as usize / 8).min(7)];
+                    Span::styled(
+                        format!("{:^cells$}", bar),
+                        Style::default().fg(quality_color(quality)),
+                    )
+                }
+                None => Span::raw(" ".repeat(cells)),
+            })
+            .collect::<Vec<_>>();
+        frame.render_widget(Paragraph::new(Line::from(bars)), chunks[3]);
+    }
+
+    /// The four channels from `start` on, with base call `i` drawn at x = `i`
+    fn chromatogram<'a>(
+        &self,
+        trace: &'a Trace,
+        start: usize,
+        visible: usize,
+    ) -> Canvas<'a, impl Fn(&mut ratatui::widgets::canvas::Context) + 'a> {
+        let peaks = &trace.peak_locations[..self.base_count()];
+        // Scans are mapped to base positions linearly between neighbouring peaks
+        let first = start.saturating_sub(1);
+        let last = (start + visible + 1).min(peaks.len() - 1);
+        let segments: Vec<(f64, usize)> = (first..last)
+            .flat_map(|i| {
+                let (from, to) = (peaks[i] as usize, peaks[i + 1] as usize);
+                let span = to.saturating_sub(from).max(1) as f64;
+                (from..to).map(move |scan| (i as f64 + (scan - from) as f64 / span, scan))
+            })
+            .collect();
+        let top = trace
+            .channels
+            .iter()
+            .flat_map(|channel| segments.iter().filter_map(|&(_, scan)| channel.get(scan)))
+            .copied()
+            .max()
+            .unwrap_or(1)
+            .max(1) as f64;
+        let colors = trace.base_order.map(base_color);
+        let channels = &trace.channels;
+        Canvas::default()
+            .marker(ratatui::symbols::Marker::Braille)
+            .x_bounds([start as f64 - 0.5, (start + visible) as f64 - 0.5])
+            .y_bounds([0.0, top])
+            .paint(move |ctx| {
+                for (channel, &color) in channels.iter().zip(&colors) {
+                    for pair in segments.windows(2) {
+                        let (x1, scan1) = pair[0];
+                        let (x2, scan2) = pair[1];
+                        let (Some(&y1), Some(&y2)) = (channel.get(scan1), channel.get(scan2))
+                        else {
+                            continue;
+                        };
+                        ctx.draw(&CanvasLine {
+                            x1,
+                            y1: y1.max(0) as f64,
+                            x2,
+                            y2: y2.max(0) as f64,
+                            color,
+                        });
+                    }
+                }
+            })
+    }
+}
+
+/// Base numbers (1-based) above every tenth base in view
+pub fn ruler(start: usize, end: usize, cells: usize) -> String {
+    let mut ruler = String::new();
+    for i in start..end {
+        let column = (i - start) * cells;
+        if (i + 1) % 10 == 0 && ruler.len() <= column {
+            ruler.push_str(&" ".repeat(column - ruler.len()));
+            ruler.push_str(&(i + 1).to_string());
+        }
+    }
+    ruler
+}
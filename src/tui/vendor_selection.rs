@@ -84,7 +84,10 @@ impl VendorSelectionStage {
             Constraint::Percentage(80),
             Constraint::Percentage(10),
         ]);
-        let horizontal = Layout::horizontal([Constraint::Percentage(33); 3]).spacing(1);
+        let vendor_count = vds.len() as u32;
+        let horizontal =
+            Layout::horizontal((0..vendor_count).map(|_| Constraint::Ratio(1, vendor_count)))
+                .spacing(1);
         let [header_area, main_area, _footer_area] = vertical.areas(terminal.get_frame().area());
         let header_text = format!(
             "Selected: {}",
@@ -95,9 +98,8 @@ impl VendorSelectionStage {
             header_text,
             Style::default().fg(Color::Cyan),
         )]));
-        let [left, middle, right] = horizontal.areas(main_area);
+        let areas = horizontal.split(main_area);
         terminal.draw(|f| {
-            let areas = [left, middle, right];
             for (i, (title, area)) in vds.iter().zip(areas.iter()).enumerate() {
                 let is_highlighted = i == self.get_highlighted();
                 let style = if is_highlighted {
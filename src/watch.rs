@@ -0,0 +1,239 @@
+//! Picking up new deliveries from a folder such as Downloads
+
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::field_rule::FieldRule;
+use crate::sanger_filename::{SangerFilename, Vendor};
+
+/// Extensions of the trace files vendors deliver
+pub const TRACE_EXTENSIONS: [&str; 3] = ["ab1", "abi", "seq"];
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+pub fn is_trace_file(path: &Path) -> bool {
+    has_extension(path, &TRACE_EXTENSIONS)
+}
+
+pub fn is_zip_file(path: &Path) -> bool {
+    has_extension(path, &["zip"])
+}
+
+/// Traces written by [`extract_traces`]
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Extracted {
+    pub traces: Vec<PathBuf>,
+    /// Traces left in the archive because a file of the same name exists,
+    /// from an earlier extraction or another folder of the archive
+    pub clashes: Vec<PathBuf>,
+}
+
+/// Extract the trace files of a ZIP archive into `{dir}/{archive stem}/`
+///
+/// Folders inside the archive are dropped so every trace lands directly in
+/// the new folder, and existing files are never overwritten. Archives without
+/// traces are left alone and give no files.
+pub fn extract_traces(archive: &Path, dir: &Path) -> anyhow::Result<Extracted> {
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(archive)?)?;
+    let target = dir.join(archive.file_stem().unwrap_or_default());
+    let mut extracted = Extracted::default();
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        let Some(name) = entry
+            .enclosed_name()
+            .and_then(|path| path.file_name().map(PathBuf::from))
+        else {
+            continue;
+        };
+        if !entry.is_file() || !is_trace_file(&name) {
+            continue;
+        }
+        std::fs::create_dir_all(&target)?;
+        let path = target.join(name);
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                extracted.clashes.push(path);
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        std::io::copy(&mut entry, &mut file)?;
+        extracted.traces.push(path);
+    }
+    Ok(extracted)
+}
+
+/// Parse a new trace with its detected (or the given) vendor and the saved rule
+///
+/// The saved mappings are left to the caller, to apply after the config has
+/// set the name template and so decided which files are already standardized.
+pub fn prepare(
+    path: &Path,
+    vendor: Option<Vendor>,
+    field_rule: Option<FieldRule>,
+) -> Result<SangerFilename, String> {
+    let name = path.to_string_lossy();
+    let vendor = vendor
+        .or_else(|| Vendor::detect(&name))
+        .ok_or_else(|| "vendor not recognized".to_string())?;
+    let mut sanger_fn = SangerFilename::new(&name, vendor);
+    if vendor == Vendor::Genewiz && field_rule.is_some() {
+        sanger_fn.set_field_rule(field_rule);
+    }
+    if let Some(err) = sanger_fn.parse_error() {
+        return Err(err.to_string());
+    }
+    Ok(sanger_fn)
+}
+
+/// A trace waiting to be confirmed in the TUI
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct QueuedTrace {
+    pub path: PathBuf,
+    pub vendor: String,
+}
+
+/// Traces found by `watch` that are not renamed yet, one JSON record per line
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct WatchQueue {
+    path: PathBuf,
+}
+
+impl WatchQueue {
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// `watch_queue.jsonl` in the user's data directory
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("sanger_rename").join("watch_queue.jsonl"))
+    }
+
+    pub fn open_default() -> anyhow::Result<Self> {
+        Self::default_path()
+            .map(Self::open)
+            .ok_or_else(|| anyhow::anyhow!("No data directory available for the queue"))
+    }
+
+    pub fn push(&self, trace: &QueuedTrace) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(trace)?)?;
+        Ok(())
+    }
+
+    /// Queued traces that still exist under their queued name, oldest first
+    ///
+    /// Renamed or deleted traces are dropped from the queue file.
+    pub fn pending(&self) -> anyhow::Result<Vec<QueuedTrace>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let file = std::fs::File::open(&self.path)?;
+        let mut pending: Vec<QueuedTrace> = Vec::new();
+        let mut dropped = false;
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let trace: QueuedTrace = serde_json::from_str(&line).map_err(|err| {
+                anyhow::anyhow!("{} line {}: {}", self.path.display(), i + 1, err)
+            })?;
+            if trace.path.exists() && !pending.contains(&trace) {
+                pending.push(trace);
+            } else {
+                dropped = true;
+            }
+        }
+        if dropped {
+            let mut content = String::new();
+            for trace in &pending {
+                content.push_str(&serde_json::to_string(trace)?);
+                content.push('\n');
+            }
+            std::fs::write(&self.path, content)?;
+        }
+        Ok(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapping::NameMappings;
+
+    #[test]
+    fn test_extract_traces() {
+        let dir = std::env::temp_dir().join("sanger_rename_watch_zip");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let archive = dir.join("order_34781340.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for name in [
+            "results/K528-1.C1.34781340.B08.ab1",
+            "results/report.pdf",
+            "rerun/K528-1.C1.34781340.B08.ab1",
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(b"trace").unwrap();
+        }
+        zip.finish().unwrap();
+
+        let trace = dir
+            .join("order_34781340")
+            .join("K528-1.C1.34781340.B08.ab1");
+        let extracted = extract_traces(&archive, &dir).unwrap();
+        assert_eq!(extracted.traces, vec![trace.clone()]);
+        assert_eq!(extracted.clashes, vec![trace.clone()]);
+        assert_eq!(std::fs::read(&trace).unwrap(), b"trace");
+
+        // Extracting again leaves the traces already there alone
+        std::fs::write(&trace, b"renamed elsewhere").unwrap();
+        let extracted = extract_traces(&archive, &dir).unwrap();
+        assert!(extracted.traces.is_empty());
+        assert_eq!(std::fs::read(&trace).unwrap(), b"renamed elsewhere");
+    }
+
+    #[test]
+    fn test_prepare_and_queue() {
+        let mappings: NameMappings = toml::from_str("[primers]\nC1 = \"T7\"\n").unwrap();
+        let path = Path::new("fixtures/ruibio/K528-1.C1.34781340.B08.ab1");
+        let mut sanger_fn = prepare(path, None, None).unwrap();
+        assert_eq!(sanger_fn.get_vendor(), &Vendor::Ruibio);
+        mappings.apply(&mut sanger_fn);
+        assert_eq!(sanger_fn.get_primer_name(), "T7");
+        assert!(prepare(Path::new("notes.ab1"), None, None).is_err());
+
+        let dir = std::env::temp_dir().join("sanger_rename_watch_queue");
+        let _ = std::fs::remove_dir_all(&dir);
+        let queue = WatchQueue::open(dir.join("queue.jsonl"));
+        let trace = QueuedTrace {
+            path: path.to_path_buf(),
+            vendor: "Ruibio".to_string(),
+        };
+        let gone = QueuedTrace {
+            path: dir.join("gone.ab1"),
+            vendor: "Ruibio".to_string(),
+        };
+        queue.push(&trace).unwrap();
+        queue.push(&gone).unwrap();
+        queue.push(&trace).unwrap();
+        assert_eq!(queue.pending().unwrap(), vec![trace]);
+        let content = std::fs::read_to_string(dir.join("queue.jsonl")).unwrap();
+        assert_eq!(content.lines().count(), 1);
+    }
+}